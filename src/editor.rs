@@ -1,18 +1,95 @@
-use crossterm::event::{Event, KeyEvent, KeyEventKind, read};
+use crossterm::event::{
+    Event, KeyCode, KeyEvent, KeyEventKind, MouseButton, MouseEvent, MouseEventKind, poll, read,
+};
+use std::cmp::min;
+use std::collections::VecDeque;
+use std::io::IsTerminal;
 use std::panic::{set_hook, take_hook};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
+mod chord;
+mod commandbar;
+mod commandpalette;
+mod companion;
+mod config;
+mod datetime;
+mod documentstatus;
 mod editorcommand;
+mod messagebar;
+mod pipe;
 mod position;
+mod quickfix;
+mod recent;
+mod statusbar;
 mod terminal;
+mod text;
+mod theme;
 mod view;
+use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
 use terminal::{Size, Terminal};
 
-use editorcommand::EditorCommand;
-use view::View;
+use chord::{ChordOutcome, ChordState};
+use commandbar::CommandBar;
+use config::Config;
+use editorcommand::{Direction, EditorCommand};
+use messagebar::MessageBar;
+use position::Position;
+use recent::RecentFiles;
+use statusbar::StatusBar;
+use view::{SearchOptions, View};
+
+// how long to wait for input before running idle tasks
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+// two clicks on the same spot within this time make a double click
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(400);
+// rows at the bottom of the terminal taken by the status and message bars
+const RESERVED_ROWS: usize = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PromptType {
+    Search,
+    OpenRecent,
+    AppendFile,
+    PipeCommand,
+    Grep,
+    AlignOn,
+    GotoPercent,
+    ConfirmPaste,
+    CommandPalette,
+    None,
+}
 
 pub struct Editor {
     should_quit: bool,
+    // the last command was a quit refused because of unsaved changes
+    quit_warned: bool,
+    // waiting for the target char of a jump
+    pending_jump: bool,
     view: View,
+    // the other open files, in the order `NextFile` cycles through them
+    background_views: VecDeque<View>,
+    status_bar: StatusBar,
+    message_bar: MessageBar,
+    command_bar: CommandBar,
+    prompt_type: PromptType,
+    search_options: SearchOptions,
+    terminal_size: Size,
+    config: Config,
+    last_input: Instant,
+    recent_files: RecentFiles,
+    // index of the highlighted entry in the filtered command palette
+    palette_selection: usize,
+    // where and when the last click happened, to spot double clicks
+    last_click: Option<(Position, Instant)>,
+    // set by the signal handler when we are asked to exit
+    terminate_requested: Arc<AtomicBool>,
+    // keys typed so far towards a multi-key command
+    chords: ChordState,
+    // a large paste waiting for the user to confirm it
+    pending_paste: Option<String>,
 }
 
 impl Editor {
@@ -23,27 +100,84 @@ impl Editor {
             current_hook(panic_info);
         }));
 
+        let file_names = Self::parse_args(std::env::args());
+        let mut editor = Self::new_without_hook(Config::load(), RecentFiles::load());
+        // piped input has to be read before stdin is put into raw mode
+        let read_stdin = Self::should_read_stdin(file_names.first().map(String::as_str));
+        if read_stdin {
+            editor.view.load_from_reader(std::io::stdin().lock())?;
+        }
         Terminal::initialize()?;
-        let size: Size = Terminal::size().unwrap_or_default();
-        let mut view = View::new(size);
 
-        if let Some(file_name) = Self::get_filename() {
-            view.load(&file_name);
+        // a `-` in front stands for stdin, which is already loaded
+        let skip = usize::from(read_stdin && !file_names.is_empty());
+        for (index, file_name) in file_names.iter().skip(skip).enumerate() {
+            if index == 0 && !read_stdin {
+                editor.open(file_name);
+            } else {
+                editor.open_in_background(file_name);
+            }
+        }
+
+        // registered once startup is done, so a signal only ever stops the
+        // main loop, which exits so that `Drop` restores the terminal
+        for signal in [SIGINT, SIGTERM, SIGHUP] {
+            signal_hook::flag::register(signal, Arc::clone(&editor.terminate_requested))?;
         }
-        Ok(Self {
+        Ok(editor)
+    }
+
+    /// An editor for a host application that manages the terminal
+    /// itself: unlike `new` it installs no panic hook or signal handlers,
+    /// leaves the terminal mode alone and opens no files.
+    pub fn new_without_hook(config: Config, recent_files: RecentFiles) -> Self {
+        let size = Terminal::size().unwrap_or_default();
+        let view = View::new(Self::view_size(size), config.clone());
+        Self::with_view(view, size, config, recent_files)
+    }
+
+    fn with_view(view: View, size: Size, config: Config, recent_files: RecentFiles) -> Self {
+        let mut message_bar = MessageBar::default();
+        message_bar.update_message("HELP: Ctrl-F = find | Ctrl-S = save | Ctrl-Q = quit");
+        Self {
             should_quit: false,
+            quit_warned: false,
+            pending_jump: false,
             view,
-        })
+            background_views: VecDeque::new(),
+            status_bar: StatusBar::new(config.theme),
+            message_bar,
+            command_bar: CommandBar::default(),
+            prompt_type: PromptType::None,
+            search_options: SearchOptions::default(),
+            terminal_size: size,
+            config,
+            last_input: Instant::now(),
+            recent_files,
+            palette_selection: 0,
+            last_click: None,
+            terminate_requested: Arc::new(AtomicBool::new(false)),
+            chords: ChordState::default(),
+            pending_paste: None,
+        }
+    }
+
+    fn view_size(terminal_size: Size) -> Size {
+        Size {
+            width: terminal_size.width,
+            height: terminal_size.text_height(RESERVED_ROWS),
+        }
     }
 
     pub fn run(&mut self) -> Result<(), std::io::Error> {
         loop {
             self.refresh_screen()?;
-            if self.should_quit {
+            if self.should_quit || self.terminate_requested.load(Ordering::Relaxed) {
                 break;
             }
-            match read() {
-                Ok(event) => self.evaluate_event(event),
+            match Self::next_event() {
+                Ok(Some(event)) => self.evaluate_event(event, Instant::now()),
+                Ok(None) => self.tick(),
                 Err(err) => {
                     #[cfg(debug_assertions)]
                     {
@@ -55,17 +189,61 @@ impl Editor {
         Ok(())
     }
 
+    /// Wait up to `POLL_INTERVAL` for the next event, so that the caller
+    /// gets a chance to run timers while there is no input.
+    fn next_event() -> Result<Option<Event>, std::io::Error> {
+        if poll(POLL_INTERVAL)? {
+            read().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn tick(&mut self) {
+        self.chords.expire(Instant::now());
+        self.message_bar.tick(Instant::now());
+        self.auto_save_if_idle();
+    }
+
     fn refresh_screen(&mut self) -> Result<(), std::io::Error> {
         Terminal::begin_frame()?;
         self.view.render()?;
-        Terminal::end_frame(self.view.get_cursor_position())
+        let bottom_row = self.terminal_size.last_row();
+        if self.terminal_size.height >= RESERVED_ROWS {
+            self.status_bar.update_status(self.view.get_status());
+            self.status_bar
+                .render(bottom_row - 1, self.terminal_size.width)?;
+        }
+        let show_cursor = is_cursor_visible(self.last_input.elapsed(), self.config.cursor_blink);
+        if self.prompt_type == PromptType::None {
+            self.message_bar
+                .render(bottom_row, self.terminal_size.width)?;
+            Terminal::end_frame(self.view.get_cursor_position(), show_cursor)
+        } else {
+            self.command_bar
+                .render(bottom_row, self.terminal_size.width)?;
+            Terminal::end_frame(
+                Position {
+                    col: min(self.command_bar.cursor_col(), self.terminal_size.last_col()),
+                    row: bottom_row,
+                },
+                show_cursor,
+            )
+        }
     }
 
+    /// Handle `event`, received at `now`. This only updates the state the
+    /// next frame is drawn from, so scripted events can drive the editor
+    /// without a terminal.
     #[allow(clippy::needless_pass_by_value)]
-    fn evaluate_event(&mut self, event: Event) {
+    fn evaluate_event(&mut self, event: Event, now: Instant) {
+        self.last_input = now;
         let should_process = match &event {
             Event::Key(KeyEvent { kind, .. }) => kind == &KeyEventKind::Press,
-            Event::Resize(..) => true,
+            Event::Resize(..) | Event::Paste(..) => true,
+            Event::Mouse(MouseEvent { kind, .. }) => {
+                kind == &MouseEventKind::Down(MouseButton::Left)
+            }
             _ => false,
         };
 
@@ -73,14 +251,51 @@ impl Editor {
             return;
         }
 
-        match EditorCommand::try_from(event) {
-            Ok(command) => {
-                if matches!(command, EditorCommand::Quit) {
-                    self.should_quit = true;
-                } else {
-                    self.view.handle_command(command);
+        // a resize or paste leaves the jump waiting for its key
+        if self.pending_jump && matches!(event, Event::Key(_)) {
+            self.pending_jump = false;
+            // any key other than a plain char cancels the jump
+            if let Event::Key(KeyEvent {
+                code: KeyCode::Char(ch),
+                ..
+            }) = event
+            {
+                self.view.handle_command(EditorCommand::JumpToChar(ch));
+            }
+            return;
+        }
+
+        if let Event::Key(KeyEvent {
+            code, modifiers, ..
+        }) = event
+            && self.prompt_type == PromptType::None
+        {
+            match self.chords.feed((code, modifiers), now) {
+                ChordOutcome::Pending | ChordOutcome::Cancelled => return,
+                ChordOutcome::Complete(command) => {
+                    self.process_command(command);
+                    return;
                 }
+                ChordOutcome::NotAChord => {}
             }
+        }
+
+        match EditorCommand::try_from(event) {
+            Ok(EditorCommand::Resize(size)) => self.resize(size),
+            Ok(command) => match self.prompt_type {
+                PromptType::Search => self.process_command_during_search(command),
+                PromptType::CommandPalette => self.process_command_during_palette(command),
+                PromptType::OpenRecent
+                | PromptType::AppendFile
+                | PromptType::PipeCommand
+                | PromptType::Grep
+                | PromptType::AlignOn
+                | PromptType::GotoPercent
+                | PromptType::ConfirmPaste => {
+                    self.process_command_during_prompt(command);
+                }
+                PromptType::None => self.process_command(command),
+            },
             Err(err) => {
                 #[cfg(debug_assertions)]
                 eprintln!("Ignoring input: {err}");
@@ -88,11 +303,601 @@ impl Editor {
         }
     }
 
-    fn get_filename() -> Option<String> {
-        let mut args = std::env::args();
-        let _program = args.next();
-        args.next()
+    fn process_command(&mut self, command: EditorCommand) {
+        if command != EditorCommand::Quit {
+            self.quit_warned = false;
+        }
+        match command {
+            EditorCommand::Quit => self.quit(false),
+            EditorCommand::NextFile => self.next_file(),
+            EditorCommand::InsertDate => {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |since| since.as_secs());
+                let date = datetime::format_timestamp(now, &self.config.date_format);
+                self.view.insert_str(&date);
+            }
+            EditorCommand::ForceQuit => self.quit(true),
+            EditorCommand::Paste(text)
+                if paste_needs_confirming(&text, self.config.paste_confirm_lines) =>
+            {
+                self.pending_paste = Some(text);
+                self.set_prompt(PromptType::ConfirmPaste);
+            }
+            EditorCommand::StartJump => self.pending_jump = true,
+            EditorCommand::Save => self.save(),
+            EditorCommand::Search => self.enter_search(),
+            EditorCommand::SearchNext | EditorCommand::SearchPrevious => {
+                if !self
+                    .view
+                    .repeat_search(command == EditorCommand::SearchNext)
+                {
+                    self.message_bar.update_message("No matches.");
+                }
+            }
+            EditorCommand::OpenRecent => self.enter_open_recent(),
+            EditorCommand::AppendFile => self.set_prompt(PromptType::AppendFile),
+            EditorCommand::PipeCommand => self.set_prompt(PromptType::PipeCommand),
+            EditorCommand::Grep => self.set_prompt(PromptType::Grep),
+            EditorCommand::SwitchHeaderSource => self.switch_header_source(),
+            EditorCommand::CheckWhitespace => {
+                let report = self.view.check_whitespace();
+                self.message_bar.update_message(&report.to_string());
+            }
+            EditorCommand::InsertNewline if self.view.is_jump_list() => self.open_location(),
+            EditorCommand::AlignOn => self.set_prompt(PromptType::AlignOn),
+            EditorCommand::GotoPercent => self.set_prompt(PromptType::GotoPercent),
+            EditorCommand::CommandPalette => self.set_prompt(PromptType::CommandPalette),
+            EditorCommand::DiffAgainstDisk => self.diff_against_disk(),
+            EditorCommand::GoToDefinition => {
+                if !self.view.go_to_definition() {
+                    self.message_bar
+                        .update_message("No definition found for the word under the cursor.");
+                }
+            }
+            EditorCommand::Click(at) => {
+                // `last_input` is when the click arrived
+                let command = self.classify_click(at, self.last_input);
+                self.view.handle_command(command);
+            }
+            command => self.view.handle_command(command),
+        }
+    }
+
+    fn quit(&mut self, force: bool) {
+        let is_dirty = self.view.is_dirty() || self.background_views.iter().any(View::is_dirty);
+        if can_quit(force, is_dirty, self.quit_warned) {
+            self.should_quit = true;
+            return;
+        }
+        self.quit_warned = true;
+        self.message_bar.update_message(
+            "Unsaved changes. Press Ctrl-Q again or Ctrl-Alt-Q to quit without saving.",
+        );
+    }
+
+    /// Turn a click into a double click if it follows another one on the
+    /// same spot quickly enough.
+    fn classify_click(&mut self, at: Position, now: Instant) -> EditorCommand {
+        let is_double = self.last_click.is_some_and(|(last_at, last_time)| {
+            last_at == at && now.duration_since(last_time) <= DOUBLE_CLICK_INTERVAL
+        });
+        if is_double {
+            // a third click starts over
+            self.last_click = None;
+            EditorCommand::DoubleClick(at)
+        } else {
+            self.last_click = Some((at, now));
+            EditorCommand::Click(at)
+        }
+    }
+
+    #[allow(clippy::needless_pass_by_value)]
+    fn process_command_during_search(&mut self, command: EditorCommand) {
+        match command {
+            EditorCommand::Dismiss => {
+                self.set_prompt(PromptType::None);
+                self.view.dismiss_search();
+            }
+            EditorCommand::InsertNewline => {
+                self.set_prompt(PromptType::None);
+                self.view.exit_search();
+            }
+            EditorCommand::Insert(ch) => {
+                self.command_bar.insert(ch);
+                self.view
+                    .search(self.command_bar.value(), self.search_options);
+            }
+            EditorCommand::Paste(text) => {
+                self.command_bar.insert_str(&text);
+                self.view
+                    .search(self.command_bar.value(), self.search_options);
+            }
+            EditorCommand::DeleteLeft => {
+                self.command_bar.delete_left();
+                self.view
+                    .search(self.command_bar.value(), self.search_options);
+            }
+            EditorCommand::Move(Direction::Down | Direction::Right) => {
+                self.view
+                    .search_next(self.command_bar.value(), self.search_options);
+            }
+            EditorCommand::ToggleMatchCase => {
+                self.search_options.match_case = !self.search_options.match_case;
+                self.update_search_prompt();
+                self.view
+                    .search(self.command_bar.value(), self.search_options);
+            }
+            EditorCommand::ToggleWholeWord => {
+                self.search_options.whole_word = !self.search_options.whole_word;
+                self.update_search_prompt();
+                self.view
+                    .search(self.command_bar.value(), self.search_options);
+            }
+            _ => {}
+        }
+        if self.prompt_type == PromptType::Search {
+            self.update_search_progress();
+        }
+    }
+
+    // "[3/7]" after the query, or "[-/7]" when off the matches
+    fn update_search_progress(&mut self) {
+        let suffix = match self.view.search_progress() {
+            Some(_) if self.command_bar.value().is_empty() => String::new(),
+            Some((Some(current), total)) => format!(" [{current}/{total}]"),
+            Some((None, total)) => format!(" [-/{total}]"),
+            None => String::new(),
+        };
+        self.command_bar.set_suffix(&suffix);
+    }
+
+    #[allow(clippy::needless_pass_by_value)]
+    fn process_command_during_palette(&mut self, command: EditorCommand) {
+        let matches = commandpalette::filter(self.command_bar.value());
+        match command {
+            EditorCommand::Dismiss => self.set_prompt(PromptType::None),
+            EditorCommand::InsertNewline => {
+                self.set_prompt(PromptType::None);
+                if let Some(action) = matches.get(self.palette_selection) {
+                    self.process_command(action.command.clone());
+                }
+            }
+            EditorCommand::Move(Direction::Down) if !matches.is_empty() => {
+                self.palette_selection = (self.palette_selection + 1) % matches.len();
+                self.update_palette_prompt();
+            }
+            EditorCommand::Move(Direction::Up) if !matches.is_empty() => {
+                self.palette_selection =
+                    (self.palette_selection + matches.len() - 1) % matches.len();
+                self.update_palette_prompt();
+            }
+            EditorCommand::Insert(ch) => {
+                self.command_bar.insert(ch);
+                self.palette_selection = 0;
+                self.update_palette_prompt();
+            }
+            EditorCommand::Paste(text) => {
+                self.command_bar.insert_str(&text);
+                self.palette_selection = 0;
+                self.update_palette_prompt();
+            }
+            EditorCommand::DeleteLeft => {
+                self.command_bar.delete_left();
+                self.palette_selection = 0;
+                self.update_palette_prompt();
+            }
+            _ => {}
+        }
+    }
+
+    fn update_palette_prompt(&mut self) {
+        let matches = commandpalette::filter(self.command_bar.value());
+        let prompt = match matches.get(self.palette_selection) {
+            Some(action) => format!(
+                "Command [{}] ({}/{}): ",
+                action.label,
+                self.palette_selection + 1,
+                matches.len()
+            ),
+            None => "Command [no match]: ".to_string(),
+        };
+        self.command_bar.set_prompt(&prompt);
+    }
+
+    /// Edit the value of a plain text prompt, submitting it on Enter.
+    #[allow(clippy::needless_pass_by_value)]
+    fn process_command_during_prompt(&mut self, command: EditorCommand) {
+        match command {
+            EditorCommand::Dismiss => {
+                // a cancelled paste may be large, so don't hold on to it
+                self.pending_paste = None;
+                self.set_prompt(PromptType::None);
+            }
+            EditorCommand::InsertNewline => {
+                let prompt_type = self.prompt_type;
+                let value = self.command_bar.value().to_string();
+                self.set_prompt(PromptType::None);
+                self.submit_prompt(prompt_type, &value);
+            }
+            EditorCommand::Insert(ch) => self.command_bar.insert(ch),
+            EditorCommand::Paste(text) => self.command_bar.insert_str(&text),
+            EditorCommand::DeleteLeft => self.command_bar.delete_left(),
+            _ => {}
+        }
+    }
+
+    fn submit_prompt(&mut self, prompt_type: PromptType, value: &str) {
+        match prompt_type {
+            PromptType::OpenRecent => self.open_recent(value),
+            PromptType::AppendFile => self.append_file(value),
+            PromptType::PipeCommand => self.pipe_through(value),
+            PromptType::Grep => self.grep(value),
+            PromptType::ConfirmPaste => {
+                let text = self.pending_paste.take().unwrap_or_default();
+                if value.trim().eq_ignore_ascii_case("y") {
+                    self.view.paste(&text);
+                }
+            }
+            PromptType::AlignOn => {
+                if let Some(sep) = value.chars().next() {
+                    self.view.align_on(sep);
+                }
+            }
+            PromptType::GotoPercent => match value.trim().trim_end_matches('%').parse() {
+                Ok(percent) => self.view.goto_percent(percent),
+                Err(_) => self
+                    .message_bar
+                    .update_message(&format!("Not a percentage: {value}")),
+            },
+            PromptType::Search | PromptType::CommandPalette | PromptType::None => {}
+        }
+    }
+
+    fn open_recent(&mut self, choice: &str) {
+        let path = choice
+            .parse::<usize>()
+            .ok()
+            .and_then(|number| number.checked_sub(1))
+            .and_then(|index| self.recent_files.get(index))
+            .map(str::to_string);
+        match path {
+            Some(path) => self.open(&path),
+            None => self.message_bar.update_message("No such recent file."),
+        }
+    }
+
+    fn append_file(&mut self, file_name: &str) {
+        let message = match self.view.append_file(file_name) {
+            Ok(()) => format!("Appended {file_name}."),
+            Err(err) => format!("Could not append {file_name}: {err}"),
+        };
+        self.message_bar.update_message(&message);
+    }
+
+    fn pipe_through(&mut self, command: &str) {
+        let message = match self.view.filter_through(|text| pipe::run(command, text)) {
+            Ok(()) => format!("Piped through {command}."),
+            Err(err) => format!("{command} failed: {err}"),
+        };
+        self.message_bar.update_message(&message);
+    }
+
+    // list the locations `command` prints in a buffer of their own,
+    // replacing the previous list if that is the current buffer
+    fn grep(&mut self, command: &str) {
+        let locations = match pipe::run(command, "") {
+            Ok(output) => quickfix::parse(&output),
+            Err(err) => {
+                self.message_bar
+                    .update_message(&format!("{command} failed: {err}"));
+                return;
+            }
+        };
+        if locations.is_empty() {
+            self.message_bar.update_message("No matches.");
+            return;
+        }
+        let text = locations
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("\n");
+        let mut list = View::new(Self::view_size(self.terminal_size), self.config.clone());
+        if let Err(err) = list.load_jump_list(&text) {
+            self.message_bar
+                .update_message(&format!("Could not list matches: {err}"));
+            return;
+        }
+        std::mem::swap(&mut self.view, &mut list);
+        if !list.is_jump_list() {
+            self.background_views.push_back(list);
+        }
+        self.message_bar
+            .update_message(&format!("{} matches, Enter opens one.", locations.len()));
+    }
+
+    // open the location on the cursor line of the jump list
+    fn open_location(&mut self) {
+        let Some(location) = quickfix::Location::parse(&self.view.current_line()) else {
+            return;
+        };
+        if self.switch_to(&location.file) {
+            self.view.goto_line(location.line);
+        }
+    }
+
+    fn switch_header_source(&mut self) {
+        let Some(file_name) = self.view.get_status().file_name else {
+            self.message_bar
+                .update_message("The buffer has no file name.");
+            return;
+        };
+        match companion::companion_path(Path::new(&file_name)) {
+            Some(path) => {
+                self.switch_to(&path.to_string_lossy());
+            }
+            None => self
+                .message_bar
+                .update_message(&format!("No header or source found for {file_name}.")),
+        }
+    }
+
+    // bring `file_name` to the front, opening it unless it is open in the
+    // background already, and returning whether that worked
+    fn switch_to(&mut self, file_name: &str) -> bool {
+        let open = self
+            .background_views
+            .iter()
+            .position(|view| view.get_status().file_name.as_deref() == Some(file_name));
+        let mut view =
+            if let Some(view) = open.and_then(|index| self.background_views.remove(index)) {
+                view
+            } else {
+                let mut view = View::new(Self::view_size(self.terminal_size), self.config.clone());
+                if let Err(err) = view.load(file_name) {
+                    self.message_bar
+                        .update_message(&format!("Could not open {file_name}: {err}"));
+                    return false;
+                }
+                self.push_recent(file_name);
+                view
+            };
+        std::mem::swap(&mut self.view, &mut view);
+        self.background_views.push_back(view);
+        self.view.resize(Self::view_size(self.terminal_size));
+        true
+    }
+
+    fn enter_open_recent(&mut self) {
+        if self.recent_files.is_empty() {
+            self.message_bar.update_message("No recent files.");
+        } else if self.view.is_dirty() {
+            self.message_bar
+                .update_message("Unsaved changes, save before opening another file.");
+        } else {
+            self.set_prompt(PromptType::OpenRecent);
+        }
+    }
+
+    fn open(&mut self, file_name: &str) {
+        match self.view.load(file_name) {
+            Ok(()) if self.view.is_truncated() => {
+                self.push_recent(file_name);
+                self.message_bar.update_message(&format!(
+                    "{file_name} is too long, showing its start only; saving is disabled."
+                ));
+            }
+            Ok(()) => self.push_recent(file_name),
+            Err(err) => self
+                .message_bar
+                .update_message(&format!("Could not open {file_name}: {err}")),
+        }
+    }
+
+    // open `file_name` behind the current file
+    fn open_in_background(&mut self, file_name: &str) {
+        let mut view = View::new(Self::view_size(self.terminal_size), self.config.clone());
+        std::mem::swap(&mut self.view, &mut view);
+        self.open(file_name);
+        std::mem::swap(&mut self.view, &mut view);
+        self.background_views.push_back(view);
+    }
+
+    fn next_file(&mut self) {
+        let Some(mut next) = self.background_views.pop_front() else {
+            self.message_bar.update_message("No other files are open.");
+            return;
+        };
+        std::mem::swap(&mut self.view, &mut next);
+        self.background_views.push_back(next);
+        // the terminal may have been resized while it was in the background
+        self.view.resize(Self::view_size(self.terminal_size));
     }
+
+    /// Record `path` as the most recently opened file.
+    pub fn push_recent(&mut self, path: &str) {
+        let path = std::fs::canonicalize(path).map_or_else(
+            |_| path.to_string(),
+            |path| path.to_string_lossy().into_owned(),
+        );
+        self.recent_files.push(&path);
+        // best effort, the list is a convenience
+        let _ = self.recent_files.save();
+    }
+
+    fn enter_search(&mut self) {
+        self.set_prompt(PromptType::Search);
+        self.view.enter_search();
+    }
+
+    fn update_search_prompt(&mut self) {
+        let flag = |enabled: bool| if enabled { "on" } else { "off" };
+        let SearchOptions {
+            match_case,
+            whole_word,
+        } = self.search_options;
+        self.command_bar.set_prompt(&format!(
+            "Search [Alt-C case: {}] [Alt-W word: {}]: ",
+            flag(match_case),
+            flag(whole_word)
+        ));
+    }
+
+    fn set_prompt(&mut self, prompt_type: PromptType) {
+        match prompt_type {
+            PromptType::None => self.message_bar.set_needs_redraw(),
+            PromptType::Search => {
+                self.command_bar.clear_value();
+                self.update_search_prompt();
+            }
+            PromptType::OpenRecent => {
+                let entries: Vec<String> = self
+                    .recent_files
+                    .iter()
+                    .enumerate()
+                    .map(|(index, path)| format!("[{}] {path}", index + 1))
+                    .collect();
+                self.command_bar.clear_value();
+                self.command_bar
+                    .set_prompt(&format!("Open recent {}: ", entries.join(" ")));
+            }
+            PromptType::AppendFile => {
+                self.command_bar.clear_value();
+                self.command_bar.set_prompt("Append file: ");
+            }
+            PromptType::PipeCommand => {
+                self.command_bar.clear_value();
+                self.command_bar.set_prompt("Pipe through: ");
+            }
+            PromptType::Grep => {
+                self.command_bar.clear_value();
+                self.command_bar.set_prompt("Grep: ");
+            }
+            PromptType::AlignOn => {
+                self.command_bar.clear_value();
+                self.command_bar.set_prompt("Align selection on: ");
+            }
+            PromptType::GotoPercent => {
+                self.command_bar.clear_value();
+                self.command_bar.set_prompt("Go to percent of file: ");
+            }
+            PromptType::ConfirmPaste => {
+                let lines = self
+                    .pending_paste
+                    .as_deref()
+                    .map_or(0, |text| text.lines().count());
+                self.command_bar.clear_value();
+                self.command_bar
+                    .set_prompt(&format!("Paste {lines} lines? Type y to confirm: "));
+            }
+            PromptType::CommandPalette => {
+                self.command_bar.clear_value();
+                self.palette_selection = 0;
+                self.update_palette_prompt();
+            }
+        }
+        self.view.set_prompt_open(prompt_type != PromptType::None);
+        self.prompt_type = prompt_type;
+    }
+
+    fn resize(&mut self, size: Size) {
+        self.terminal_size = size;
+        self.view.resize(Self::view_size(size));
+        self.status_bar.set_needs_redraw();
+        self.message_bar.set_needs_redraw();
+        self.command_bar.set_needs_redraw();
+    }
+
+    fn save(&mut self) {
+        let target_missing = self.view.is_save_target_missing();
+        let message = match self.view.save() {
+            Ok(()) if target_missing => {
+                "Warning: link target was missing and has been recreated.".to_string()
+            }
+            Ok(()) => format!("Wrote {}.", self.view.size_summary()),
+            Err(err) => format!("Error writing file: {err}"),
+        };
+        self.message_bar.update_message(&message);
+    }
+
+    fn diff_against_disk(&mut self) {
+        let message = match self.view.diff_against_disk() {
+            Ok(stats) if stats.is_empty() => "No changes since the last save.".to_string(),
+            Ok(stats) => format!("Since the last save: {stats}."),
+            Err(err) => format!("Could not compare with the saved file: {err}"),
+        };
+        self.message_bar.update_message(&message);
+    }
+
+    fn auto_save_if_idle(&mut self) {
+        if !should_auto_save(
+            self.last_input.elapsed(),
+            self.config.auto_save_after,
+            self.view.is_dirty(),
+            self.view.has_file_name(),
+        ) {
+            return;
+        }
+        // nothing to save if the changes were undone by hand
+        if !self.view.recheck_dirty() {
+            return;
+        }
+        let message = match self.view.save() {
+            Ok(()) => "Auto-saved.".to_string(),
+            Err(err) => format!("Auto-save failed: {err}"),
+        };
+        self.message_bar.update_message(&message);
+        // wait for another idle period before retrying a failed save
+        self.last_input = Instant::now();
+    }
+
+    // `-` asks for stdin explicitly; without a file name, piped input is used
+    fn should_read_stdin(file_name: Option<&str>) -> bool {
+        match file_name {
+            Some(name) => name == "-",
+            None => !std::io::stdin().is_terminal(),
+        }
+    }
+
+    // the file names given on the command line, after the program name
+    fn parse_args(args: impl Iterator<Item = String>) -> Vec<String> {
+        args.skip(1).collect()
+    }
+}
+
+/// Decide whether a quit goes ahead: unsaved changes stop the first
+/// plain quit, so that it takes a second one, while a forced quit
+/// always goes ahead.
+fn can_quit(force: bool, is_dirty: bool, warned: bool) -> bool {
+    force || !is_dirty || warned
+}
+
+/// Whether pasting `text` should be confirmed first, for having more
+/// than `max_lines` lines.
+fn paste_needs_confirming(text: &str, max_lines: Option<usize>) -> bool {
+    max_lines.is_some_and(|max_lines| text.lines().count() > max_lines)
+}
+
+/// Decide whether an idle buffer should be saved: auto-save has to be
+/// enabled, the buffer modified and named, and the idle time reached.
+fn should_auto_save(
+    idle_for: Duration,
+    auto_save_after: Option<Duration>,
+    is_dirty: bool,
+    has_file_name: bool,
+) -> bool {
+    is_dirty && has_file_name && auto_save_after.is_some_and(|after| idle_for >= after)
+}
+
+/// Decide whether a blinking cursor is shown after `idle_for` without
+/// input. It stays visible for the first interval, so it never blinks
+/// while typing, then alternates each interval.
+fn is_cursor_visible(idle_for: Duration, blink_interval: Option<Duration>) -> bool {
+    let Some(interval) = blink_interval.filter(|interval| !interval.is_zero()) else {
+        return true;
+    };
+    (idle_for.as_millis() / interval.as_millis()).is_multiple_of(2)
 }
 
 impl Drop for Editor {
@@ -103,3 +908,289 @@ impl Drop for Editor {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyModifiers;
+
+    const SECOND: Duration = Duration::from_secs(1);
+    const MILLISECOND: Duration = Duration::from_millis(1);
+
+    // an editor that never touches the terminal
+    fn editor(config: Config) -> Editor {
+        let size = Size {
+            width: 80,
+            height: 10,
+        };
+        let view = View::new(Editor::view_size(size), config.clone());
+        Editor::with_view(view, size, config, RecentFiles::default())
+    }
+
+    #[test]
+    fn editor_without_hook_leaves_terminal_alone() {
+        // needs no TTY, so this also runs in CI
+        let editor = Editor::new_without_hook(Config::default(), RecentFiles::default());
+        assert!(!editor.should_quit);
+        assert!(!editor.view.has_file_name());
+        assert_eq!(editor.prompt_type, PromptType::None);
+        assert!(!editor.terminate_requested.load(Ordering::Relaxed));
+    }
+
+    fn paste(editor: &mut Editor, text: &str) {
+        editor.process_command(EditorCommand::Paste(text.to_string()));
+    }
+
+    #[test]
+    fn small_paste_applies_directly() {
+        let mut editor = editor(Config {
+            paste_confirm_lines: Some(3),
+            ..Config::default()
+        });
+        paste(&mut editor, "one\ntwo\nthree");
+        assert_eq!(editor.prompt_type, PromptType::None);
+        assert_eq!(editor.view.get_status().total_lines, 3);
+    }
+
+    #[test]
+    fn large_paste_waits_for_confirmation() {
+        let mut editor = editor(Config {
+            paste_confirm_lines: Some(2),
+            ..Config::default()
+        });
+        paste(&mut editor, "one\ntwo\nthree");
+        assert_eq!(editor.prompt_type, PromptType::ConfirmPaste);
+        assert!(!editor.view.is_dirty());
+
+        // cancelled
+        editor.process_command_during_prompt(EditorCommand::Dismiss);
+        assert_eq!(editor.prompt_type, PromptType::None);
+        assert!(!editor.view.is_dirty());
+
+        // confirmed
+        paste(&mut editor, "one\ntwo\nthree");
+        editor.process_command_during_prompt(EditorCommand::Insert('y'));
+        editor.process_command_during_prompt(EditorCommand::InsertNewline);
+        assert_eq!(editor.view.get_status().total_lines, 3);
+        assert!(editor.view.is_dirty());
+    }
+
+    #[test]
+    fn paste_guard_can_be_turned_off() {
+        assert!(!paste_needs_confirming(&"x\n".repeat(5000), None));
+        assert!(paste_needs_confirming("a\nb", Some(1)));
+        assert!(!paste_needs_confirming("a\nb", Some(2)));
+    }
+
+    #[test]
+    fn auto_save_after_idle_period() {
+        assert!(should_auto_save(2 * SECOND, Some(2 * SECOND), true, true));
+        assert!(!should_auto_save(SECOND, Some(2 * SECOND), true, true));
+    }
+
+    #[test]
+    fn auto_save_requires_dirty_named_buffer() {
+        assert!(!should_auto_save(3 * SECOND, Some(2 * SECOND), false, true));
+        assert!(!should_auto_save(3 * SECOND, Some(2 * SECOND), true, false));
+    }
+
+    #[test]
+    fn auto_save_disabled_by_default() {
+        let config = Config::default();
+        assert!(!should_auto_save(
+            3600 * SECOND,
+            config.auto_save_after,
+            true,
+            true
+        ));
+    }
+
+    #[test]
+    fn parse_args_collects_file_names_in_order() {
+        let args = ["hecto", "a.txt", "b.rs", "c.md"].map(String::from);
+        assert_eq!(
+            Editor::parse_args(args.into_iter()),
+            ["a.txt", "b.rs", "c.md"]
+        );
+    }
+
+    #[test]
+    fn quit_with_unsaved_changes_needs_confirming() {
+        assert!(can_quit(false, false, false));
+        assert!(!can_quit(false, true, false));
+        assert!(can_quit(false, true, true));
+    }
+
+    #[test]
+    fn force_quit_ignores_unsaved_changes() {
+        let mut editor = editor(Config::default());
+        editor.view.insert_str("unsaved");
+        assert!(editor.view.is_dirty());
+        editor.process_command(EditorCommand::ForceQuit);
+        assert!(editor.should_quit);
+    }
+
+    #[test]
+    fn cursor_blinks_only_when_idle() {
+        let interval = Some(500 * MILLISECOND);
+        assert!(is_cursor_visible(3600 * SECOND, None));
+        assert!(is_cursor_visible(Duration::ZERO, interval));
+        assert!(is_cursor_visible(499 * MILLISECOND, interval));
+        assert!(!is_cursor_visible(500 * MILLISECOND, interval));
+        assert!(!is_cursor_visible(999 * MILLISECOND, interval));
+        assert!(is_cursor_visible(1000 * MILLISECOND, interval));
+    }
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> Event {
+        Event::Key(KeyEvent::new(code, modifiers))
+    }
+
+    fn typed(text: &str) -> Vec<Event> {
+        text.chars()
+            .map(|ch| key(KeyCode::Char(ch), KeyModifiers::NONE))
+            .collect()
+    }
+
+    // feed `events` one millisecond apart, as if typed after `start`
+    fn play(editor: &mut Editor, start: Instant, events: Vec<Event>) {
+        for (index, event) in (1..).zip(events) {
+            editor.evaluate_event(event, start + MILLISECOND * index);
+        }
+    }
+
+    #[test]
+    fn scripted_typing_edits_the_buffer() {
+        let mut editor = editor(Config::default());
+        let mut events = typed("hi");
+        events.push(key(KeyCode::Left, KeyModifiers::NONE));
+        events.push(key(KeyCode::Backspace, KeyModifiers::NONE));
+        play(&mut editor, Instant::now(), events);
+        assert_eq!(editor.view.text(), "i");
+        assert_eq!(editor.view.text_position(), Position { row: 0, col: 0 });
+    }
+
+    #[test]
+    fn scripted_session_across_lines() {
+        let mut editor = editor(Config::default());
+        let mut events = typed("one");
+        events.push(key(KeyCode::Enter, KeyModifiers::NONE));
+        events.extend(typed("two"));
+        events.push(key(KeyCode::Up, KeyModifiers::NONE));
+        events.push(key(KeyCode::End, KeyModifiers::NONE));
+        events.extend(typed("!"));
+        play(&mut editor, Instant::now(), events);
+        assert_eq!(editor.view.text(), "one!\ntwo");
+        assert_eq!(editor.view.text_position(), Position { row: 0, col: 4 });
+        assert!(editor.view.is_dirty());
+    }
+
+    #[test]
+    fn scripted_chord_follows_the_event_clock() {
+        let mut editor = editor(Config::default());
+        let start = Instant::now();
+        play(&mut editor, start, typed("ab"));
+        let ctrl_g = key(KeyCode::Char('g'), KeyModifiers::CONTROL);
+        let g = key(KeyCode::Char('g'), KeyModifiers::NONE);
+
+        editor.evaluate_event(ctrl_g.clone(), start + SECOND);
+        editor.evaluate_event(g.clone(), start + SECOND + MILLISECOND);
+        assert_eq!(editor.view.text_position(), Position { row: 0, col: 0 });
+
+        // too late to complete the chord, so the g is typed
+        editor.evaluate_event(ctrl_g, start + SECOND * 2);
+        editor.evaluate_event(g, start + SECOND * 5);
+        assert_eq!(editor.view.text(), "gab");
+    }
+
+    #[test]
+    fn pending_jump_waits_out_a_resize() {
+        let mut editor = editor(Config::default());
+        editor.view.insert_str("abc");
+        editor.view.move_cursor(&Direction::DocumentStart);
+        let mut events = vec![
+            key(KeyCode::Char('t'), KeyModifiers::CONTROL),
+            Event::Resize(40, 10),
+        ];
+        events.extend(typed("c"));
+        play(&mut editor, Instant::now(), events);
+        assert_eq!(editor.terminal_size.width, 40);
+        assert_eq!(editor.view.text_position(), Position { row: 0, col: 2 });
+        assert_eq!(editor.view.text(), "abc");
+    }
+
+    #[test]
+    fn paste_goes_into_an_open_prompt() {
+        let mut editor = editor(Config::default());
+        editor.view.insert_str("one two\nthree two");
+        editor.view.move_cursor(&Direction::DocumentStart);
+        editor.process_command(EditorCommand::Search);
+        let now = Instant::now();
+        editor.evaluate_event(Event::Paste("two\n".to_string()), now);
+        assert_eq!(editor.prompt_type, PromptType::Search);
+        assert_eq!(editor.command_bar.value(), "two");
+        assert_eq!(editor.view.text_position(), Position { row: 0, col: 4 });
+        // the buffer is left alone
+        assert_eq!(editor.view.text(), "one two\nthree two");
+
+        editor.process_command_during_search(EditorCommand::Dismiss);
+        editor.process_command(EditorCommand::Grep);
+        editor.evaluate_event(Event::Paste("needle".to_string()), now);
+        assert_eq!(editor.command_bar.value(), "needle");
+    }
+
+    #[test]
+    fn dismissing_the_paste_prompt_drops_the_text() {
+        let mut editor = editor(Config {
+            paste_confirm_lines: Some(1),
+            ..Config::default()
+        });
+        paste(&mut editor, "one\ntwo");
+        assert!(editor.pending_paste.is_some());
+        editor.process_command_during_prompt(EditorCommand::Dismiss);
+        assert!(editor.pending_paste.is_none());
+        assert_eq!(editor.prompt_type, PromptType::None);
+    }
+
+    #[test]
+    fn open_location_jumps_to_the_line_in_an_open_file() -> std::io::Result<()> {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let path = std::env::temp_dir().join(format!("hecto_test_editor_open_location_{nanos}"));
+        std::fs::write(&path, "one\ntwo\nthree")?;
+        let file_name = path.to_str().unwrap();
+        let mut editor = editor(Config::default());
+        let mut view = View::new(Editor::view_size(editor.terminal_size), Config::default());
+        view.load(file_name)?;
+        editor.background_views.push_back(view);
+
+        editor
+            .view
+            .load_jump_list(&format!("{file_name}:3:three"))?;
+        editor.open_location();
+        assert_eq!(
+            editor.view.get_status().file_name.as_deref(),
+            Some(file_name)
+        );
+        assert_eq!(editor.view.text_position(), Position { col: 0, row: 2 });
+        assert!(
+            editor
+                .background_views
+                .back()
+                .is_some_and(View::is_jump_list)
+        );
+        std::fs::remove_file(path)
+    }
+
+    #[test]
+    fn switch_to_an_unreadable_file_keeps_the_current_buffer() {
+        let mut editor = editor(Config::default());
+        editor.view.insert_str("text");
+        // a directory cannot be read as a file
+        let dir = std::env::temp_dir();
+        assert!(!editor.switch_to(dir.to_str().unwrap()));
+        assert_eq!(editor.view.current_line(), "text");
+        assert!(editor.background_views.is_empty());
+    }
+}
@@ -0,0 +1,133 @@
+use super::editorcommand::{Direction, EditorCommand};
+use crossterm::event::{KeyCode, KeyModifiers};
+use std::time::{Duration, Instant};
+
+/// A pending chord is dropped if its next key doesn't come within this
+/// time.
+const CHORD_TIMEOUT: Duration = Duration::from_secs(1);
+
+type Key = (KeyCode, KeyModifiers);
+
+const CTRL_G: Key = (KeyCode::Char('g'), KeyModifiers::CONTROL);
+
+/// Commands bound to a sequence of keys rather than a single one.
+const CHORDS: &[(&[Key], EditorCommand)] = &[
+    (
+        &[CTRL_G, (KeyCode::Char('g'), KeyModifiers::NONE)],
+        EditorCommand::Move(Direction::DocumentStart),
+    ),
+    (
+        &[CTRL_G, (KeyCode::Char('e'), KeyModifiers::NONE)],
+        EditorCommand::Move(Direction::DocumentEnd),
+    ),
+    (
+        &[CTRL_G, (KeyCode::Char('d'), KeyModifiers::NONE)],
+        EditorCommand::GoToDefinition,
+    ),
+    (
+        &[CTRL_G, (KeyCode::Char('p'), KeyModifiers::NONE)],
+        EditorCommand::GotoPercent,
+    ),
+];
+
+#[derive(Debug, PartialEq)]
+pub enum ChordOutcome {
+    /// The key starts or continues a chord; wait for the next one.
+    Pending,
+    /// The key completed a chord.
+    Complete(EditorCommand),
+    /// The key broke off a chord and is swallowed with it.
+    Cancelled,
+    /// The key has nothing to do with chords.
+    NotAChord,
+}
+
+/// The keys typed so far towards a chord.
+#[derive(Default)]
+pub struct ChordState {
+    pending: Vec<Key>,
+    last_key_at: Option<Instant>,
+}
+
+impl ChordState {
+    pub fn feed(&mut self, key: Key, now: Instant) -> ChordOutcome {
+        self.expire(now);
+        let in_chord = !self.pending.is_empty();
+        self.pending.push(key);
+        if let Some((_, command)) = CHORDS.iter().find(|(keys, _)| *keys == self.pending) {
+            self.clear();
+            return ChordOutcome::Complete(command.clone());
+        }
+        if CHORDS
+            .iter()
+            .any(|(keys, _)| keys.starts_with(&self.pending))
+        {
+            self.last_key_at = Some(now);
+            return ChordOutcome::Pending;
+        }
+        self.clear();
+        if in_chord {
+            ChordOutcome::Cancelled
+        } else {
+            ChordOutcome::NotAChord
+        }
+    }
+
+    /// Drop a chord left unfinished for too long, returning whether there
+    /// was one.
+    pub fn expire(&mut self, now: Instant) -> bool {
+        let expired = self
+            .last_key_at
+            .is_some_and(|at| now.saturating_duration_since(at) > CHORD_TIMEOUT);
+        if expired {
+            self.clear();
+        }
+        expired
+    }
+
+    fn clear(&mut self) {
+        self.pending.clear();
+        self.last_key_at = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const G: Key = (KeyCode::Char('g'), KeyModifiers::NONE);
+
+    #[test]
+    fn full_chord_resolves_to_command() {
+        let mut chords = ChordState::default();
+        let now = Instant::now();
+        assert_eq!(chords.feed(CTRL_G, now), ChordOutcome::Pending);
+        assert_eq!(
+            chords.feed(G, now + CHORD_TIMEOUT / 2),
+            ChordOutcome::Complete(EditorCommand::Move(Direction::DocumentStart))
+        );
+    }
+
+    #[test]
+    fn incomplete_chord_is_discarded_after_timeout() {
+        let mut chords = ChordState::default();
+        let now = Instant::now();
+        assert_eq!(chords.feed(CTRL_G, now), ChordOutcome::Pending);
+        assert!(chords.expire(now + CHORD_TIMEOUT * 2));
+        // the g no longer completes anything
+        assert_eq!(
+            chords.feed(G, now + CHORD_TIMEOUT * 2),
+            ChordOutcome::NotAChord
+        );
+    }
+
+    #[test]
+    fn unbound_second_key_cancels_chord() {
+        let mut chords = ChordState::default();
+        let now = Instant::now();
+        chords.feed(CTRL_G, now);
+        let x = (KeyCode::Char('x'), KeyModifiers::NONE);
+        assert_eq!(chords.feed(x, now), ChordOutcome::Cancelled);
+        assert_eq!(chords.feed(x, now), ChordOutcome::NotAChord);
+    }
+}
@@ -0,0 +1,75 @@
+use super::terminal::Terminal;
+use super::text::truncate_to_width;
+use unicode_width::UnicodeWidthStr;
+
+/// A single-line prompt rendered in place of the message bar.
+#[derive(Default)]
+pub struct CommandBar {
+    prompt: String,
+    value: String,
+    // shown after the value, e.g. a match count
+    suffix: String,
+    needs_redraw: bool,
+}
+
+impl CommandBar {
+    pub fn set_prompt(&mut self, prompt: &str) {
+        self.prompt = prompt.to_string();
+        self.suffix.clear();
+        self.needs_redraw = true;
+    }
+
+    pub fn set_suffix(&mut self, suffix: &str) {
+        if self.suffix != suffix {
+            self.suffix = suffix.to_string();
+            self.needs_redraw = true;
+        }
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    pub fn clear_value(&mut self) {
+        self.value.clear();
+        self.needs_redraw = true;
+    }
+
+    pub fn insert(&mut self, ch: char) {
+        self.value.push(ch);
+        self.needs_redraw = true;
+    }
+
+    /// Append pasted `text`; the prompt is a single line, so only the
+    /// first line of it is kept.
+    pub fn insert_str(&mut self, text: &str) {
+        if let Some(line) = text.lines().next().filter(|line| !line.is_empty()) {
+            self.value.push_str(line);
+            self.needs_redraw = true;
+        }
+    }
+
+    pub fn delete_left(&mut self) {
+        if self.value.pop().is_some() {
+            self.needs_redraw = true;
+        }
+    }
+
+    pub fn set_needs_redraw(&mut self) {
+        self.needs_redraw = true;
+    }
+
+    pub fn cursor_col(&self) -> usize {
+        self.prompt.width() + self.value.width()
+    }
+
+    pub fn render(&mut self, row: usize, width: usize) -> Result<(), std::io::Error> {
+        if !self.needs_redraw {
+            return Ok(());
+        }
+        let text = format!("{}{}{}", self.prompt, self.value, self.suffix);
+        Terminal::print_row(row, &truncate_to_width(&text, width))?;
+        self.needs_redraw = false;
+        Ok(())
+    }
+}
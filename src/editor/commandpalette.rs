@@ -0,0 +1,245 @@
+use super::editorcommand::{Direction, EditorCommand};
+
+/// A command that can be run by name, independent of its key binding.
+pub struct Action {
+    pub label: &'static str,
+    pub command: EditorCommand,
+}
+
+pub const ACTIONS: &[Action] = &[
+    Action {
+        label: "Save",
+        command: EditorCommand::Save,
+    },
+    Action {
+        label: "Find",
+        command: EditorCommand::Search,
+    },
+    Action {
+        label: "Find next",
+        command: EditorCommand::SearchNext,
+    },
+    Action {
+        label: "Find previous",
+        command: EditorCommand::SearchPrevious,
+    },
+    Action {
+        label: "Open recent file",
+        command: EditorCommand::OpenRecent,
+    },
+    Action {
+        label: "Append file",
+        command: EditorCommand::AppendFile,
+    },
+    Action {
+        label: "Pipe through command",
+        command: EditorCommand::PipeCommand,
+    },
+    Action {
+        label: "Remove duplicate lines",
+        command: EditorCommand::UniqueLines,
+    },
+    Action {
+        label: "Toggle header/source",
+        command: EditorCommand::SwitchHeaderSource,
+    },
+    Action {
+        label: "Grep the directory",
+        command: EditorCommand::Grep,
+    },
+    Action {
+        label: "Align on character",
+        command: EditorCommand::AlignOn,
+    },
+    Action {
+        label: "Compare with disk",
+        command: EditorCommand::DiffAgainstDisk,
+    },
+    Action {
+        label: "Go to definition",
+        command: EditorCommand::GoToDefinition,
+    },
+    Action {
+        label: "Undo",
+        command: EditorCommand::Undo,
+    },
+    Action {
+        label: "Redo",
+        command: EditorCommand::Redo,
+    },
+    Action {
+        label: "Repeat last edit",
+        command: EditorCommand::RepeatEdit,
+    },
+    Action {
+        label: "Increment number",
+        command: EditorCommand::AddToNumber(1),
+    },
+    Action {
+        label: "Decrement number",
+        command: EditorCommand::AddToNumber(-1),
+    },
+    Action {
+        label: "Toggle overwrite",
+        command: EditorCommand::ToggleOverwrite,
+    },
+    Action {
+        label: "Toggle fold",
+        command: EditorCommand::ToggleFold,
+    },
+    Action {
+        label: "Toggle end of line markers",
+        command: EditorCommand::ToggleEol,
+    },
+    Action {
+        label: "Check whitespace",
+        command: EditorCommand::CheckWhitespace,
+    },
+    Action {
+        label: "Retab indentation",
+        command: EditorCommand::Retab,
+    },
+    Action {
+        label: "Convert line endings",
+        command: EditorCommand::ToggleLineEnding,
+    },
+    Action {
+        label: "Trim blank lines on save",
+        command: EditorCommand::ToggleTrimOnSave,
+    },
+    Action {
+        label: "Close open brackets",
+        command: EditorCommand::BalanceBrackets,
+    },
+    Action {
+        label: "Jump to char",
+        command: EditorCommand::StartJump,
+    },
+    Action {
+        label: "Repeat jump",
+        command: EditorCommand::RepeatJump,
+    },
+    Action {
+        label: "Delete selection",
+        command: EditorCommand::DeleteSelection,
+    },
+    Action {
+        label: "Duplicate selection",
+        command: EditorCommand::DuplicateSelection,
+    },
+    Action {
+        label: "Previous paragraph",
+        command: EditorCommand::Move(Direction::ParagraphUp),
+    },
+    Action {
+        label: "Next paragraph",
+        command: EditorCommand::Move(Direction::ParagraphDown),
+    },
+    Action {
+        label: "Go to start of file",
+        command: EditorCommand::Move(Direction::DocumentStart),
+    },
+    Action {
+        label: "Go to end of file",
+        command: EditorCommand::Move(Direction::DocumentEnd),
+    },
+    Action {
+        label: "Go to percentage",
+        command: EditorCommand::GotoPercent,
+    },
+    Action {
+        label: "Insert date and time",
+        command: EditorCommand::InsertDate,
+    },
+    Action {
+        label: "Next buffer",
+        command: EditorCommand::NextFile,
+    },
+    Action {
+        label: "Quit",
+        command: EditorCommand::Quit,
+    },
+    Action {
+        label: "Quit without saving",
+        command: EditorCommand::ForceQuit,
+    },
+];
+
+/// Rank how well `query` matches `label`, lower is better: substring
+/// matches come first by their position, then scattered (fuzzy) matches
+/// by how spread out they are. `None` if the label does not match.
+fn score(label: &str, query: &str) -> Option<usize> {
+    let label = label.to_lowercase();
+    let query = query.to_lowercase();
+    if let Some(index) = label.find(&query) {
+        return Some(index);
+    }
+    // every query char has to appear in order
+    let mut chars = label.char_indices();
+    let mut first = None;
+    let mut last = 0;
+    for wanted in query.chars() {
+        let (index, _) = chars.find(|&(_, ch)| ch == wanted)?;
+        first.get_or_insert(index);
+        last = index;
+    }
+    Some(label.len() + last - first.unwrap_or(0))
+}
+
+/// The actions matching `query`, best match first.
+pub fn filter(query: &str) -> Vec<&'static Action> {
+    let mut matches: Vec<(usize, &Action)> = ACTIONS
+        .iter()
+        .filter_map(|action| score(action.label, query).map(|score| (score, action)))
+        .collect();
+    // a stable sort keeps the registry order for equal scores
+    matches.sort_by_key(|&(score, _)| score);
+    matches.into_iter().map(|(_, action)| action).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(query: &str) -> Vec<&'static str> {
+        filter(query).iter().map(|action| action.label).collect()
+    }
+
+    #[test]
+    fn empty_query_lists_all_actions() {
+        assert_eq!(filter("").len(), ACTIONS.len());
+    }
+
+    #[test]
+    fn substring_matches_rank_by_position() {
+        assert_eq!(
+            labels("sel"),
+            vec!["Delete selection", "Duplicate selection"]
+        );
+        assert_eq!(labels("para"), vec!["Next paragraph", "Previous paragraph"]);
+        assert_eq!(labels("file")[..2], ["Append file", "Open recent file"]);
+    }
+
+    #[test]
+    fn substring_ranks_before_fuzzy_match() {
+        assert_eq!(
+            labels("ap"),
+            vec![
+                "Append file",
+                "Next paragraph",
+                "Previous paragraph",
+                "Repeat jump"
+            ]
+        );
+    }
+
+    #[test]
+    fn fuzzy_match_is_case_insensitive() {
+        assert_eq!(labels("DPSL")[0], "Duplicate selection");
+    }
+
+    #[test]
+    fn no_match() {
+        assert!(labels("xyz").is_empty());
+    }
+}
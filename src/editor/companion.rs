@@ -0,0 +1,87 @@
+use std::path::{Path, PathBuf};
+
+/// Source and header extensions that go together, most likely first.
+const PAIRS: &[(&[&str], &[&str])] = &[
+    (&["c"], &["h"]),
+    (&["cpp", "cc", "cxx", "c++"], &["hpp", "h", "hh", "hxx"]),
+    (&["m", "mm"], &["h"]),
+];
+
+/// The paths `path` may have a header or source of in its directory, in
+/// the order they are tried.
+pub fn companion_candidates(path: &Path) -> Vec<PathBuf> {
+    let Some(extension) = path.extension().and_then(|extension| extension.to_str()) else {
+        return Vec::new();
+    };
+    let mut candidates: Vec<PathBuf> = Vec::new();
+    for (sources, headers) in PAIRS {
+        let others = if sources.contains(&extension) {
+            headers
+        } else if headers.contains(&extension) {
+            sources
+        } else {
+            continue;
+        };
+        for other in *others {
+            let candidate = path.with_extension(other);
+            if !candidates.contains(&candidate) {
+                candidates.push(candidate);
+            }
+        }
+    }
+    candidates
+}
+
+/// The header of a source file or the source of a header, if one exists
+/// next to it.
+pub fn companion_path(path: &Path) -> Option<PathBuf> {
+    companion_candidates(path)
+        .into_iter()
+        .find(|candidate| candidate.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[test]
+    fn cpp_source_pairs_with_cpp_headers() {
+        let candidates = companion_candidates(Path::new("src/foo.cpp"));
+        assert_eq!(candidates[0], Path::new("src/foo.hpp"));
+        assert_eq!(candidates[1], Path::new("src/foo.h"));
+    }
+
+    #[test]
+    fn header_pairs_with_every_source_kind() {
+        let candidates = companion_candidates(Path::new("foo.h"));
+        assert_eq!(candidates[0], Path::new("foo.c"));
+        assert!(candidates.contains(&PathBuf::from("foo.cpp")));
+        assert!(candidates.contains(&PathBuf::from("foo.m")));
+    }
+
+    #[test]
+    fn other_files_have_no_companion() {
+        assert!(companion_candidates(Path::new("notes.txt")).is_empty());
+        assert!(companion_candidates(Path::new("Makefile")).is_empty());
+        assert_eq!(companion_path(Path::new("notes.txt")), None);
+    }
+
+    #[test]
+    fn companion_must_exist() -> std::io::Result<()> {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let dir = std::env::temp_dir().join(format!("hecto_test_companion_{nanos}"));
+        std::fs::create_dir_all(&dir)?;
+        let source = dir.join("foo.cpp");
+        let header = dir.join("foo.h");
+        std::fs::write(&header, "")?;
+        // foo.hpp is tried first but doesn't exist
+        assert_eq!(companion_path(&source), Some(header.clone()));
+        std::fs::remove_file(&header)?;
+        assert_eq!(companion_path(&source), None);
+        std::fs::remove_dir(dir)
+    }
+}
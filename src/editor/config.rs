@@ -0,0 +1,305 @@
+use super::theme::Theme;
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IndentStyle {
+    Tabs,
+    Spaces,
+}
+
+/// How a buffer is indented: the style, and the width of one level in
+/// columns.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Indent {
+    pub style: IndentStyle,
+    pub width: usize,
+}
+
+impl Display for Indent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.style {
+            IndentStyle::Tabs => write!(f, "Tabs: {}", self.width),
+            IndentStyle::Spaces => write!(f, "Spaces: {}", self.width),
+        }
+    }
+}
+
+/// User-tunable settings shared by the editor components.
+#[derive(Clone, Debug, PartialEq)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct Config {
+    /// Text shown on the welcome screen, `None` shows the default banner.
+    pub welcome_message: Option<String>,
+    /// Give trailing spaces and tabs a distinct background.
+    pub highlight_trailing_whitespace: bool,
+    /// Skip the trailing whitespace highlight on the line being edited.
+    pub trailing_whitespace_skip_cursor_line: bool,
+    /// Save a modified, named buffer after this long without input.
+    pub auto_save_after: Option<Duration>,
+    /// Blink the cursor at this interval while there is no input.
+    pub cursor_blink: Option<Duration>,
+    /// Lines kept in view when paging up or down.
+    pub page_overlap: usize,
+    /// Let page motions scroll on past the end of the buffer, until the
+    /// last line reaches the top of the view.
+    pub scroll_past_end: bool,
+    /// Drawn on rows past the end of the buffer; empty leaves them blank.
+    pub end_of_buffer_marker: String,
+    /// Draw ASCII stand-ins for symbols such as `·` and `⋯`, for
+    /// terminals that lack them.
+    pub ascii_only: bool,
+    /// Draw a marker after the end of each line.
+    pub show_eol: bool,
+    /// Collapse blank lines at the end of a file into one when loading,
+    /// and by default when saving; see `ToggleTrimOnSave`.
+    pub trim_trailing_blank_lines: bool,
+    /// Report mixed indentation and trailing whitespace in the status bar
+    /// on load and save.
+    pub check_whitespace: bool,
+    /// Indentation for buffers whose own style can't be detected.
+    pub indent: Indent,
+    /// Columns between tab stops.
+    pub tab_width: usize,
+    /// Columns to scroll at once when the cursor leaves the view
+    /// sideways.
+    pub hscroll_step: usize,
+    /// Lines wider than this many columns have the excess highlighted.
+    pub max_line_length: Option<usize>,
+    /// Load at most this many lines of a file, as a guard against
+    /// freezing on huge files.
+    pub max_lines: Option<usize>,
+    /// Keep at most this many groups of edits to undo; the oldest are
+    /// forgotten first.
+    pub undo_limit: Option<usize>,
+    /// Ask before pasting text of more than this many lines.
+    pub paste_confirm_lines: Option<usize>,
+    /// Expand tabs in pasted text to spaces, at `tab_width` stops, when
+    /// the buffer indents with spaces.
+    pub paste_tabs_to_spaces: bool,
+    /// Colors to draw with, picked by name in the config file.
+    pub theme: Theme,
+    /// Draw a gutter of line numbers left of the text.
+    pub line_numbers: bool,
+    /// Number the other lines by their distance from the cursor line,
+    /// going back to absolute numbers while a prompt is open.
+    pub relative_line_numbers: bool,
+    /// Drawn between the line numbers and the text, e.g. `│`; may be
+    /// empty.
+    pub gutter_separator: String,
+    /// Blank columns between the gutter separator and the text.
+    pub gutter_padding: usize,
+    /// How the insert date command writes the time, in UTC; see
+    /// `datetime::format_timestamp`.
+    pub date_format: String,
+    /// A word list, one word per line, to highlight words missing from;
+    /// no spell checking without one.
+    pub dictionary: Option<PathBuf>,
+    /// Draw an overview of the whole buffer along the right edge.
+    pub minimap: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            welcome_message: None,
+            highlight_trailing_whitespace: true,
+            trailing_whitespace_skip_cursor_line: true,
+            auto_save_after: None,
+            cursor_blink: None,
+            page_overlap: 0,
+            scroll_past_end: false,
+            end_of_buffer_marker: "~".to_string(),
+            ascii_only: false,
+            show_eol: false,
+            trim_trailing_blank_lines: false,
+            check_whitespace: false,
+            indent: Indent {
+                style: IndentStyle::Spaces,
+                width: 4,
+            },
+            tab_width: 8,
+            hscroll_step: 1,
+            max_line_length: None,
+            max_lines: None,
+            undo_limit: Some(1000),
+            paste_confirm_lines: Some(1000),
+            paste_tabs_to_spaces: false,
+            theme: Theme::default(),
+            line_numbers: false,
+            relative_line_numbers: false,
+            gutter_separator: String::new(),
+            gutter_padding: 1,
+            date_format: "%Y-%m-%d %H:%M".to_string(),
+            dictionary: None,
+            minimap: false,
+        }
+    }
+}
+
+impl Config {
+    /// Read the settings from the config file, keeping the defaults if it
+    /// is missing and for any line that can't be understood.
+    pub fn load() -> Self {
+        Self::config_file()
+            .and_then(|file| std::fs::read_to_string(file).ok())
+            .map(|contents| Self::parse(&contents))
+            .unwrap_or_default()
+    }
+
+    /// Parse `key = value` lines on top of the defaults. Blank lines and
+    /// lines starting with `#` are skipped; a value may be wrapped in
+    /// double quotes to keep surrounding spaces.
+    pub fn parse(contents: &str) -> Self {
+        let mut config = Self::default();
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some((key, value)) = line.split_once('=') {
+                let value = value.trim();
+                let value = value
+                    .strip_prefix('"')
+                    .and_then(|value| value.strip_suffix('"'))
+                    .unwrap_or(value);
+                config.set(key.trim(), value);
+            }
+        }
+        config
+    }
+
+    // unknown keys and malformed values leave the setting as it was
+    fn set(&mut self, key: &str, value: &str) {
+        match key {
+            "welcome_message" => self.welcome_message = Some(value.to_string()),
+            "highlight_trailing_whitespace" => {
+                set_parsed(&mut self.highlight_trailing_whitespace, value);
+            }
+            "trailing_whitespace_skip_cursor_line" => {
+                set_parsed(&mut self.trailing_whitespace_skip_cursor_line, value);
+            }
+            "auto_save_after" => {
+                if let Ok(seconds) = value.parse::<u64>() {
+                    self.auto_save_after = (seconds > 0).then(|| Duration::from_secs(seconds));
+                }
+            }
+            "cursor_blink_ms" => {
+                if let Ok(millis) = value.parse::<u64>() {
+                    self.cursor_blink = (millis > 0).then(|| Duration::from_millis(millis));
+                }
+            }
+            "page_overlap" => set_parsed(&mut self.page_overlap, value),
+            "scroll_past_end" => set_parsed(&mut self.scroll_past_end, value),
+            "end_of_buffer_marker" => self.end_of_buffer_marker = value.to_string(),
+            "ascii_only" => set_parsed(&mut self.ascii_only, value),
+            "show_eol" => set_parsed(&mut self.show_eol, value),
+            "trim_trailing_blank_lines" => set_parsed(&mut self.trim_trailing_blank_lines, value),
+            "check_whitespace" => set_parsed(&mut self.check_whitespace, value),
+            "indent_style" => match value {
+                "tabs" => self.indent.style = IndentStyle::Tabs,
+                "spaces" => self.indent.style = IndentStyle::Spaces,
+                _ => {}
+            },
+            "indent_width" => set_positive(&mut self.indent.width, value),
+            "tab_width" => set_positive(&mut self.tab_width, value),
+            "hscroll_step" => set_positive(&mut self.hscroll_step, value),
+            "max_line_length" => {
+                if let Ok(max_line_length) = value.parse::<usize>() {
+                    self.max_line_length = (max_line_length > 0).then_some(max_line_length);
+                }
+            }
+            "max_lines" => {
+                if let Ok(max_lines) = value.parse::<usize>() {
+                    self.max_lines = (max_lines > 0).then_some(max_lines);
+                }
+            }
+            "undo_limit" => {
+                if let Ok(undo_limit) = value.parse::<usize>() {
+                    self.undo_limit = (undo_limit > 0).then_some(undo_limit);
+                }
+            }
+            "paste_confirm_lines" => {
+                if let Ok(lines) = value.parse::<usize>() {
+                    self.paste_confirm_lines = (lines > 0).then_some(lines);
+                }
+            }
+            "paste_tabs_to_spaces" => set_parsed(&mut self.paste_tabs_to_spaces, value),
+            "theme" => {
+                if let Some(theme) = Theme::named(value) {
+                    self.theme = theme;
+                }
+            }
+            "line_numbers" => set_parsed(&mut self.line_numbers, value),
+            "relative_line_numbers" => set_parsed(&mut self.relative_line_numbers, value),
+            "gutter_separator" => self.gutter_separator = value.to_string(),
+            "gutter_padding" => set_parsed(&mut self.gutter_padding, value),
+            "date_format" => self.date_format = value.to_string(),
+            "dictionary" => self.dictionary = Some(PathBuf::from(value)),
+            "minimap" => set_parsed(&mut self.minimap, value),
+            _ => {}
+        }
+    }
+
+    fn config_file() -> Option<PathBuf> {
+        let dir = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(dir.join("hecto").join("config"))
+    }
+}
+
+fn set_parsed<T: FromStr>(setting: &mut T, value: &str) {
+    if let Ok(parsed) = value.parse() {
+        *setting = parsed;
+    }
+}
+
+fn set_positive(setting: &mut usize, value: &str) {
+    if let Ok(parsed @ 1..) = value.parse() {
+        *setting = parsed;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sample_config() {
+        let config = Config::parse(
+            "# hecto settings\n\
+             tab_width = 4\n\
+             indent_style = tabs\n\
+             ascii_only=true\n\
+             \n\
+             end_of_buffer_marker = \" \"\n\
+             auto_save_after = 30\n\
+             theme = light\n\
+             gutter_separator = \"│\"\n\
+             gutter_padding = 0\n",
+        );
+        assert_eq!(config.tab_width, 4);
+        assert_eq!(config.indent.style, IndentStyle::Tabs);
+        assert!(config.ascii_only);
+        assert_eq!(config.end_of_buffer_marker, " ");
+        assert_eq!(config.auto_save_after, Some(Duration::from_secs(30)));
+        assert_eq!(config.theme, Theme::LIGHT);
+        assert_eq!(config.gutter_separator, "│");
+        assert_eq!(config.gutter_padding, 0);
+    }
+
+    #[test]
+    fn unknown_keys_and_bad_values_keep_defaults() {
+        let config = Config::parse(
+            "colour_scheme = solarized\n\
+             tab_width = wide\n\
+             hscroll_step = 0\n\
+             theme = neon\n\
+             no equals sign here\n",
+        );
+        assert_eq!(config, Config::default());
+    }
+}
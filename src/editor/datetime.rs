@@ -0,0 +1,80 @@
+use std::fmt::Write;
+
+/// Format `timestamp`, in seconds since the Unix epoch, in UTC. The
+/// format understands `%Y`, `%m`, `%d`, `%H`, `%M`, `%S` and `%%`; any
+/// other text is copied as it is.
+pub fn format_timestamp(timestamp: u64, format: &str) -> String {
+    let (year, month, day) = civil_from_days(timestamp / 86_400);
+    let seconds = timestamp % 86_400;
+    let (hour, minute, second) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+
+    let mut result = String::new();
+    let mut chars = format.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '%' {
+            result.push(ch);
+            continue;
+        }
+        let value = match chars.next() {
+            Some('Y') => year,
+            Some('m') => month,
+            Some('d') => day,
+            Some('H') => hour,
+            Some('M') => minute,
+            Some('S') => second,
+            Some('%') | None => {
+                result.push('%');
+                continue;
+            }
+            Some(other) => {
+                result.push('%');
+                result.push(other);
+                continue;
+            }
+        };
+        let _ = write!(result, "{value:02}");
+    }
+    result
+}
+
+// the year, month and day `days` days after 1970-01-01, after Howard
+// Hinnant's `civil_from_days`
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    // months counted from March, so that the leap day comes last
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_fixed_timestamp() {
+        // 2024-02-29 13:05:09 UTC
+        let timestamp = 1_709_211_909;
+        assert_eq!(
+            format_timestamp(timestamp, "%Y-%m-%d %H:%M:%S"),
+            "2024-02-29 13:05:09"
+        );
+        assert_eq!(format_timestamp(timestamp, "%d.%m. 100%%"), "29.02. 100%");
+    }
+
+    #[test]
+    fn epoch_and_unknown_specifiers() {
+        assert_eq!(format_timestamp(0, "%Y-%m-%d %q"), "1970-01-01 %q");
+    }
+}
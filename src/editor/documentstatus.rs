@@ -0,0 +1,112 @@
+use super::config::Indent;
+use super::view::WhitespaceReport;
+
+/// What the status bar shows about the buffer being edited.
+#[derive(Clone, Debug, PartialEq)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct DocumentStatus {
+    pub file_name: Option<String>,
+    pub total_lines: usize,
+    pub current_line_index: usize,
+    /// The 1-based screen column of the cursor.
+    pub display_column: usize,
+    pub is_modified: bool,
+    /// Whether the file doesn't exist on disk yet.
+    pub is_new_file: bool,
+    pub indent: Indent,
+    /// Whether the line with the cursor reads right to left.
+    pub is_rtl: bool,
+    /// Whether typing replaces the grapheme under the cursor.
+    pub overwrite: bool,
+    /// Graphemes and lines selected, if there is a selection.
+    pub selection: Option<(usize, usize)>,
+    /// The name of the line ending saving writes, e.g. `LF`.
+    pub line_ending: &'static str,
+    /// The result of the last whitespace check, if there was one.
+    pub whitespace: Option<WhitespaceReport>,
+    /// Whether saving collapses trailing blank lines.
+    pub trims_on_save: bool,
+}
+
+impl DocumentStatus {
+    pub fn file_name_to_string(&self) -> String {
+        self.file_name
+            .clone()
+            .unwrap_or_else(|| "[No Name]".to_string())
+    }
+
+    pub fn new_file_indicator_to_string(&self) -> String {
+        if self.is_new_file {
+            "[New File]".to_string()
+        } else {
+            String::new()
+        }
+    }
+
+    pub fn line_count_to_string(&self) -> String {
+        format!("{} lines", self.total_lines)
+    }
+
+    pub fn modified_indicator_to_string(&self) -> String {
+        if self.is_modified {
+            "(modified)".to_string()
+        } else {
+            String::new()
+        }
+    }
+
+    pub fn position_indicator_to_string(&self) -> String {
+        format!(
+            "{}/{}",
+            self.current_line_index.saturating_add(1),
+            self.total_lines
+        )
+    }
+
+    pub fn column_to_string(&self) -> String {
+        format!("Col {}", self.display_column)
+    }
+
+    pub fn indent_to_string(&self) -> String {
+        self.indent.to_string()
+    }
+
+    pub fn mode_to_string(&self) -> String {
+        if self.overwrite {
+            "OVR".to_string()
+        } else {
+            "INS".to_string()
+        }
+    }
+
+    pub fn selection_to_string(&self) -> String {
+        match self.selection {
+            Some((graphemes, 1)) => format!("{graphemes} selected"),
+            Some((graphemes, lines)) => format!("{graphemes} selected, {lines} lines"),
+            None => String::new(),
+        }
+    }
+
+    pub fn whitespace_to_string(&self) -> String {
+        self.whitespace
+            .filter(|report| !report.is_clean())
+            .map(|report| report.to_string())
+            .unwrap_or_default()
+    }
+
+    pub fn trim_to_string(&self) -> String {
+        if self.trims_on_save {
+            "Trim".to_string()
+        } else {
+            String::new()
+        }
+    }
+
+    pub fn direction_to_string(&self) -> String {
+        if self.is_rtl {
+            "RTL".to_string()
+        } else {
+            String::new()
+        }
+    }
+}
@@ -1,9 +1,13 @@
-use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use crossterm::event::{
+    Event, KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
 
+use super::position::Position;
 use super::terminal::Size;
 use std::convert::TryFrom;
 use std::fmt::{Display, Formatter};
 
+#[derive(Clone, Debug, PartialEq)]
 pub enum Direction {
     PageUp,
     PageDown,
@@ -13,15 +17,89 @@ pub enum Direction {
     Down,
     Left,
     Right,
+    WordLeft,
+    WordRight,
+    ParagraphUp,
+    ParagraphDown,
+    DocumentStart,
+    DocumentEnd,
 }
 
+#[derive(Clone, Debug, PartialEq)]
 pub enum EditorCommand {
     Move(Direction),
+    Select(Direction),
+    DeleteSelection,
+    DuplicateSelection,
+    /// Remove repeated adjacent lines, like `uniq`.
+    UniqueLines,
+    /// Convert indentation to the buffer's indent style.
+    Retab,
+    /// Report mixed indentation and trailing whitespace.
+    CheckWhitespace,
+    AddCursorBelow,
+    /// A left click at a position on the screen.
+    Click(Position),
+    DoubleClick(Position),
     Resize(Size),
     Insert(char),
+    /// Text pasted into the terminal in one go.
+    Paste(String),
     DeleteLeft,
     DeleteRight,
+    Undo,
+    Redo,
+    /// Apply the last text-changing command again at the cursor.
+    RepeatEdit,
+    AddToNumber(i64),
+    ToggleOverwrite,
+    ToggleFold,
+    /// Show or hide the end of line markers.
+    ToggleEol,
+    /// Switch the buffer between LF and CRLF line endings.
+    ToggleLineEnding,
+    /// Close the brackets left open before the cursor on its line.
+    BalanceBrackets,
+    /// Turn collapsing trailing blank lines on save on or off for the
+    /// current buffer only.
+    ToggleTrimOnSave,
+    StartJump,
+    JumpToChar(char),
+    RepeatJump,
+    InsertNewline,
+    Save,
+    Search,
+    /// Go to the next match of the latest search.
+    SearchNext,
+    /// Go to the previous match of the latest search.
+    SearchPrevious,
+    OpenRecent,
+    AppendFile,
+    PipeCommand,
+    /// Open the header of a C or C++ source file, or the source of a
+    /// header.
+    SwitchHeaderSource,
+    /// Run a search command and list the `file:line` matches it prints.
+    Grep,
+    /// Line up a separator character across the selected lines.
+    AlignOn,
+    /// Ask for a percentage and go to that far into the buffer.
+    GotoPercent,
+    /// Report how the buffer differs from the saved file.
+    DiffAgainstDisk,
+    /// Jump to where the identifier under the cursor is defined.
+    GoToDefinition,
+    CommandPalette,
+    ToggleMatchCase,
+    ToggleWholeWord,
+    /// Insert the current date and time at the cursor.
+    InsertDate,
+    /// Switch to the next of the open files.
+    NextFile,
+    Dismiss,
     Quit,
+    /// Quit at once, discarding unsaved changes.
+    ForceQuit,
 }
 
 #[derive(Debug)]
@@ -49,6 +127,52 @@ impl TryFrom<Event> for EditorCommand {
                 code, modifiers, ..
             }) => match (code, modifiers) {
                 (KeyCode::Char('q'), KeyModifiers::CONTROL) => Ok(Self::Quit),
+                (KeyCode::Char('q'), modifiers)
+                    if modifiers == KeyModifiers::CONTROL | KeyModifiers::ALT =>
+                {
+                    Ok(Self::ForceQuit)
+                }
+                (KeyCode::Char('s'), KeyModifiers::CONTROL) => Ok(Self::Save),
+                (KeyCode::Char('f'), KeyModifiers::CONTROL) => Ok(Self::Search),
+                (KeyCode::Char('o'), KeyModifiers::CONTROL) => Ok(Self::OpenRecent),
+                (KeyCode::Char('a'), KeyModifiers::ALT) => Ok(Self::AppendFile),
+                (KeyCode::Char('f'), KeyModifiers::ALT) => Ok(Self::PipeCommand),
+                (KeyCode::Char('o'), KeyModifiers::ALT) => Ok(Self::SwitchHeaderSource),
+                (KeyCode::Char('p'), KeyModifiers::CONTROL) => Ok(Self::CommandPalette),
+                (KeyCode::Char('c'), KeyModifiers::ALT) => Ok(Self::ToggleMatchCase),
+                (KeyCode::Char('w'), KeyModifiers::ALT) => Ok(Self::ToggleWholeWord),
+                (KeyCode::Char('t'), KeyModifiers::CONTROL) => Ok(Self::StartJump),
+                (KeyCode::Char('t'), KeyModifiers::ALT) => Ok(Self::RepeatJump),
+                (KeyCode::Char('k'), KeyModifiers::CONTROL) => Ok(Self::DeleteSelection),
+                (KeyCode::Char('d'), KeyModifiers::CONTROL) => Ok(Self::DuplicateSelection),
+                (KeyCode::Char('z'), KeyModifiers::CONTROL) => Ok(Self::Undo),
+                (KeyCode::Char('y'), KeyModifiers::CONTROL) => Ok(Self::Redo),
+                (KeyCode::Char('z'), KeyModifiers::ALT) => Ok(Self::ToggleFold),
+                (KeyCode::Char('.'), KeyModifiers::ALT) => Ok(Self::RepeatEdit),
+                (KeyCode::Char('a'), KeyModifiers::CONTROL) => Ok(Self::AddToNumber(1)),
+                (KeyCode::Char('x'), KeyModifiers::CONTROL) => Ok(Self::AddToNumber(-1)),
+                (KeyCode::Up, KeyModifiers::SHIFT) => Ok(Self::Select(Direction::Up)),
+                (KeyCode::Down, KeyModifiers::SHIFT) => Ok(Self::Select(Direction::Down)),
+                (KeyCode::Left, KeyModifiers::SHIFT) => Ok(Self::Select(Direction::Left)),
+                (KeyCode::Right, KeyModifiers::SHIFT) => Ok(Self::Select(Direction::Right)),
+                (KeyCode::Home, KeyModifiers::SHIFT) => Ok(Self::Select(Direction::Home)),
+                (KeyCode::End, KeyModifiers::SHIFT) => Ok(Self::Select(Direction::End)),
+                (KeyCode::Left, modifiers)
+                    if modifiers == KeyModifiers::CONTROL | KeyModifiers::SHIFT =>
+                {
+                    Ok(Self::Select(Direction::WordLeft))
+                }
+                (KeyCode::Right, modifiers)
+                    if modifiers == KeyModifiers::CONTROL | KeyModifiers::SHIFT =>
+                {
+                    Ok(Self::Select(Direction::WordRight))
+                }
+                (KeyCode::Left, KeyModifiers::CONTROL) => Ok(Self::Move(Direction::WordLeft)),
+                (KeyCode::Right, KeyModifiers::CONTROL) => Ok(Self::Move(Direction::WordRight)),
+                (KeyCode::Up, KeyModifiers::CONTROL) => Ok(Self::Move(Direction::ParagraphUp)),
+                (KeyCode::Down, KeyModifiers::CONTROL) => Ok(Self::Move(Direction::ParagraphDown)),
+                (KeyCode::Down, KeyModifiers::ALT) => Ok(Self::AddCursorBelow),
+                (KeyCode::PageDown, KeyModifiers::CONTROL) => Ok(Self::NextFile),
                 (KeyCode::Up, _) => Ok(Self::Move(Direction::Up)),
                 (KeyCode::Down, _) => Ok(Self::Move(Direction::Down)),
                 (KeyCode::Left, _) => Ok(Self::Move(Direction::Left)),
@@ -60,8 +184,26 @@ impl TryFrom<Event> for EditorCommand {
                 (KeyCode::Char(ch), _) => Ok(Self::Insert(ch)),
                 (KeyCode::Backspace, _) => Ok(Self::DeleteLeft),
                 (KeyCode::Delete, _) => Ok(Self::DeleteRight),
+                (KeyCode::Insert, _) => Ok(Self::ToggleOverwrite),
+                (KeyCode::F(12), _) => Ok(Self::GoToDefinition),
+                (KeyCode::F(5), _) => Ok(Self::InsertDate),
+                (KeyCode::F(3), KeyModifiers::SHIFT) => Ok(Self::SearchPrevious),
+                (KeyCode::F(3), _) => Ok(Self::SearchNext),
+                (KeyCode::Enter, _) => Ok(Self::InsertNewline),
+                (KeyCode::Esc, _) => Ok(Self::Dismiss),
                 _ => Err(CommandError::UnsupportedKey(code)),
             },
+            Event::Mouse(MouseEvent {
+                kind: MouseEventKind::Down(MouseButton::Left),
+                column,
+                row,
+                ..
+            }) => Ok(Self::Click(Position {
+                col: usize::from(column),
+                row: usize::from(row),
+            })),
+            // many terminals send the line breaks of a paste as `\r`
+            Event::Paste(text) => Ok(Self::Paste(text.replace("\r\n", "\n").replace('\r', "\n"))),
             Event::Resize(width_u16, height_u16) => {
                 let height = usize::from(height_u16);
                 let width = usize::from(width_u16);
@@ -71,3 +213,26 @@ impl TryFrom<Event> for EditorCommand {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_key_toggles_overwrite() {
+        let event = Event::Key(KeyEvent::new(KeyCode::Insert, KeyModifiers::NONE));
+        assert_eq!(
+            EditorCommand::try_from(event).ok(),
+            Some(EditorCommand::ToggleOverwrite)
+        );
+    }
+
+    #[test]
+    fn pasted_line_breaks_become_newlines() {
+        let event = Event::Paste("one\rtwo\r\nthree".to_string());
+        assert_eq!(
+            EditorCommand::try_from(event).ok(),
+            Some(EditorCommand::Paste("one\ntwo\nthree".to_string()))
+        );
+    }
+}
@@ -0,0 +1,79 @@
+use super::terminal::Terminal;
+use super::text::truncate_to_width;
+use std::time::{Duration, Instant};
+
+const DEFAULT_DURATION: Duration = Duration::from_secs(5);
+
+pub struct MessageBar {
+    message: String,
+    set_time: Instant,
+    needs_redraw: bool,
+}
+
+impl MessageBar {
+    pub fn update_message(&mut self, message: &str) {
+        self.message = message.to_string();
+        self.set_time = Instant::now();
+        self.needs_redraw = true;
+    }
+
+    /// Clear the message once it has been shown for long enough.
+    pub fn tick(&mut self, now: Instant) {
+        if !self.message.is_empty() && now.duration_since(self.set_time) > DEFAULT_DURATION {
+            self.message.clear();
+            self.needs_redraw = true;
+        }
+    }
+
+    pub fn set_needs_redraw(&mut self) {
+        self.needs_redraw = true;
+    }
+
+    pub fn render(&mut self, row: usize, width: usize) -> Result<(), std::io::Error> {
+        if !self.needs_redraw {
+            return Ok(());
+        }
+        Terminal::print_row(row, &truncate_to_width(&self.message, width))?;
+        self.needs_redraw = false;
+        Ok(())
+    }
+}
+
+impl Default for MessageBar {
+    fn default() -> Self {
+        Self {
+            message: String::new(),
+            set_time: Instant::now(),
+            needs_redraw: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tick_clears_expired_message() {
+        let mut message_bar = MessageBar::default();
+        message_bar.update_message("File saved successfully.");
+        message_bar.needs_redraw = false;
+
+        message_bar.tick(Instant::now() + Duration::from_secs(6));
+
+        assert!(message_bar.message.is_empty());
+        assert!(message_bar.needs_redraw);
+    }
+
+    #[test]
+    fn tick_keeps_recent_message() {
+        let mut message_bar = MessageBar::default();
+        message_bar.update_message("File saved successfully.");
+        message_bar.needs_redraw = false;
+
+        message_bar.tick(Instant::now() + Duration::from_secs(1));
+
+        assert_eq!(message_bar.message, "File saved successfully.");
+        assert!(!message_bar.needs_redraw);
+    }
+}
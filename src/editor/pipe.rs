@@ -0,0 +1,56 @@
+use std::io::{Error, ErrorKind, Write};
+use std::process::{Command, Stdio};
+
+/// Run `command` through the shell with `input` on its stdin and return
+/// what it printed. A nonzero exit is an error carrying its stderr.
+pub fn run(command: &str, input: &str) -> Result<String, Error> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // feed stdin from another thread, the child may fill its stdout pipe
+    // before it has read all of its input
+    let mut stdin = child
+        .stdin
+        .take()
+        .ok_or_else(|| Error::other("stdin not captured"))?;
+    let input = input.to_string();
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+
+    let output = child.wait_with_output()?;
+    // a command that doesn't read its input closes the pipe early
+    match writer.join() {
+        Ok(Err(err)) if err.kind() != ErrorKind::BrokenPipe => return Err(err),
+        Ok(_) => {}
+        Err(_) => return Err(Error::other("writing to the command failed")),
+    }
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr.lines().next().unwrap_or_default();
+        return Err(Error::other(format!("{} {reason}", output.status)));
+    }
+    String::from_utf8(output.stdout).map_err(|err| Error::new(ErrorKind::InvalidData, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_replaces_input() -> Result<(), Error> {
+        assert_eq!(run("cat", "one\ntwo\n")?, "one\ntwo\n");
+        assert_eq!(run("tr a-z A-Z", "one\ntwo\n")?, "ONE\nTWO\n");
+        Ok(())
+    }
+
+    #[test]
+    fn nonzero_exit_is_an_error() {
+        let err = run("echo oops >&2; exit 3", "").unwrap_err();
+        assert!(err.to_string().contains("oops"));
+    }
+}
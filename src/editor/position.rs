@@ -1,5 +1,51 @@
-#[derive(Copy, Clone, Default, Debug, PartialEq)]
+use std::cmp::Ordering;
+
+/// A place in the text or on the screen. Positions are ordered as they
+/// are read: by row, then by column.
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq)]
 pub struct Position {
     pub col: usize,
     pub row: usize,
 }
+
+impl Position {
+    /// `a` and `b` as `(start, end)`, whichever order they came in.
+    pub fn normalize(a: Self, b: Self) -> (Self, Self) {
+        if b < a { (b, a) } else { (a, b) }
+    }
+}
+
+impl Ord for Position {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.row, self.col).cmp(&(other.row, other.col))
+    }
+}
+
+impl PartialOrd for Position {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ordered_by_row_then_column() {
+        let early = Position { col: 9, row: 0 };
+        let late = Position { col: 0, row: 1 };
+        assert!(early < late);
+        assert!(Position { col: 1, row: 1 } > late);
+        assert_eq!(early.max(late), late);
+        assert_eq!(early.min(late), early);
+    }
+
+    #[test]
+    fn normalize_swaps_reversed_pair() {
+        let start = Position { col: 4, row: 2 };
+        let end = Position { col: 1, row: 3 };
+        assert_eq!(Position::normalize(end, start), (start, end));
+        assert_eq!(Position::normalize(start, end), (start, end));
+    }
+}
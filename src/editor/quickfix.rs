@@ -0,0 +1,81 @@
+use std::fmt::{Display, Formatter};
+
+/// A place in a file, as printed by `grep -n` and friends.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Location {
+    pub file: String,
+    /// 1-based, as printed.
+    pub line: usize,
+    pub text: String,
+}
+
+impl Location {
+    /// Parse a `file:line:text` line. The text may contain colons of its
+    /// own; anything without a file name and line number is rejected.
+    pub fn parse(line: &str) -> Option<Self> {
+        let (file, rest) = line.split_once(':')?;
+        let (number, text) = rest.split_once(':')?;
+        let number = number.parse().ok().filter(|&number| number > 0)?;
+        (!file.is_empty()).then(|| Self {
+            file: file.to_string(),
+            line: number,
+            text: text.to_string(),
+        })
+    }
+}
+
+impl Display for Location {
+    fn fmt(&self, formatter: &mut Formatter) -> std::fmt::Result {
+        write!(formatter, "{}:{}:{}", self.file, self.line, self.text)
+    }
+}
+
+/// The locations in the output of a search command, skipping lines that
+/// aren't one, such as warnings.
+pub fn parse(output: &str) -> Vec<Location> {
+    output.lines().filter_map(Location::parse).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grep_output_becomes_locations() {
+        let output = "src/main.rs:3:fn main() {\ngrep: target: Is a directory\nREADME.md:10:\n";
+        assert_eq!(
+            parse(output),
+            vec![
+                Location {
+                    file: "src/main.rs".to_string(),
+                    line: 3,
+                    text: "fn main() {".to_string(),
+                },
+                Location {
+                    file: "README.md".to_string(),
+                    line: 10,
+                    text: String::new(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn colons_in_text_are_kept() {
+        let location = Location::parse("lib.rs:42:use std::io::{self, Write};").unwrap();
+        assert_eq!(location.file, "lib.rs");
+        assert_eq!(location.line, 42);
+        assert_eq!(location.text, "use std::io::{self, Write};");
+        assert_eq!(
+            location.to_string(),
+            "lib.rs:42:use std::io::{self, Write};"
+        );
+    }
+
+    #[test]
+    fn lines_without_a_number_are_not_locations() {
+        assert_eq!(Location::parse("notes: see below"), None);
+        assert_eq!(Location::parse(":3:text"), None);
+        assert_eq!(Location::parse("a.txt:0:text"), None);
+    }
+}
@@ -0,0 +1,111 @@
+use std::path::PathBuf;
+
+const MAX_RECENT: usize = 9;
+
+/// Most recently opened files, newest first.
+#[derive(Default, Debug, PartialEq)]
+pub struct RecentFiles {
+    paths: Vec<String>,
+}
+
+impl RecentFiles {
+    /// Read the list from the state file, starting empty if it is
+    /// missing or unreadable.
+    pub fn load() -> Self {
+        let paths = Self::state_file()
+            .and_then(|file| std::fs::read_to_string(file).ok())
+            .map(|contents| Self::parse(&contents))
+            .unwrap_or_default();
+        Self { paths }
+    }
+
+    pub fn save(&self) -> Result<(), std::io::Error> {
+        let Some(file) = Self::state_file() else {
+            return Ok(());
+        };
+        if let Some(dir) = file.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(file, self.paths.join("\n"))
+    }
+
+    fn parse(contents: &str) -> Vec<String> {
+        contents
+            .lines()
+            .filter(|line| !line.is_empty())
+            .take(MAX_RECENT)
+            .map(str::to_string)
+            .collect()
+    }
+
+    fn state_file() -> Option<PathBuf> {
+        let dir = std::env::var_os("XDG_STATE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| {
+                std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state"))
+            })?;
+        Some(dir.join("hecto").join("recent"))
+    }
+
+    /// Move `path` to the front of the list, dropping the oldest entry
+    /// once the list is full.
+    pub fn push(&mut self, path: &str) {
+        self.paths.retain(|existing| existing != path);
+        self.paths.insert(0, path.to_string());
+        self.paths.truncate(MAX_RECENT);
+    }
+
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.paths.get(index).map(String::as_str)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.paths.iter().map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_orders_newest_first() {
+        let mut recent = RecentFiles::default();
+        recent.push("a.rs");
+        recent.push("b.rs");
+        recent.push("c.rs");
+        assert_eq!(
+            recent.iter().collect::<Vec<_>>(),
+            vec!["c.rs", "b.rs", "a.rs"]
+        );
+    }
+
+    #[test]
+    fn push_same_path_twice_dedups() {
+        let mut recent = RecentFiles::default();
+        recent.push("a.rs");
+        recent.push("b.rs");
+        recent.push("a.rs");
+        assert_eq!(recent.iter().collect::<Vec<_>>(), vec!["a.rs", "b.rs"]);
+    }
+
+    #[test]
+    fn push_caps_length() {
+        let mut recent = RecentFiles::default();
+        for index in 0..=MAX_RECENT {
+            recent.push(&format!("{index}.rs"));
+        }
+        assert_eq!(recent.iter().count(), MAX_RECENT);
+        assert_eq!(recent.get(0), Some("9.rs"));
+        assert_eq!(recent.get(MAX_RECENT - 1), Some("1.rs"));
+    }
+
+    #[test]
+    fn parse_skips_empty_lines() {
+        assert_eq!(RecentFiles::parse("a.rs\n\nb.rs\n"), vec!["a.rs", "b.rs"]);
+    }
+}
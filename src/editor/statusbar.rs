@@ -0,0 +1,150 @@
+use super::documentstatus::DocumentStatus;
+use super::terminal::Terminal;
+use super::text::truncate_to_width;
+use super::theme::Theme;
+use unicode_width::UnicodeWidthStr;
+
+/// A colored line above the message bar describing the buffer.
+#[derive(Default)]
+pub struct StatusBar {
+    current_status: Option<DocumentStatus>,
+    needs_redraw: bool,
+    theme: Theme,
+}
+
+impl StatusBar {
+    pub fn new(theme: Theme) -> Self {
+        Self {
+            current_status: None,
+            needs_redraw: true,
+            theme,
+        }
+    }
+
+    pub fn update_status(&mut self, new_status: DocumentStatus) {
+        if self.current_status.as_ref() != Some(&new_status) {
+            self.current_status = Some(new_status);
+            self.needs_redraw = true;
+        }
+    }
+
+    pub fn set_needs_redraw(&mut self) {
+        self.needs_redraw = true;
+    }
+
+    pub fn render(&mut self, row: usize, width: usize) -> Result<(), std::io::Error> {
+        if !self.needs_redraw {
+            return Ok(());
+        }
+        let line = self.current_status.as_ref().map_or_else(
+            // the bar is colored even without a status
+            || " ".repeat(width),
+            |status| Self::status_line(status, width),
+        );
+        Terminal::print_colored_row(
+            row,
+            &line,
+            self.theme.status_bar_foreground,
+            self.theme.status_bar_background,
+        )?;
+        self.needs_redraw = false;
+        Ok(())
+    }
+
+    // file info on the left, indentation and position on the right
+    fn status_line(status: &DocumentStatus, width: usize) -> String {
+        let mut name = status.file_name_to_string();
+        let new_file = status.new_file_indicator_to_string();
+        if !new_file.is_empty() {
+            name = format!("{name} {new_file}");
+        }
+        let left = format!(
+            "{} - {} {}",
+            name,
+            status.line_count_to_string(),
+            status.modified_indicator_to_string()
+        );
+        let mut right = format!(
+            "{} | {} | {} | {} | {}",
+            status.line_ending,
+            status.mode_to_string(),
+            status.indent_to_string(),
+            status.column_to_string(),
+            status.position_indicator_to_string()
+        );
+        let trim = status.trim_to_string();
+        if !trim.is_empty() {
+            right = format!("{trim} | {right}");
+        }
+        // the cursor may not sit where expected on right-to-left text
+        let direction = status.direction_to_string();
+        if !direction.is_empty() {
+            right = format!("{direction} | {right}");
+        }
+        let whitespace = status.whitespace_to_string();
+        if !whitespace.is_empty() {
+            right = format!("{whitespace} | {right}");
+        }
+        let selection = status.selection_to_string();
+        if !selection.is_empty() {
+            right = format!("{selection} | {right}");
+        }
+        let padding = width.saturating_sub(left.width() + right.width());
+        let line = format!("{left}{:padding$}{right}", "");
+        truncate_to_width(&line, width)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::config::{Indent, IndentStyle};
+
+    #[test]
+    fn status_line_aligns_position_right() {
+        let status = DocumentStatus {
+            file_name: Some("notes.txt".to_string()),
+            total_lines: 12,
+            current_line_index: 2,
+            display_column: 7,
+            is_modified: true,
+            is_new_file: false,
+            indent: Indent {
+                style: IndentStyle::Tabs,
+                width: 8,
+            },
+            is_rtl: false,
+            overwrite: true,
+            selection: None,
+            line_ending: "CRLF",
+            whitespace: None,
+            trims_on_save: false,
+        };
+        let line = StatusBar::status_line(&status, 70);
+        assert_eq!(line.width(), 70);
+        assert!(line.starts_with("notes.txt - 12 lines (modified)"));
+        assert!(line.ends_with("CRLF | OVR | Tabs: 8 | Col 7 | 3/12"));
+
+        let status = DocumentStatus {
+            selection: Some((42, 3)),
+            ..status
+        };
+        let line = StatusBar::status_line(&status, 90);
+        assert!(line.ends_with("42 selected, 3 lines | CRLF | OVR | Tabs: 8 | Col 7 | 3/12"));
+
+        let status = DocumentStatus {
+            is_new_file: true,
+            is_modified: false,
+            ..status
+        };
+        let line = StatusBar::status_line(&status, 90);
+        assert!(line.starts_with("notes.txt [New File] - 12 lines"));
+
+        let status = DocumentStatus {
+            trims_on_save: true,
+            ..status
+        };
+        let line = StatusBar::status_line(&status, 100);
+        assert!(line.ends_with("Trim | CRLF | OVR | Tabs: 8 | Col 7 | 3/12"));
+    }
+}
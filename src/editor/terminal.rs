@@ -1,17 +1,85 @@
 use crossterm::cursor::MoveTo;
+use crossterm::event::{
+    DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+};
+use crossterm::style::{Color, Colors, Print, ResetColor, SetColors};
 use crossterm::terminal::{
     Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode,
     enable_raw_mode, size,
 };
-use crossterm::{Command, queue};
+use crossterm::{Command, execute, queue};
 use std::io::{Write, stdout};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// set while raw mode and the alternate screen are active, so that only
+// the first of several shutdown paths restores the terminal
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+// whether `initialize` got the alternate screen, see `ScreenMode`
+static ALTERNATE_SCREEN: AtomicBool = AtomicBool::new(false);
+
+/// Where the editor draws, decided once at startup.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScreenMode {
+    /// The alternate screen, which leaves the scrollback untouched.
+    Alternate,
+    /// The normal screen, for terminals without an alternate one. It is
+    /// cleared on exit instead, so whatever the user had on screen before
+    /// starting the editor is gone, not restored.
+    Main,
+}
+
+impl ScreenMode {
+    fn record(self) {
+        ALTERNATE_SCREEN.store(self == Self::Alternate, Ordering::SeqCst);
+    }
 
-#[derive(Copy, Clone, Default)]
+    /// The mode `initialize` entered.
+    pub fn recorded() -> Self {
+        if ALTERNATE_SCREEN.load(Ordering::SeqCst) {
+            Self::Alternate
+        } else {
+            Self::Main
+        }
+    }
+
+    // queue the inverse of entering this mode
+    fn restore<W: Write>(self, out: &mut W) -> Result<(), std::io::Error> {
+        match self {
+            Self::Alternate => queue!(out, LeaveAlternateScreen),
+            Self::Main => queue!(out, Clear(ClearType::All), MoveTo(0, 0)),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Default, Debug, PartialEq)]
 pub struct Size {
     pub width: usize,
     pub height: usize,
 }
 
+impl Size {
+    /// The number of cells.
+    pub fn area(self) -> usize {
+        self.width * self.height
+    }
+
+    /// Index of the bottom row, 0 for an empty size.
+    pub fn last_row(self) -> usize {
+        self.height.saturating_sub(1)
+    }
+
+    /// Index of the rightmost column, 0 for an empty size.
+    pub fn last_col(self) -> usize {
+        self.width.saturating_sub(1)
+    }
+
+    /// The rows left for text once `reserved` rows at the bottom are
+    /// taken by the bars.
+    pub fn text_height(self, reserved: usize) -> usize {
+        self.height.saturating_sub(reserved)
+    }
+}
+
 use crate::editor::position::Position;
 
 pub struct Terminal {}
@@ -26,29 +94,50 @@ impl Terminal {
 
     pub fn initialize() -> Result<(), std::io::Error> {
         enable_raw_mode()?;
-        Self::enter_alternate_screen()?;
+        ACTIVE.store(true, Ordering::SeqCst);
+        Self::enter_screen().record();
+        Self::queue_command(EnableMouseCapture)?;
+        Self::queue_command(EnableBracketedPaste)?;
         Self::clear_screen()?;
         Self::move_cursor_to(Position::default())?;
         Self::flush()?;
         Ok(())
     }
 
+    /// Restore the terminal. Only the first call after `initialize` does
+    /// anything, since a signal, a panic and `Drop` may all try.
     pub fn terminate() -> Result<(), std::io::Error> {
-        Self::leave_alternate_screen()?;
-        Self::show_cursor()?;
+        if !Self::restore_once(&ACTIVE, &mut stdout())? {
+            return Ok(());
+        }
         Self::flush()?;
         disable_raw_mode()?;
         Ok(())
     }
 
-    fn enter_alternate_screen() -> Result<(), std::io::Error> {
-        Self::queue_command(EnterAlternateScreen)?;
-        Ok(())
+    // queue what undoes `initialize` if `active` is still set, clearing
+    // it, and return whether it was
+    fn restore_once<W: Write>(active: &AtomicBool, out: &mut W) -> Result<bool, std::io::Error> {
+        if !active.swap(false, Ordering::SeqCst) {
+            return Ok(false);
+        }
+        queue!(out, DisableBracketedPaste, DisableMouseCapture)?;
+        ScreenMode::recorded().restore(out)?;
+        queue!(out, crossterm::cursor::Show)?;
+        Ok(true)
     }
 
-    fn leave_alternate_screen() -> Result<(), std::io::Error> {
-        Self::queue_command(LeaveAlternateScreen)?;
-        Ok(())
+    // switch to the alternate screen, or stay on the main one where that
+    // fails or TERM is `dumb`; other terminals without an alternate screen
+    // usually accept the escape and ignore it, so they are not detected
+    // and exit leaves the editor's last frame behind
+    fn enter_screen() -> ScreenMode {
+        let dumb = std::env::var("TERM").is_ok_and(|term| term == "dumb");
+        if !dumb && execute!(stdout(), EnterAlternateScreen).is_ok() {
+            ScreenMode::Alternate
+        } else {
+            ScreenMode::Main
+        }
     }
 
     pub fn clear_screen() -> Result<(), std::io::Error> {
@@ -66,7 +155,33 @@ impl Terminal {
     }
 
     pub fn print(string: &str) -> Result<(), std::io::Error> {
-        Self::queue_command(crossterm::style::Print(string))
+        Self::queue_command(Print(string))
+    }
+
+    /// Print `text` in the given colors, keeping the current ones where
+    /// `None`, and reset the colors after.
+    pub fn print_styled(
+        text: &str,
+        foreground: Option<Color>,
+        background: Option<Color>,
+    ) -> Result<(), std::io::Error> {
+        Self::queue_styled(&mut stdout(), text, foreground, background)
+    }
+
+    fn queue_styled<W: Write>(
+        out: &mut W,
+        text: &str,
+        foreground: Option<Color>,
+        background: Option<Color>,
+    ) -> Result<(), std::io::Error> {
+        if foreground.is_none() && background.is_none() {
+            return queue!(out, Print(text));
+        }
+        let colors = Colors {
+            foreground,
+            background,
+        };
+        queue!(out, SetColors(colors), Print(text), ResetColor)
     }
 
     pub fn print_row(row: usize, line: &str) -> Result<(), std::io::Error> {
@@ -76,13 +191,28 @@ impl Terminal {
         Ok(())
     }
 
+    pub fn print_colored_row(
+        row: usize,
+        line: &str,
+        foreground: Color,
+        background: Color,
+    ) -> Result<(), std::io::Error> {
+        Self::move_cursor_to(Position { col: 0, row })?;
+        Self::clear_line()?;
+        Self::print_styled(line, Some(foreground), Some(background))
+    }
+
     pub fn begin_frame() -> Result<(), std::io::Error> {
         Self::hide_cursor()
     }
 
-    pub fn end_frame(cursor: Position) -> Result<(), std::io::Error> {
+    /// Place the cursor, which stays hidden unless `show_cursor` is set,
+    /// and flush the frame.
+    pub fn end_frame(cursor: Position, show_cursor: bool) -> Result<(), std::io::Error> {
         Self::move_cursor_to(cursor)?;
-        Self::show_cursor()?;
+        if show_cursor {
+            Self::show_cursor()?;
+        }
         Self::flush()
     }
 
@@ -103,3 +233,76 @@ impl Terminal {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn helpers_on_regular_size() {
+        let size = Size {
+            width: 80,
+            height: 24,
+        };
+        assert_eq!(size.area(), 1920);
+        assert_eq!(size.last_row(), 23);
+        assert_eq!(size.last_col(), 79);
+        assert_eq!(size.text_height(1), 23);
+    }
+
+    #[test]
+    fn terminate_is_idempotent() -> Result<(), std::io::Error> {
+        let active = AtomicBool::new(true);
+        let mut out = Vec::new();
+        assert!(Terminal::restore_once(&active, &mut out)?);
+        let first = String::from_utf8_lossy(&out).into_owned();
+        assert!(first.contains("\x1b[?2004l"));
+        assert!(first.contains("\x1b[?25h"));
+
+        let mut out = Vec::new();
+        assert!(!Terminal::restore_once(&active, &mut out)?);
+        assert!(out.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn helpers_on_zero_size() {
+        let size = Size::default();
+        assert_eq!(size.area(), 0);
+        assert_eq!(size.last_row(), 0);
+        assert_eq!(size.last_col(), 0);
+        assert_eq!(size.text_height(1), 0);
+    }
+
+    #[test]
+    fn restore_undoes_the_recorded_mode() -> Result<(), std::io::Error> {
+        ScreenMode::Main.record();
+        assert_eq!(ScreenMode::recorded(), ScreenMode::Main);
+        let mut out = Vec::new();
+        ScreenMode::recorded().restore(&mut out)?;
+        let main = String::from_utf8_lossy(&out).into_owned();
+        assert!(main.contains("\x1b[2J"));
+
+        ScreenMode::Alternate.record();
+        assert_eq!(ScreenMode::recorded(), ScreenMode::Alternate);
+        let mut out = Vec::new();
+        ScreenMode::recorded().restore(&mut out)?;
+        let alternate = String::from_utf8_lossy(&out).into_owned();
+        assert!(alternate.contains("\x1b[?1049l"));
+        assert!(!alternate.contains("\x1b[2J"));
+        Ok(())
+    }
+
+    #[test]
+    fn styled_text_sets_colors_prints_and_resets() -> Result<(), std::io::Error> {
+        let mut out = Vec::new();
+        Terminal::queue_styled(&mut out, "hi", Some(Color::Red), Some(Color::Blue))?;
+        let styled = String::from_utf8_lossy(&out).into_owned();
+        assert_eq!(styled, "\x1b[38;5;9;48;5;12mhi\x1b[0m");
+
+        let mut out = Vec::new();
+        Terminal::queue_styled(&mut out, "hi", None, None)?;
+        assert_eq!(out, b"hi");
+        Ok(())
+    }
+}
@@ -0,0 +1,98 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Cut `text` down to at most `width` terminal cells without splitting
+/// a grapheme.
+pub fn truncate_to_width(text: &str, width: usize) -> String {
+    let mut used = 0;
+    text.graphemes(true)
+        .take_while(|grapheme| {
+            used += grapheme.width();
+            used <= width
+        })
+        .collect()
+}
+
+/// Cut `text` down to `width` cells like `truncate_to_width`, then fill
+/// it up with spaces to exactly `width` cells.
+pub fn pad_to_width(text: &str, width: usize) -> String {
+    let mut padded = truncate_to_width(text, width);
+    let used = padded.width();
+    padded.extend(std::iter::repeat_n(' ', width - used));
+    padded
+}
+
+/// Replace the tabs in `text` with spaces up to the next multiple of
+/// `tab_width` cells, with the text starting at column `start_col`.
+/// Columns count from 0 again after each line break.
+pub fn expand_tabs(text: &str, start_col: usize, tab_width: usize) -> String {
+    let tab_width = tab_width.max(1);
+    let mut col = start_col;
+    let mut expanded = String::with_capacity(text.len());
+    for grapheme in text.graphemes(true) {
+        match grapheme {
+            "\t" => {
+                let spaces = tab_width - col % tab_width;
+                expanded.extend(std::iter::repeat_n(' ', spaces));
+                col += spaces;
+            }
+            "\n" | "\r\n" => {
+                expanded.push_str(grapheme);
+                col = 0;
+            }
+            _ => {
+                expanded.push_str(grapheme);
+                col += grapheme.width();
+            }
+        }
+    }
+    expanded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_text_is_unchanged() {
+        assert_eq!(truncate_to_width("hecto", 10), "hecto");
+        assert_eq!(truncate_to_width("hecto", 5), "hecto");
+    }
+
+    #[test]
+    fn ascii_text_is_cut_at_width() {
+        assert_eq!(truncate_to_width("hecto editor", 5), "hecto");
+        assert_eq!(truncate_to_width("hecto", 0), "");
+    }
+
+    #[test]
+    fn wide_graphemes_never_exceed_width() {
+        let text = "ＡＢＣ";
+        assert_eq!(truncate_to_width(text, 4), "ＡＢ");
+        // a wide glyph that does not fit is dropped rather than split
+        assert_eq!(truncate_to_width(text, 5), "ＡＢ");
+        assert_eq!(truncate_to_width("a👋b", 2), "a");
+        assert_eq!(truncate_to_width("a👋b", 3), "a👋");
+    }
+
+    #[test]
+    fn combining_marks_stay_with_their_base() {
+        assert_eq!(truncate_to_width("e\u{301}x", 1), "e\u{301}");
+    }
+
+    #[test]
+    fn tabs_expand_to_the_next_stop() {
+        assert_eq!(expand_tabs("a\tb", 0, 4), "a   b");
+        assert_eq!(expand_tabs("\tb", 0, 4), "    b");
+        assert_eq!(expand_tabs("a\tb", 2, 4), "a b");
+        assert_eq!(expand_tabs("Ｂ\t\n\tc", 0, 4), "Ｂ  \n    c");
+    }
+
+    #[test]
+    fn padding_fills_to_exact_width() {
+        assert_eq!(pad_to_width("ab", 4), "ab  ");
+        assert_eq!(pad_to_width("abcdef", 4), "abcd");
+        // the wide glyph that does not fit leaves a space behind
+        assert_eq!(pad_to_width("aＢ", 2), "a ");
+    }
+}
@@ -0,0 +1,85 @@
+use crossterm::style::Color;
+
+/// The colors the editor draws with, so that no component hardcodes its
+/// own.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Theme {
+    pub status_bar_foreground: Color,
+    pub status_bar_background: Color,
+    /// Background of selected text.
+    pub selection: Color,
+    /// Background of the match the search is on.
+    pub search_match: Color,
+    /// Background of the bracket pair around the cursor.
+    pub matching_bracket: Color,
+    /// Background of the cells under secondary cursors.
+    pub extra_cursor: Color,
+    /// Background of trailing spaces and tabs.
+    pub trailing_whitespace: Color,
+    /// Background of text past the maximum line length.
+    pub overflow: Color,
+    /// Background of words missing from the dictionary.
+    pub misspelled: Color,
+    /// Background of the minimap rows for the lines in view.
+    pub minimap_view: Color,
+}
+
+impl Theme {
+    pub const DARK: Self = Self {
+        status_bar_foreground: Color::Black,
+        status_bar_background: Color::Grey,
+        selection: Color::DarkBlue,
+        search_match: Color::DarkYellow,
+        matching_bracket: Color::DarkMagenta,
+        extra_cursor: Color::Grey,
+        trailing_whitespace: Color::DarkRed,
+        overflow: Color::DarkGrey,
+        misspelled: Color::DarkCyan,
+        minimap_view: Color::DarkGrey,
+    };
+
+    pub const LIGHT: Self = Self {
+        status_bar_foreground: Color::White,
+        status_bar_background: Color::DarkGrey,
+        selection: Color::Cyan,
+        search_match: Color::Yellow,
+        matching_bracket: Color::Magenta,
+        extra_cursor: Color::DarkGrey,
+        trailing_whitespace: Color::Red,
+        overflow: Color::Grey,
+        misspelled: Color::Green,
+        minimap_view: Color::Grey,
+    };
+
+    /// The built-in theme called `name`, if there is one.
+    pub fn named(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(Self::DARK),
+            "light" => Some(Self::LIGHT),
+            _ => None,
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::DARK
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_theme_tells_selection_from_search_match() {
+        let theme = Theme::default();
+        assert_ne!(theme.selection, theme.search_match);
+    }
+
+    #[test]
+    fn themes_are_found_by_name() {
+        assert_eq!(Theme::named("light"), Some(Theme::LIGHT));
+        assert_eq!(Theme::named("neon"), None);
+    }
+}
@@ -1,40 +1,129 @@
+use super::config::{Config, Indent, IndentStyle};
+use super::documentstatus::DocumentStatus;
 use super::editorcommand::{Direction, EditorCommand};
 use super::terminal::{Size, Terminal};
+use super::text::{expand_tabs, pad_to_width, truncate_to_width};
+use crossterm::style::Color;
 use std::cmp::{max, min};
+use std::ops::Range;
+use std::time::Instant;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
 mod buffer;
+mod diff;
+mod fold;
+mod gutter;
+mod highlight;
 mod line;
+mod minimap;
+mod search;
+mod spelling;
+mod undo;
 
 use crate::editor::position::Position;
 use buffer::Buffer;
+pub use buffer::WhitespaceReport;
+use diff::DiffStats;
+use fold::{Folds, VisibleRow};
+use gutter::Gutter;
+use highlight::Highlight;
+use line::{Glyphs, Line};
+pub use search::SearchOptions;
+use spelling::Dictionary;
+use undo::{EditKind, Restore, Snapshot, UndoStack};
+
+/// Where the cursor was before a search started, so that it can be
+/// restored when the search is dismissed.
+struct SearchInfo {
+    prev_position: Position,
+    prev_scroll_offset: Position,
+    // graphemes in the match at the cursor, 0 without one
+    match_len: usize,
+    // all matches of the query and options they were found with, kept
+    // until the query changes
+    matches: Vec<Position>,
+    matches_for: Option<(String, SearchOptions)>,
+}
 
+#[allow(clippy::struct_excessive_bools)]
 pub struct View {
     buffer: Buffer,
     needs_redraw: bool,
     size: Size,
     cursor_position: Position,
     scroll_offset: Position,
+    // the other end of the selection, the cursor being the first
+    selection_anchor: Option<Position>,
+    // secondary cursors, all below the primary one and sorted by row
+    extra_cursors: Vec<Position>,
+    overwrite: bool,
+    last_jump: Option<char>,
+    // the latest command that changed the text, for RepeatEdit
+    last_edit: Option<EditorCommand>,
+    show_welcome: bool,
+    search_info: Option<SearchInfo>,
+    undo: UndoStack,
+    folds: Folds,
+    // detected from the buffer, or the configured default
+    indent: Indent,
+    // words outside it are highlighted; `None` unless configured
+    dictionary: Option<Dictionary>,
+    // the buffer lists `file:line:text` locations for Enter to open
+    jump_list: bool,
+    // as of the last save or whitespace check, `None` before any
+    whitespace_report: Option<WhitespaceReport>,
+    // the query and options of the latest search, for repeating it
+    last_search: Option<(String, SearchOptions)>,
+    // set by the editor while its command bar takes the input
+    prompt_open: bool,
+    // whether saving trims trailing blank lines, overriding the config
+    // for this buffer; `None` follows the config
+    trim_on_save: Option<bool>,
+    config: Config,
 }
 
 const NAME: &str = env!("CARGO_PKG_NAME");
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 impl View {
-    pub fn new(size: Size) -> Self {
+    pub fn new(size: Size, config: Config) -> Self {
         Self {
             buffer: Buffer::default(),
             needs_redraw: true,
             size,
             cursor_position: Position { col: 0, row: 0 },
             scroll_offset: Position { col: 0, row: 0 },
+            selection_anchor: None,
+            extra_cursors: Vec::new(),
+            overwrite: false,
+            last_jump: None,
+            last_edit: None,
+            show_welcome: true,
+            search_info: None,
+            undo: UndoStack::with_limit(config.undo_limit),
+            folds: Folds::default(),
+            indent: config.indent,
+            // an unreadable word list just leaves spell checking off
+            dictionary: config
+                .dictionary
+                .as_deref()
+                .and_then(|path| Dictionary::load(path).ok()),
+            jump_list: false,
+            whitespace_report: None,
+            last_search: None,
+            prompt_open: false,
+            trim_on_save: None,
+            config,
         }
     }
     pub fn render(&mut self) -> Result<(), std::io::Error> {
-        if !self.needs_redraw {
+        self.sync_folds();
+        if !self.needs_redraw || self.size.area() == 0 {
             return Ok(());
         }
 
-        if self.buffer.is_empty() {
+        if self.should_render_welcome() {
             self.render_welcome_message()?;
         } else {
             self.render_buffer()?;
@@ -42,664 +131,3415 @@ impl View {
         Ok(())
     }
 
-    pub fn load(&mut self, file_name: &str) {
-        if let Ok(buffer) = Buffer::load(file_name) {
-            self.buffer = buffer;
+    fn should_render_welcome(&self) -> bool {
+        self.show_welcome && self.buffer.is_empty()
+    }
+
+    /// Load `file_name`, or start an empty buffer with that name if the
+    /// file doesn't exist. Any other error, such as a lack of permission,
+    /// is returned and leaves the current buffer in place.
+    pub fn load(&mut self, file_name: &str) -> Result<(), std::io::Error> {
+        self.buffer = Self::loaded_or_new(
+            Buffer::load_capped(file_name, self.config.max_lines),
+            file_name,
+        )?;
+        if self.config.trim_trailing_blank_lines {
+            self.buffer.trim_trailing_blank_lines();
+        }
+        self.reset_for_new_buffer();
+        Ok(())
+    }
+
+    // a file that isn't there yet is a new file rather than an error
+    fn loaded_or_new(
+        loaded: Result<Buffer, std::io::Error>,
+        file_name: &str,
+    ) -> Result<Buffer, std::io::Error> {
+        match loaded {
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                Ok(Buffer::new_file(file_name))
+            }
+            result => result,
         }
+    }
+
+    /// Whether only the first `max_lines` lines of the file were loaded.
+    pub fn is_truncated(&self) -> bool {
+        self.buffer.is_truncated()
+    }
+
+    /// Load an unnamed buffer from `reader`; it can't be saved until it
+    /// is given a file name.
+    pub fn load_from_reader<R: std::io::Read>(&mut self, reader: R) -> Result<(), std::io::Error> {
+        self.buffer = Buffer::from_reader(reader)?;
+        self.reset_for_new_buffer();
+        Ok(())
+    }
+
+    /// Show `text`, one location per line, as a list to jump from.
+    pub fn load_jump_list(&mut self, text: &str) -> Result<(), std::io::Error> {
+        self.load_from_reader(text.as_bytes())?;
+        self.jump_list = true;
+        Ok(())
+    }
+
+    pub fn is_jump_list(&self) -> bool {
+        self.jump_list
+    }
+
+    /// The text of the line with the cursor.
+    pub fn current_line(&self) -> String {
+        self.buffer
+            .get_line(self.cursor_position.row)
+            .map(ToString::to_string)
+            .unwrap_or_default()
+    }
+
+    /// The whole buffer, lines joined with `\n`.
+    #[cfg(test)]
+    pub fn text(&self) -> String {
+        self.buffer
+            .lines_in_range(0..self.buffer.num_lines())
+            .map(|(_, line)| line.to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// The cursor as a grapheme index into its line, unlike
+    /// `get_cursor_position`, which is on screen.
+    #[cfg(test)]
+    pub fn text_position(&self) -> Position {
+        self.cursor_position
+    }
+
+    fn reset_for_new_buffer(&mut self) {
+        self.cursor_position = Position::default();
+        self.scroll_offset = Position::default();
+        self.selection_anchor = None;
+        self.extra_cursors.clear();
+        self.undo = UndoStack::with_limit(self.config.undo_limit);
+        self.folds.clear();
+        self.trim_on_save = None;
+        self.whitespace_report = self
+            .config
+            .check_whitespace
+            .then(|| self.buffer.whitespace_issues());
+        self.indent = self
+            .buffer
+            .detect_indent(self.config.tab_width)
+            .unwrap_or(self.config.indent);
         self.needs_redraw = true;
     }
 
-    pub fn resize(&mut self, to: Size) {
-        self.size = to;
-        // we need to ensure that the cursor is always in view
-        self.scroll_offset = self.update_scroll_offset(to);
+    pub fn get_status(&self) -> DocumentStatus {
+        DocumentStatus {
+            file_name: self.buffer.file_name().map(str::to_string),
+            total_lines: self.buffer.num_lines(),
+            current_line_index: self.cursor_position.row,
+            display_column: self.display_column(),
+            is_modified: self.buffer.is_dirty(),
+            is_new_file: self.buffer.is_new_file(),
+            indent: self.indent,
+            is_rtl: self
+                .buffer
+                .get_line(self.cursor_position.row)
+                .is_some_and(Line::is_rtl),
+            overwrite: self.overwrite,
+            selection: self
+                .selection()
+                .map(|(start, end)| self.buffer.selection_stats(start, end)),
+            line_ending: self.buffer.line_ending().name(),
+            whitespace: self.whitespace_report,
+            trims_on_save: self.trims_on_save(),
+        }
+    }
+
+    /// Append the lines of another file at the end of the buffer.
+    pub fn append_file(&mut self, file_name: &str) -> Result<(), std::io::Error> {
+        let other = Buffer::load(file_name)?;
+        self.record_edit(EditKind::Other);
+        self.buffer.append(other);
         self.needs_redraw = true;
+        Ok(())
     }
 
-    pub fn insert(&mut self, ch: char) {
-        let at = self.cursor_position;
-        let old_line_length = self.buffer.line_len(at.row);
+    pub fn save(&mut self) -> Result<(), std::io::Error> {
+        // a save that is bound to fail must not trim either
+        self.buffer.check_saveable()?;
+        self.trim_before_save();
+        self.buffer.save()?;
+        if self.config.check_whitespace {
+            self.check_whitespace();
+        }
+        Ok(())
+    }
+
+    /// Whether saving collapses trailing blank lines into one.
+    pub fn trims_on_save(&self) -> bool {
+        self.trim_on_save
+            .unwrap_or(self.config.trim_trailing_blank_lines)
+    }
 
-        self.buffer.insert(self.cursor_position, ch);
+    /// Flip `trims_on_save` for this buffer alone.
+    pub fn toggle_trim_on_save(&mut self) {
+        self.trim_on_save = Some(!self.trims_on_save());
+    }
 
-        if self.buffer.line_len(at.row) > old_line_length {
-            self.move_cursor(&Direction::Right);
+    fn trim_before_save(&mut self) {
+        if !self.trims_on_save() || !self.buffer.can_trim_trailing_blank_lines() {
+            return;
         }
+        self.record_edit(EditKind::Other);
+        self.buffer.trim_trailing_blank_lines();
+        let end = self.buffer.end();
+        for cursor in &mut self.extra_cursors {
+            *cursor = min(*cursor, end);
+        }
+        self.extra_cursors.dedup();
+        self.sync_folds();
+        self.move_cursor_to(min(self.cursor_position, end));
+    }
+
+    /// Look for mixed indentation and trailing whitespace, keeping the
+    /// result for the status bar.
+    pub fn check_whitespace(&mut self) -> WhitespaceReport {
+        let report = self.buffer.whitespace_issues();
+        self.whitespace_report = Some(report);
+        report
+    }
 
+    pub fn is_dirty(&self) -> bool {
+        self.buffer.is_dirty()
+    }
+
+    /// See `Buffer::recheck_dirty`.
+    pub fn recheck_dirty(&mut self) -> bool {
+        self.buffer.recheck_dirty()
+    }
+
+    /// A summary of what saving writes, like "3 lines, 20 characters,
+    /// 24 bytes".
+    pub fn size_summary(&self) -> String {
+        format!(
+            "{} lines, {} characters, {} bytes",
+            self.buffer.num_lines(),
+            self.buffer.grapheme_count(),
+            self.buffer.byte_len()
+        )
+    }
+
+    /// How the buffer differs from its file on disk.
+    pub fn diff_against_disk(&self) -> Result<DiffStats, std::io::Error> {
+        self.buffer.diff_against_disk()
+    }
+
+    pub fn has_file_name(&self) -> bool {
+        self.buffer.has_file_name()
+    }
+
+    pub fn is_save_target_missing(&self) -> bool {
+        self.buffer.is_save_target_missing()
+    }
+
+    pub fn resize(&mut self, to: Size) {
+        self.size = to;
+        // we need to ensure that the cursor is always in view
+        self.scroll_offset = self.update_scroll_offset(to);
         self.needs_redraw = true;
     }
 
-    pub fn delete_left(&mut self) {
-        if self.cursor_position.col == 0 {
-            // nothing to delete
+    pub fn insert(&mut self, ch: char) {
+        if let Some(close) = Self::closing_delimiter(ch)
+            && self.extra_cursors.is_empty()
+            && self.surround_selection(ch, close)
+        {
             return;
         }
+        self.record_edit(EditKind::Insert);
+        // typing replaces the selection, which with several cursors is
+        // only dropped
+        if self.extra_cursors.is_empty() {
+            self.remove_selection();
+        }
+        self.selection_anchor = None;
+        // bottom-to-top, so an edit never shifts a cursor still to be
+        // processed
+        for index in (0..self.extra_cursors.len()).rev() {
+            let advance = self.insert_at(self.extra_cursors[index], ch);
+            self.extra_cursors[index].col += advance;
+        }
+        if self.insert_at(self.cursor_position, ch) > 0 {
+            self.move_cursor(&Direction::Right);
+        }
+        self.needs_redraw = true;
+    }
 
-        // move left, then remove at column
-        self.move_cursor(&Direction::Left);
-        let is_deleted = self.buffer.delete(self.cursor_position);
-        if is_deleted {
-            self.needs_redraw = true;
+    // the delimiter that typing `open` over a selection pairs it with
+    fn closing_delimiter(open: char) -> Option<char> {
+        match open {
+            '(' => Some(')'),
+            '[' => Some(']'),
+            '{' => Some('}'),
+            '"' => Some('"'),
+            _ => None,
         }
     }
 
-    pub fn delete_right(&mut self) {
-        let is_deleted = self.buffer.delete(self.cursor_position);
-        if is_deleted {
+    // wrap the selection in `open` and `close`, keeping them selected
+    fn surround_selection(&mut self, open: char, close: char) -> bool {
+        let Some((start, end)) = self.selection() else {
+            return false;
+        };
+        self.record_edit(EditKind::Other);
+        let Some(end) = self.buffer.surround(start, end, open, close) else {
+            return false;
+        };
+        if self.cursor_position == start {
+            self.selection_anchor = Some(end);
             self.needs_redraw = true;
+        } else {
+            self.selection_anchor = Some(start);
+            self.move_cursor_to(end);
         }
+        true
     }
 
-    pub fn handle_command(&mut self, command: EditorCommand) {
-        match command {
-            EditorCommand::Move(direction) => self.move_cursor(&direction),
-            EditorCommand::Resize(size) => self.resize(size),
-            EditorCommand::Insert(ch) => self.insert(ch),
-            EditorCommand::DeleteLeft => self.delete_left(),
-            EditorCommand::DeleteRight => self.delete_right(),
-            EditorCommand::Quit => {}
+    /// Insert `text`, which may span several lines, at the cursor and
+    /// move past it.
+    pub fn insert_str(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
         }
+        self.record_edit(EditKind::Other);
+        self.remove_selection();
+        let end = self.buffer.insert_text(self.cursor_position, text);
+        self.move_cursor_to(end);
     }
 
-    pub fn move_cursor(&mut self, direction: &Direction) {
-        self.cursor_position = self.update_cursor_position(direction);
-        self.scroll_offset = self.update_scroll_offset(self.size);
-        self.needs_redraw = true;
+    /// Insert the closing brackets for those left open before the cursor
+    /// on its line, innermost first.
+    pub fn balance_brackets(&mut self) {
+        let Position { col, row } = self.cursor_position;
+        let closers = self
+            .buffer
+            .get_line(row)
+            .map(|line| line.missing_closers(col))
+            .unwrap_or_default();
+        self.selection_anchor = None;
+        self.insert_str(&closers);
     }
 
-    pub fn get_cursor_position(&self) -> Position {
-        let absolute = self.buffer.grid_position_of(self.cursor_position);
-        let offset = self.scroll_offset;
-        Position {
-            col: absolute.col.saturating_sub(offset.col),
-            row: absolute.row.saturating_sub(offset.row),
+    /// Insert pasted `text` like `insert_str`, first expanding its tabs if
+    /// `paste_tabs_to_spaces` is set and the buffer indents with spaces.
+    pub fn paste(&mut self, text: &str) {
+        if !self.config.paste_tabs_to_spaces || self.indent.style != IndentStyle::Spaces {
+            self.insert_str(text);
+            return;
         }
+        // the text goes where the selection starts, if there is one
+        let at = self
+            .selection()
+            .map_or(self.cursor_position, |(start, _)| start);
+        let before = self
+            .buffer
+            .get_line(at.row)
+            .map(|line| line.text(0..at.col))
+            .unwrap_or_default();
+        let tab_width = self.config.tab_width;
+        let start_col = expand_tabs(&before, 0, tab_width).width();
+        self.insert_str(&expand_tabs(text, start_col, tab_width));
     }
 
-    fn update_cursor_position(&self, direction: &Direction) -> Position {
-        let Position { mut row, mut col } = self.cursor_position;
-        match direction {
-            Direction::Left => {
-                col = col.saturating_sub(1);
-            }
-            Direction::Right => {
-                col = col.saturating_add(1);
-            }
-            Direction::Up => {
-                row = row.saturating_sub(1);
-            }
-            Direction::Down => {
-                row = row.saturating_add(1);
-            }
-            Direction::Home => {
-                col = 0;
-            }
-            Direction::End => {
-                // Caret at end: allow position after last grapheme
-                col = self.buffer.line_len(row);
-            }
-            Direction::PageUp => {
-                row = row.saturating_sub(self.size.height);
-            }
-            Direction::PageDown => {
-                row = row.saturating_add(self.size.height);
+    // returns how many graphemes a cursor at `at` advances past the new
+    // char
+    fn insert_at(&mut self, at: Position, ch: char) -> usize {
+        // in overwrite mode we replace the grapheme under the cursor,
+        // past the end of the line we fall back to a regular insert
+        if self.overwrite {
+            let old_line_length = self.buffer.line_len(at.row);
+            if self.buffer.replace(at, ch) {
+                // a replacement keeps the line length, unless the char
+                // merged into the previous grapheme
+                let kept_length = self.buffer.line_len(at.row) >= old_line_length;
+                return usize::from(kept_length);
             }
         }
-        // Ensure we do not go out of bounds. Allow caret at end of line.
-        row = min(self.buffer.num_lines().saturating_sub(1), row);
-        col = min(self.buffer.line_len(row), col);
-        Position { col, row }
+        self.buffer.insert_returning_advance(at, ch)
     }
 
-    fn update_scroll_offset(&self, size: Size) -> Position {
-        // we need to ensure that the cursor is always in view
-        let Size { height, width } = size;
-        let Position { row, col } = self.cursor_position;
-        let position = self.buffer.grid_position_of(Position { col, row });
+    /// Add a secondary cursor on the line below the lowest cursor, at
+    /// the column of the primary one.
+    pub fn add_cursor_below(&mut self) {
+        let lowest = self.extra_cursors.last().unwrap_or(&self.cursor_position);
+        let row = lowest.row + 1;
+        if row >= self.buffer.num_lines() {
+            return;
+        }
+        let col = min(self.cursor_position.col, self.buffer.line_len(row));
+        self.extra_cursors.push(Position { col, row });
+        self.needs_redraw = true;
+    }
 
-        // Two conditions:
-        // (1): dy < row
-        // (2): dy + height > row
-        let dy = max(
-            min(self.scroll_offset.row, row),
-            row.saturating_sub(height.saturating_sub(1)),
-        );
-        // Two conditions:
-        // (1): dx < col_pos
-        // (2): dx + width > col_pos
-        let dx = max(
-            min(self.scroll_offset.col, position.col),
-            position.col.saturating_sub(width.saturating_sub(1)),
+    pub fn insert_newline(&mut self) {
+        self.record_edit(EditKind::Other);
+        self.remove_selection();
+        let position = self.buffer.split_line(self.cursor_position);
+        self.move_cursor_to(position);
+    }
+
+    /// Add `delta` to the number under or after the cursor, keeping
+    /// leading zeros, and put the cursor on its last digit.
+    pub fn add_to_number(&mut self, delta: i64) {
+        let row = self.cursor_position.row;
+        let Some(line) = self.buffer.get_line(row) else {
+            return;
+        };
+        let Some((range, value)) = line.number_span_at(self.cursor_position.col) else {
+            return;
+        };
+        let Some(new_value) = value.checked_add(delta) else {
+            return;
+        };
+        let old_text = line.text(range.clone());
+        let digits = old_text.trim_start_matches('-');
+        let width = if digits.starts_with('0') {
+            digits.len()
+        } else {
+            0
+        };
+        let sign = if new_value < 0 { "-" } else { "" };
+        let number = format!("{sign}{:0width$}", new_value.unsigned_abs());
+        let text = format!(
+            "{}{number}{}",
+            line.text(0..range.start),
+            line.text(range.end..line.len())
         );
 
-        Position { col: dx, row: dy }
+        self.record_edit(EditKind::Other);
+        self.selection_anchor = None;
+        self.buffer.replace_line(row, &text);
+        self.move_cursor_to(Position {
+            col: range.start + number.len() - 1,
+            row,
+        });
     }
 
-    fn render_buffer(&mut self) -> Result<(), std::io::Error> {
-        let Size { height, width } = self.size;
-        let Position { col, row } = self.scroll_offset;
-
-        for current in 0..height {
-            if let Some(line) = self.buffer.get_line(current + row) {
-                View::render_line(current, &line.get(col..(col + width)))?;
-            } else {
-                View::render_line(current, "~")?;
-            }
+    /// Fold the block of lines indented deeper than the cursor line
+    /// below it, or unfold the fold at or just below the cursor.
+    pub fn toggle_fold(&mut self) {
+        self.sync_folds();
+        let row = self.cursor_position.row;
+        if !self.folds.remove_containing(row) && !self.folds.remove_containing(row + 1) {
+            let block = self.buffer.indented_block_below(row, self.config.tab_width);
+            self.folds.add(block, self.buffer.num_lines());
         }
-        self.needs_redraw = false;
-        Ok(())
+        self.move_cursor_to(self.cursor_position);
     }
 
-    fn render_line(at: usize, line: &str) -> Result<(), std::io::Error> {
-        Terminal::print_row(at, line)
+    // folds only survive edits that keep the number of lines
+    fn sync_folds(&mut self) {
+        if self.folds.is_stale(self.buffer.num_lines()) {
+            self.folds.clear();
+            self.needs_redraw = true;
+        }
     }
 
-    pub fn render_welcome_message(&self) -> Result<(), std::io::Error> {
-        let Size { width, height } = self.size;
-        let mut message = format!("{NAME} editor -- v{VERSION}");
-        message.truncate(width);
-        let col = width.saturating_sub(message.len()) / 2;
-        let row = height / 3;
-        Terminal::move_cursor_to(Position { col, row })?;
-        Terminal::print(&message)?;
-        Ok(())
+    // the buffer row to put the cursor on for a visible row
+    fn row_at_visible(&self, visible: usize) -> usize {
+        match self.folds.to_buffer(visible) {
+            VisibleRow::Line(row) => row,
+            VisibleRow::Fold(range) => range.start,
+        }
     }
-}
 
-impl Default for View {
-    fn default() -> Self {
-        Self {
-            buffer: Buffer::default(),
-            needs_redraw: true,
-            size: Terminal::size().unwrap_or_default(),
-            cursor_position: Position { col: 0, row: 0 },
-            scroll_offset: Position { col: 0, row: 0 },
-        }
+    pub fn toggle_overwrite(&mut self) {
+        self.overwrite = !self.overwrite;
+        self.needs_redraw = true;
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    pub fn delete_left(&mut self) {
+        if self.extra_cursors.is_empty() && self.selection().is_some() {
+            self.delete_selection();
+            return;
+        }
+        self.selection_anchor = None;
+        let Position { col, row } = self.cursor_position;
+        // joining lines would shift the secondary cursors, so with those
+        // backspace stays within the line
+        let joins_lines = col == 0 && row > 0 && self.extra_cursors.is_empty();
+        let mut cursors = std::iter::once(&self.cursor_position).chain(&self.extra_cursors);
+        if joins_lines || cursors.any(|cursor| cursor.col > 0) {
+            self.record_edit(EditKind::DeleteLeft);
+        }
+        for index in (0..self.extra_cursors.len()).rev() {
+            let Position { col, row } = self.extra_cursors[index];
+            if col > 0
+                && self
+                    .buffer
+                    .delete(Position { col: col - 1, row })
+                    .is_deleted()
+            {
+                self.extra_cursors[index].col -= 1;
+                self.needs_redraw = true;
+            }
+        }
+        if joins_lines {
+            if let Some(position) = self.buffer.merge_line(row - 1) {
+                self.move_cursor_to(position);
+            }
+            return;
+        }
+        if col == 0 {
+            // nothing to delete
+            return;
+        }
 
-    fn setup() -> View {
-        let mut view = View {
-            size: Size {
-                width: 5,
-                height: 3,
-            },
-            ..Default::default()
+        // the cursor lands where the removed grapheme started, so its grid
+        // column drops by the columns that grapheme took up
+        let grid_col = self.cursor_grid_col();
+        let deletion = self.buffer.delete(Position { col: col - 1, row });
+        if !deletion.is_deleted() {
+            self.move_cursor(&Direction::Left);
+            return;
+        }
+        self.cursor_position.col = col - 1;
+        let text_size = Size {
+            width: self.text_width(),
+            ..self.size
         };
-        view.buffer.push("Hello world!");
-        view.buffer.push("How are we all doing?");
-        view.buffer.push("");
-        view.buffer.push("👋Ｂ👋");
-        view.buffer.push("Goodbye all");
-        view
+        self.scroll_offset.col =
+            self.horizontal_offset(grid_col.saturating_sub(deletion.columns()), text_size);
+        self.needs_redraw = true;
     }
 
-    #[test]
-    fn move_home_direction() {
-        let mut view = setup();
-        view.cursor_position = Position { row: 1, col: 3 };
-
-        view.move_cursor(&Direction::Home);
-        let expected_position = Position { row: 1, col: 0 };
-        let expected_offset = Position { row: 0, col: 0 };
+    pub fn delete_right(&mut self) {
+        if self.selection().is_some() {
+            self.delete_selection();
+            return;
+        }
+        self.selection_anchor = None;
+        let Position { col, row } = self.cursor_position;
+        let at_line_end = col >= self.buffer.line_len(row);
+        if !at_line_end || row + 1 < self.buffer.num_lines() {
+            self.record_edit(EditKind::DeleteRight);
+        }
+        if at_line_end {
+            if self.buffer.merge_line(row).is_some() {
+                self.needs_redraw = true;
+            }
+            return;
+        }
+        self.needs_redraw |= self.buffer.delete(self.cursor_position).is_deleted();
+    }
 
-        assert_eq!(view.cursor_position, expected_position);
-        assert_eq!(view.scroll_offset, expected_offset);
+    /// Move the cursor to the next occurrence of `ch` on the current line,
+    /// remembering it for `repeat_jump`.
+    pub fn jump_to_char(&mut self, ch: char) {
+        self.last_jump = Some(ch);
+        self.repeat_jump();
     }
 
-    #[test]
-    fn move_end_direction() {
-        let mut view = setup();
-        view.cursor_position = Position { row: 1, col: 3 };
+    pub fn repeat_jump(&mut self) {
+        let Some(ch) = self.last_jump else {
+            return;
+        };
+        let Position { col, row } = self.cursor_position;
+        if let Some(target) = self
+            .buffer
+            .find_char_from(Position { col: col + 1, row }, ch)
+        {
+            self.move_cursor_to(target);
+        }
+    }
 
-        view.move_cursor(&Direction::End);
-        let expected_position = Position { row: 1, col: 21 };
-        let expected_offset = Position { row: 0, col: 17 };
+    fn repeat_edit(&mut self) {
+        if let Some(command) = self.last_edit.clone() {
+            // a repeat is undone on its own
+            self.undo.break_group();
+            self.handle_command(command);
+        }
+    }
 
-        assert_eq!(view.cursor_position, expected_position);
-        assert_eq!(view.scroll_offset, expected_offset);
+    pub fn enter_search(&mut self) {
+        self.undo.break_group();
+        self.search_info = Some(SearchInfo {
+            prev_position: self.cursor_position,
+            prev_scroll_offset: self.scroll_offset,
+            match_len: 0,
+            matches: Vec::new(),
+            matches_for: None,
+        });
     }
 
-    #[test]
-    fn move_right_cannot_go_past_line() {
-        let mut view = setup();
-        view.cursor_position = Position { row: 3, col: 2 };
-        view.scroll_offset = Position { row: 1, col: 0 };
+    pub fn exit_search(&mut self) {
+        self.search_info = None;
+    }
 
-        view.move_cursor(&Direction::Right);
-        let expected_position = Position { row: 3, col: 3 };
-        let expected_offset = Position { row: 1, col: 2 };
+    pub fn dismiss_search(&mut self) {
+        if let Some(search_info) = self.search_info.take() {
+            self.cursor_position = search_info.prev_position;
+            self.scroll_offset = search_info.prev_scroll_offset;
+            self.needs_redraw = true;
+        }
+    }
 
-        assert_eq!(view.cursor_position, expected_position);
-        assert_eq!(view.scroll_offset, expected_offset);
+    /// Move to the first match of `query` at or after the cursor, so the
+    /// current match is kept while the query is being typed.
+    pub fn search(&mut self, query: &str, options: SearchOptions) {
+        self.search_from(self.cursor_position, query, options);
     }
 
-    #[test]
-    fn move_left_cannot_go_past_line() {
-        let mut view = setup();
-        view.cursor_position = Position { row: 3, col: 0 };
-        view.scroll_offset = Position { row: 1, col: 0 };
-        view.move_cursor(&Direction::Left);
+    pub fn search_next(&mut self, query: &str, options: SearchOptions) {
+        let Position { col, row } = self.cursor_position;
+        self.search_from(Position { col: col + 1, row }, query, options);
+    }
 
-        let expected_position = Position { row: 3, col: 0 };
+    fn search_from(&mut self, from: Position, query: &str, options: SearchOptions) {
+        self.last_search = (!query.is_empty()).then(|| (query.to_string(), options));
+        let found = self.buffer.find(query, from, options);
+        if let Some(search_info) = &mut self.search_info {
+            search_info.match_len = found.map_or(0, |_| query.graphemes(true).count());
+            let key = (query.to_string(), options);
+            if search_info.matches_for.as_ref() != Some(&key) {
+                search_info.matches = self.buffer.search_iter(query, options).collect();
+                search_info.matches_for = Some(key);
+            }
+            self.needs_redraw = true;
+        }
+        if let Some(position) = found {
+            self.move_cursor_to(position);
+        }
+    }
+
+    /// Go to the next match of the latest search after the cursor, or the
+    /// previous one before it, wrapping around. Returns whether there was
+    /// a match to go to.
+    pub fn repeat_search(&mut self, forward: bool) -> bool {
+        let Some((query, options)) = &self.last_search else {
+            return false;
+        };
+        let Position { col, row } = self.cursor_position;
+        let found = if forward {
+            self.buffer
+                .find(query, Position { col: col + 1, row }, *options)
+        } else {
+            self.buffer.rfind(query, self.cursor_position, *options)
+        };
+        let Some(position) = found else {
+            return false;
+        };
+        self.selection_anchor = None;
+        self.undo.break_group();
+        self.move_cursor_to(position);
+        true
+    }
+
+    /// The number of the match at the cursor, if it is on one, and the
+    /// total number of matches for the current search.
+    pub fn search_progress(&self) -> Option<(Option<usize>, usize)> {
+        let search_info = self.search_info.as_ref()?;
+        search_info.matches_for.as_ref()?;
+        let current = (search_info.match_len > 0)
+            .then(|| search::match_number(&search_info.matches, self.cursor_position))
+            .flatten();
+        Some((current, search_info.matches.len()))
+    }
+
+    pub fn handle_command(&mut self, command: EditorCommand) {
+        // the welcome screen goes away on the first keypress
+        if self.show_welcome && !matches!(command, EditorCommand::Resize(_)) {
+            self.show_welcome = false;
+            self.needs_redraw = true;
+        }
+        // secondary cursors only follow typing and backspacing
+        if !self.extra_cursors.is_empty()
+            && !matches!(
+                command,
+                EditorCommand::Insert(_)
+                    | EditorCommand::DeleteLeft
+                    | EditorCommand::AddCursorBelow
+                    | EditorCommand::Resize(_)
+            )
+        {
+            self.extra_cursors.clear();
+            self.needs_redraw = true;
+        }
+        // moving the cursor ends a run of edits for undo
+        if matches!(
+            command,
+            EditorCommand::Move(_)
+                | EditorCommand::Select(_)
+                | EditorCommand::JumpToChar(_)
+                | EditorCommand::RepeatJump
+                | EditorCommand::Click(_)
+                | EditorCommand::DoubleClick(_)
+        ) {
+            self.undo.break_group();
+        }
+        if matches!(
+            command,
+            EditorCommand::Insert(_)
+                | EditorCommand::InsertNewline
+                | EditorCommand::DeleteLeft
+                | EditorCommand::DeleteRight
+                | EditorCommand::DeleteSelection
+                | EditorCommand::DuplicateSelection
+                | EditorCommand::UniqueLines
+                | EditorCommand::AddToNumber(_)
+        ) {
+            self.last_edit = Some(command.clone());
+        }
+        match command {
+            EditorCommand::Move(direction) => self.move_cursor(&direction),
+            EditorCommand::Select(direction) => self.select(&direction),
+            EditorCommand::DeleteSelection => self.delete_selection(),
+            EditorCommand::DuplicateSelection => self.duplicate_selection(),
+            EditorCommand::UniqueLines => self.unique_lines(),
+            EditorCommand::AddCursorBelow => self.add_cursor_below(),
+            EditorCommand::Click(at) => self.click(at),
+            EditorCommand::DoubleClick(at) => self.select_word_at(at),
+            EditorCommand::Resize(size) => self.resize(size),
+            EditorCommand::Insert(ch) => self.insert(ch),
+            EditorCommand::InsertNewline => self.insert_newline(),
+            EditorCommand::DeleteLeft => self.delete_left(),
+            EditorCommand::DeleteRight => self.delete_right(),
+            EditorCommand::Undo => self.undo(),
+            EditorCommand::Redo => self.redo(),
+            EditorCommand::RepeatEdit => self.repeat_edit(),
+            EditorCommand::AddToNumber(delta) => self.add_to_number(delta),
+            EditorCommand::ToggleOverwrite => self.toggle_overwrite(),
+            EditorCommand::ToggleFold => self.toggle_fold(),
+            EditorCommand::Retab => self.retab(),
+            EditorCommand::ToggleLineEnding => {
+                let line_ending = self.buffer.line_ending().toggled();
+                self.buffer.set_line_ending(line_ending);
+            }
+            EditorCommand::ToggleTrimOnSave => self.toggle_trim_on_save(),
+            EditorCommand::BalanceBrackets => self.balance_brackets(),
+            EditorCommand::ToggleEol => {
+                self.config.show_eol = !self.config.show_eol;
+                self.needs_redraw = true;
+            }
+            EditorCommand::Paste(text) => self.paste(&text),
+            EditorCommand::JumpToChar(ch) => self.jump_to_char(ch),
+            EditorCommand::RepeatJump => self.repeat_jump(),
+            // handled by the editor, or only meaningful in a prompt
+            EditorCommand::StartJump
+            | EditorCommand::Save
+            | EditorCommand::Search
+            | EditorCommand::OpenRecent
+            | EditorCommand::AppendFile
+            | EditorCommand::PipeCommand
+            | EditorCommand::Grep
+            | EditorCommand::SearchNext
+            | EditorCommand::SearchPrevious
+            | EditorCommand::CheckWhitespace
+            | EditorCommand::SwitchHeaderSource
+            | EditorCommand::AlignOn
+            | EditorCommand::GotoPercent
+            | EditorCommand::DiffAgainstDisk
+            | EditorCommand::GoToDefinition
+            | EditorCommand::CommandPalette
+            | EditorCommand::ToggleMatchCase
+            | EditorCommand::ToggleWholeWord
+            | EditorCommand::Dismiss
+            | EditorCommand::InsertDate
+            | EditorCommand::NextFile
+            | EditorCommand::Quit
+            | EditorCommand::ForceQuit => {}
+        }
+    }
+
+    pub fn move_cursor(&mut self, direction: &Direction) {
+        self.selection_anchor = None;
+        self.scroll_page(direction);
+        self.move_cursor_to(self.update_cursor_position(direction));
+    }
+
+    /// Move the cursor while extending the selection.
+    pub fn select(&mut self, direction: &Direction) {
+        if self.selection_anchor.is_none() {
+            self.selection_anchor = Some(self.cursor_position);
+        }
+        self.scroll_page(direction);
+        self.move_cursor_to(self.update_cursor_position(direction));
+    }
+
+    // with `scroll_past_end`, a page motion scrolls the view along with
+    // the cursor, so paging on at the end lifts the last line up the view;
+    // `update_scroll_offset` keeps the offset at most at the cursor row
+    fn scroll_page(&mut self, direction: &Direction) {
+        if !self.config.scroll_past_end {
+            return;
+        }
+        let step = self.page_step();
+        let row = &mut self.scroll_offset.row;
+        match direction {
+            Direction::PageDown => *row = row.saturating_add(step),
+            Direction::PageUp => *row = row.saturating_sub(step),
+            _ => {}
+        }
+    }
+
+    /// Move the cursor to the text shown at `screen`, a position within
+    /// the view.
+    pub fn click(&mut self, screen: Position) {
+        if let Some(position) = self.position_at_screen(screen) {
+            self.selection_anchor = None;
+            self.move_cursor_to(position);
+        }
+    }
+
+    /// Convert the indentation of the selected lines, or of the whole
+    /// buffer without a selection, to the buffer's indent style.
+    pub fn retab(&mut self) {
+        let rows = self.selected_rows().unwrap_or(0..self.buffer.num_lines());
+        let to_spaces = self.indent.style == IndentStyle::Spaces;
+        let width = self.config.tab_width;
+        if !self.buffer.can_retab(rows.clone(), to_spaces, width) {
+            return;
+        }
+        self.record_edit(EditKind::Other);
+        self.buffer.retab(rows, to_spaces, width);
+        let Position { col, row } = self.cursor_position;
+        let col = min(col, self.buffer.line_len(row));
+        self.move_cursor_to(Position { col, row });
+    }
+
+    /// Move to the start of the 1-based line `number`, or the last line
+    /// if there aren't that many.
+    pub fn goto_line(&mut self, number: usize) {
+        let last_row = self.buffer.num_lines().saturating_sub(1);
+        let row = min(number.saturating_sub(1), last_row);
+        self.selection_anchor = None;
+        self.undo.break_group();
+        self.move_cursor_to(Position { col: 0, row });
+    }
+
+    /// Move to the start of the line `percent` percent of the way through
+    /// the buffer, clamped to 100.
+    pub fn goto_percent(&mut self, percent: usize) {
+        let last_row = self.buffer.num_lines().saturating_sub(1);
+        let row = min(percent, 100) * last_row / 100;
+        self.selection_anchor = None;
+        self.undo.break_group();
+        self.move_cursor_to(Position { col: 0, row });
+    }
+
+    /// Move to the likely definition of the identifier under the cursor,
+    /// returning whether one was found.
+    pub fn go_to_definition(&mut self) -> bool {
+        let at = self.cursor_position;
+        let Some(line) = self.buffer.get_line(at.row) else {
+            return false;
+        };
+        let name = line.text(line.word_range_at(at.col));
+        if !name.starts_with(|ch: char| ch.is_alphanumeric() || ch == '_') {
+            return false;
+        }
+        let Some(definition) = self.buffer.find_definition(&name) else {
+            return false;
+        };
+        self.selection_anchor = None;
+        self.undo.break_group();
+        self.move_cursor_to(definition);
+        true
+    }
+
+    /// Select the word, whitespace or symbol run shown at `screen`.
+    pub fn select_word_at(&mut self, screen: Position) {
+        let Some(position) = self.position_at_screen(screen) else {
+            return;
+        };
+        let range = self.buffer.word_at(position);
+        self.selection_anchor = Some(Position {
+            col: range.start,
+            ..position
+        });
+        self.move_cursor_to(Position {
+            col: range.end,
+            ..position
+        });
+    }
+
+    // the buffer position for a position on screen, clamped to the text
+    fn position_at_screen(&self, screen: Position) -> Option<Position> {
+        if screen.row >= self.size.height || screen.col >= self.size.width {
+            return None;
+        }
+        let visible = self.scroll_offset.row + screen.row;
+        let row = min(
+            self.row_at_visible(visible),
+            self.buffer.num_lines().saturating_sub(1),
+        );
+        let col = match self.buffer.get_line(row) {
+            Some(_) if self.folds.containing(row).is_some() => 0,
+            Some(line) => {
+                let text_col = screen.col.saturating_sub(self.gutter_width());
+                line.index_at_column(self.scroll_offset.col + text_col)
+            }
+            None => 0,
+        };
+        Some(Position { col, row })
+    }
+
+    /// The selected range as `(start, end)`, ordered by position.
+    fn selection(&self) -> Option<(Position, Position)> {
+        let anchor = self.selection_anchor?;
+        (anchor != self.cursor_position).then(|| Position::normalize(anchor, self.cursor_position))
+    }
+
+    pub fn delete_selection(&mut self) {
+        if self.selection().is_some() {
+            self.record_edit(EditKind::Other);
+            self.remove_selection();
+        }
+    }
+
+    // delete the selection, if any, and drop the anchor; callers record
+    // the edit for undo first, so that replacing a selection undoes as one
+    fn remove_selection(&mut self) {
+        if let Some((start, end)) = self.selection() {
+            self.buffer.delete_range(start, end);
+            self.move_cursor_to(start);
+        }
+        self.selection_anchor = None;
+    }
+
+    /// Replace the selection, or the whole buffer without one, with what
+    /// `filter` makes of it. On error the buffer is left unchanged.
+    pub fn filter_through<F>(&mut self, filter: F) -> Result<(), std::io::Error>
+    where
+        F: FnOnce(&str) -> Result<String, std::io::Error>,
+    {
+        let selection = self.selection();
+        let (start, end) = selection.unwrap_or((Position::default(), self.buffer.end()));
+        let output = filter(&self.buffer.text_in(start, end))?;
+        self.record_edit(EditKind::Other);
+
+        self.buffer.delete_range(start, end);
+        if !output.is_empty() {
+            self.buffer.insert_text(start, &output);
+        }
+        self.selection_anchor = None;
+        let cursor = if selection.is_some() {
+            start
+        } else {
+            // keep the cursor where it was, as far as the new text allows
+            let row = min(
+                self.cursor_position.row,
+                self.buffer.num_lines().saturating_sub(1),
+            );
+            let col = min(self.cursor_position.col, self.buffer.line_len(row));
+            Position { col, row }
+        };
+        self.move_cursor_to(cursor);
+        Ok(())
+    }
+
+    /// Duplicate the lines touched by the selection (or the cursor line)
+    /// below them, and move the selection onto the copy.
+    pub fn duplicate_selection(&mut self) {
+        let row = self.cursor_position.row;
+        let rows = self.selected_rows().unwrap_or(row..row + 1);
+        if rows.start < self.buffer.num_lines() {
+            self.record_edit(EditKind::Other);
+        }
+        let count = self.buffer.duplicate_lines(rows);
+        if let Some(anchor) = &mut self.selection_anchor {
+            anchor.row += count;
+        }
+        let Position { col, row } = self.cursor_position;
+        self.move_cursor_to(Position {
+            col,
+            row: row + count,
+        });
+    }
+
+    /// Collapse runs of identical lines within the selected lines, or the
+    /// whole buffer without a selection, into one line each.
+    pub fn unique_lines(&mut self) {
+        let rows = self.selected_rows().unwrap_or(0..self.buffer.num_lines());
+        if !self.buffer.has_consecutive_duplicates(rows.clone()) {
+            return;
+        }
+        self.record_edit(EditKind::Other);
+        self.buffer.dedup_consecutive(rows);
+        self.selection_anchor = None;
+        let row = min(
+            self.cursor_position.row,
+            self.buffer.num_lines().saturating_sub(1),
+        );
+        let col = min(self.cursor_position.col, self.buffer.line_len(row));
+        self.move_cursor_to(Position { col, row });
+        self.needs_redraw = true;
+    }
+
+    /// Line up the first `sep` on each selected line.
+    pub fn align_on(&mut self, sep: char) {
+        let Some(rows) = self.selected_rows() else {
+            return;
+        };
+        if !self.buffer.can_align_on(rows.clone(), sep) {
+            return;
+        }
+        self.record_edit(EditKind::Other);
+        self.buffer.align_on(rows, sep);
+        let Position { col, row } = self.cursor_position;
+        let col = min(col, self.buffer.line_len(row));
+        self.move_cursor_to(Position { col, row });
+    }
+
+    /// The rows touched by the selection, if there is one.
+    fn selected_rows(&self) -> Option<Range<usize>> {
+        let (start, end) = self.selection()?;
+        // a selection ending at the start of a line does not include it
+        let last_row = if end.col == 0 && end.row > start.row {
+            end.row - 1
+        } else {
+            end.row
+        };
+        Some(start.row..last_row + 1)
+    }
+
+    // remember the state before an edit for undo
+    fn record_edit(&mut self, kind: EditKind) {
+        // an edit on a fold summary changes the lines it hides, so show them
+        for cursor in std::iter::once(&self.cursor_position).chain(&self.extra_cursors) {
+            self.needs_redraw |= self.folds.remove_containing(cursor.row);
+        }
+        let view = self.snapshot();
+        self.undo
+            .record(kind, Instant::now(), self.buffer.lines(), view);
+    }
+
+    pub fn undo(&mut self) {
+        if let Some(restore) = self.undo.undo(self.buffer.lines(), self.snapshot()) {
+            self.restore(restore);
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if let Some(restore) = self.undo.redo(self.buffer.lines(), self.snapshot()) {
+            self.restore(restore);
+        }
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            cursor: self.cursor_position,
+            scroll_offset: self.scroll_offset,
+        }
+    }
+
+    fn restore(&mut self, restore: Restore) {
+        self.buffer.restore(restore.rows.clone(), restore.lines);
+        // a fold over the restored lines may no longer match them, even
+        // where the number of lines stayed the same
+        self.needs_redraw |= self.folds.remove_overlapping(restore.rows);
+        self.selection_anchor = None;
+        // back to the view of the time, as far as it still shows the cursor
+        self.scroll_offset = restore.view.scroll_offset;
+        self.move_cursor_to(restore.view.cursor);
+    }
+
+    fn move_cursor_to(&mut self, position: Position) {
+        self.sync_folds();
+        self.cursor_position = position;
+        self.scroll_offset = self.update_scroll_offset(self.size);
+        self.needs_redraw = true;
+    }
+
+    /// The 1-based screen column of the cursor within its line, counting
+    /// wide graphemes as two columns.
+    pub fn display_column(&self) -> usize {
+        self.cursor_grid_col() + 1
+    }
+
+    // the grid column of the cursor; a fold line shows it at the start
+    fn cursor_grid_col(&self) -> usize {
+        if self.folds.containing(self.cursor_position.row).is_some() {
+            0
+        } else {
+            self.buffer.grid_position_of(self.cursor_position).col
+        }
+    }
+
+    pub fn get_cursor_position(&self) -> Position {
+        let row = self.cursor_position.row;
+        let offset = self.scroll_offset;
+        Position {
+            col: self.cursor_grid_col().saturating_sub(offset.col) + self.gutter_width(),
+            row: self.folds.to_visible(row).saturating_sub(offset.row),
+        }
+    }
+
+    fn update_cursor_position(&self, direction: &Direction) -> Position {
+        let Position { mut row, mut col } = self.cursor_position;
+        match direction {
+            Direction::Left => {
+                col = col.saturating_sub(1);
+            }
+            Direction::Right => {
+                col = col.saturating_add(1);
+            }
+            // a fold is a single stop, left from its first or last line
+            Direction::WordLeft => {
+                let from = self
+                    .folds
+                    .containing(row)
+                    .map_or(Position { col, row }, |fold| Position {
+                        col: 0,
+                        row: fold.start,
+                    });
+                Position { row, col } = self.buffer.prev_word(from);
+            }
+            Direction::WordRight => {
+                let from = self
+                    .folds
+                    .containing(row)
+                    .map_or(Position { col, row }, |fold| Position {
+                        col: self.buffer.line_len(fold.end - 1),
+                        row: fold.end - 1,
+                    });
+                Position { row, col } = self.buffer.next_word(from);
+            }
+            Direction::Up => {
+                row = self.row_at_visible(self.folds.to_visible(row).saturating_sub(1));
+            }
+            Direction::Down => {
+                row = self.row_at_visible(self.folds.to_visible(row).saturating_add(1));
+            }
+            Direction::Home => {
+                col = 0;
+            }
+            Direction::End => {
+                // Caret at end: allow position after last grapheme
+                col = self.buffer.line_len(row);
+            }
+            Direction::PageUp => {
+                row = row.saturating_sub(self.page_step());
+            }
+            Direction::PageDown => {
+                row = row.saturating_add(self.page_step());
+            }
+            Direction::ParagraphUp => {
+                row = (0..row)
+                    .rev()
+                    .find(|&at| self.buffer.is_line_blank(at))
+                    .unwrap_or(0);
+                col = 0;
+            }
+            Direction::DocumentStart => {
+                row = 0;
+                col = 0;
+            }
+            Direction::DocumentEnd => {
+                row = usize::MAX;
+                col = usize::MAX;
+            }
+            Direction::ParagraphDown => {
+                row = (row + 1..self.buffer.num_lines())
+                    .find(|&at| self.buffer.is_line_blank(at))
+                    .unwrap_or(usize::MAX);
+                col = 0;
+            }
+        }
+        // Ensure we do not go out of bounds. Allow caret at end of line.
+        row = min(self.buffer.num_lines().saturating_sub(1), row);
+        col = min(self.buffer.line_len(row), col);
+        // a folded block is entered at its summary row
+        if let Some(fold) = self.folds.containing(row) {
+            row = fold.start;
+            col = 0;
+        }
+        Position { col, row }
+    }
+
+    /// How many lines a page motion moves. The overlap is clamped below
+    /// the viewport height so paging always makes progress.
+    fn page_step(&self) -> usize {
+        let overlap = min(self.config.page_overlap, self.size.last_row());
+        max(self.size.height - overlap, 1)
+    }
+
+    fn update_scroll_offset(&self, size: Size) -> Position {
+        // we need to ensure that the cursor is always in view
+        let Position { row, col } = self.cursor_position;
+        let position = self.buffer.grid_position_of(Position { col, row });
+        let row = self.folds.to_visible(row);
+
+        // Two conditions:
+        // (1): dy < row
+        // (2): dy + height > row
+        let dy = max(
+            min(self.scroll_offset.row, row),
+            row.saturating_sub(size.last_row()),
+        );
+        let text_size = Size {
+            width: size
+                .width
+                .saturating_sub(self.gutter_width() + self.minimap_width()),
+            ..size
+        };
+        let dx = self.horizontal_offset(position.col, text_size);
+
+        Position { col: dx, row: dy }
+    }
+
+    /// Scroll horizontally in steps of `hscroll_step` columns once the
+    /// cursor at grid column `cursor_col` leaves the view, never so far
+    /// that the cursor drops off the left edge. Once the cursor is left
+    /// of the view and would fit unscrolled, e.g. after moving onto a
+    /// short line, the view snaps back to the start of the line.
+    fn horizontal_offset(&self, cursor_col: usize, size: Size) -> usize {
+        // with every line fitting, including the cursor past its end,
+        // there is nothing to scroll to
+        if self.buffer.longest_line_width() < size.width {
+            return 0;
+        }
+        let step = max(self.config.hscroll_step, 1);
+        let current = self.scroll_offset.col;
+        // the smallest offset that keeps the cursor in view
+        let needed = cursor_col.saturating_sub(size.last_col());
+        if current < needed {
+            let steps = (needed - current).div_ceil(step);
+            min(current + steps * step, cursor_col)
+        } else if cursor_col < current && needed == 0 {
+            0
+        } else if cursor_col < current {
+            let steps = (current - cursor_col).div_ceil(step);
+            current.saturating_sub(steps * step)
+        } else {
+            current
+        }
+    }
+
+    fn render_buffer(&mut self) -> Result<(), std::io::Error> {
+        for (row, segments) in self.screen_rows().into_iter().enumerate() {
+            Terminal::move_cursor_to(Position { col: 0, row })?;
+            for (text, color) in segments {
+                View::render_segment(&text, color)?;
+            }
+        }
+        self.needs_redraw = false;
+        Ok(())
+    }
+
+    /// The colored runs of text drawn on each screen row of the view,
+    /// covering it from the gutter through the minimap.
+    fn screen_rows(&self) -> Vec<Vec<(String, Option<Color>)>> {
+        let height = self.size.height;
+        let brackets = self.bracket_pair();
+        let first = match self.folds.to_buffer(self.scroll_offset.row) {
+            VisibleRow::Line(at) => at,
+            VisibleRow::Fold(range) => range.start,
+        };
+
+        let mut rows = Vec::with_capacity(height);
+        for (at, line) in self.buffer.lines_in_range(first..usize::MAX) {
+            if rows.len() == height {
+                break;
+            }
+            let segments = match self.folds.containing(at) {
+                Some(fold) if fold.start == at => {
+                    let summary = self.gutter_label(at) + &self.fold_summary(&fold);
+                    vec![(pad_to_width(&summary, self.content_width()), None)]
+                }
+                // hidden inside a fold
+                Some(_) => continue,
+                None => self.line_segments(at, line, brackets),
+            };
+            rows.push(segments);
+        }
+        while rows.len() < height {
+            let marker = pad_to_width(&self.end_of_buffer_marker(), self.content_width());
+            rows.push(vec![(marker, None)]);
+        }
+        for (row, segments) in rows.iter_mut().enumerate() {
+            segments.extend(self.minimap_cell(row));
+        }
+        rows
+    }
+
+    /// The plain text of each screen row of the buffer area, as
+    /// `render_buffer` draws it minus the colors and the padding at the
+    /// end.
+    #[cfg(test)]
+    pub fn render_to_lines(&self) -> Vec<String> {
+        self.screen_rows()
+            .into_iter()
+            .map(|segments| {
+                let row: String = segments.into_iter().map(|(text, _)| text).collect();
+                row.trim_end_matches(' ').to_string()
+            })
+            .collect()
+    }
+
+    fn glyphs(&self) -> Glyphs {
+        if self.config.ascii_only {
+            Glyphs::Ascii
+        } else {
+            Glyphs::Unicode
+        }
+    }
+
+    fn fold_summary(&self, range: &Range<usize>) -> String {
+        let dots = match self.glyphs() {
+            Glyphs::Unicode => "···",
+            Glyphs::Ascii => "...",
+        };
+        let summary = format!("{dots} {} lines", range.len());
+        truncate_to_width(&summary, self.text_width())
+    }
+
+    fn gutter(&self) -> Option<Gutter<'_>> {
+        let gutter = Gutter::new(&self.config, self.buffer.num_lines())?;
+        // real line numbers are what a prompt might ask for
+        Some(if self.config.relative_line_numbers && !self.prompt_open {
+            gutter.relative_to(self.cursor_position.row)
+        } else {
+            gutter
+        })
+    }
+
+    /// Note whether a prompt is open, which switches relative line
+    /// numbers back to absolute ones.
+    pub fn set_prompt_open(&mut self, open: bool) {
+        if self.prompt_open != open {
+            self.prompt_open = open;
+            self.needs_redraw = true;
+        }
+    }
+
+    fn gutter_width(&self) -> usize {
+        self.gutter().map_or(0, |gutter| gutter.width())
+    }
+
+    // the gutter drawn before line `row`, empty without line numbers
+    fn gutter_label(&self, row: usize) -> String {
+        self.gutter()
+            .map(|gutter| gutter.label(Some(row)))
+            .unwrap_or_default()
+    }
+
+    // the columns left for text beside the gutter and the minimap
+    fn text_width(&self) -> usize {
+        self.content_width().saturating_sub(self.gutter_width())
+    }
+
+    // the columns left of the minimap
+    fn content_width(&self) -> usize {
+        self.size.width.saturating_sub(self.minimap_width())
+    }
+
+    fn minimap_width(&self) -> usize {
+        if self.config.minimap {
+            minimap::WIDTH
+        } else {
+            0
+        }
+    }
+
+    /// The minimap cell at the end of screen row `row`, if the minimap is
+    /// on: a shade for the lines the row stands for, with the rows of the
+    /// lines in view highlighted.
+    fn minimap_cell(&self, row: usize) -> Option<(String, Option<Color>)> {
+        if !self.config.minimap {
+            return None;
+        }
+        let Size { height, .. } = self.size;
+        let num_lines = self.buffer.num_lines();
+        let lines = minimap::lines_of_row(row, num_lines, height);
+        let first = self.row_at_visible(self.scroll_offset.row);
+        let last = self.row_at_visible(self.scroll_offset.row + height.saturating_sub(1));
+        let band = minimap::visible_band(&(first..last + 1), num_lines, height);
+        let shade = minimap::shade(
+            self.buffer.lines_in_range(lines).map(|(_, line)| line),
+            self.glyphs(),
+        );
+        let color = band
+            .contains(&row)
+            .then_some(self.config.theme.minimap_view);
+        Some((shade.to_string().repeat(minimap::WIDTH), color))
+    }
+
+    /// The marker drawn after the end of `line`, if enabled and the end
+    /// is in view.
+    fn eol_marker(&self, line: &Line) -> Option<&'static str> {
+        let Position { col, .. } = self.scroll_offset;
+        let end = line.width();
+        if !self.config.show_eol || end < col || end >= col + self.text_width() {
+            return None;
+        }
+        Some(match self.glyphs() {
+            Glyphs::Unicode => "¶",
+            Glyphs::Ascii => "$",
+        })
+    }
+
+    fn end_of_buffer_marker(&self) -> String {
+        truncate_to_width(&self.config.end_of_buffer_marker, self.size.width)
+    }
+
+    /// The runs of text drawn for buffer line `at`, each with its
+    /// background color, from the gutter to the right edge of the view.
+    /// The part past the end of the line is padded with spaces in the
+    /// line's background, so that it needs no clearing.
+    fn line_segments(
+        &self,
+        at: usize,
+        line: &Line,
+        brackets: Option<(Position, Position)>,
+    ) -> Vec<(String, Option<Color>)> {
+        let col = self.scroll_offset.col;
+        let highlights = self.highlights_on(at, brackets);
+        let mut segments = vec![(self.gutter_label(at), None)];
+        for (range, color) in highlight::segments(col..col + self.text_width(), &highlights) {
+            segments.push((line.get_with(range, self.glyphs()), color));
+        }
+        if let Some(marker) = self.eol_marker(line) {
+            segments.push((marker.to_string(), None));
+        }
+        let used: usize = segments.iter().map(|(text, _)| text.width()).sum();
+        let padding = " ".repeat(self.content_width().saturating_sub(used));
+        segments.push((padding, self.line_background(at)));
+        segments
+    }
+
+    // the background past the end of line `row`: the selection color if
+    // the selection runs on into the next line
+    fn line_background(&self, row: usize) -> Option<Color> {
+        let (start, end) = self.selection()?;
+        (start.row <= row && row < end.row).then_some(self.config.theme.selection)
+    }
+
+    fn render_segment(text: &str, color: Option<Color>) -> Result<(), std::io::Error> {
+        Terminal::print_styled(text, None, color)
+    }
+
+    fn highlights_on(&self, row: usize, brackets: Option<(Position, Position)>) -> Vec<Highlight> {
+        let mut highlights = Vec::new();
+        if let (Some(dictionary), Some(line)) = (&self.dictionary, self.buffer.get_line(row)) {
+            for word in dictionary.misspelled(line) {
+                highlights.push(Highlight {
+                    range: line.position_of(word.start)..line.position_of(word.end),
+                    color: self.config.theme.misspelled,
+                });
+            }
+        }
+        if let Some(range) = self.overflow_on(row) {
+            highlights.push(Highlight {
+                range,
+                color: self.config.theme.overflow,
+            });
+        }
+        if let Some(range) = self.trailing_whitespace_on(row) {
+            highlights.push(Highlight {
+                range,
+                color: self.config.theme.trailing_whitespace,
+            });
+        }
+        if let (Some((first, second)), Some(line)) = (brackets, self.buffer.get_line(row)) {
+            for bracket in [first, second].into_iter().filter(|at| at.row == row) {
+                highlights.push(Highlight {
+                    range: line.position_of(bracket.col)..line.position_of(bracket.col + 1),
+                    color: self.config.theme.matching_bracket,
+                });
+            }
+        }
+        for cursor in self.extra_cursors.iter().filter(|cursor| cursor.row == row) {
+            if let Some(line) = self.buffer.get_line(row) {
+                let start = line.position_of(cursor.col);
+                highlights.push(Highlight {
+                    range: start..max(line.position_of(cursor.col + 1), start + 1),
+                    color: self.config.theme.extra_cursor,
+                });
+            }
+        }
+        if let Some(range) = self.search_match_on(row) {
+            highlights.push(Highlight {
+                range,
+                color: self.config.theme.search_match,
+            });
+        }
+        if let Some(range) = self.selection_on(row) {
+            highlights.push(Highlight {
+                range,
+                color: self.config.theme.selection,
+            });
+        }
+        highlights
+    }
+
+    /// The bracket under the cursor, or else the one just left of it,
+    /// together with its match. This runs on every frame, so the match
+    /// is only looked for within two view heights of the cursor, which
+    /// covers the rows in view and a margin around them.
+    fn bracket_pair(&self) -> Option<(Position, Position)> {
+        let cursor = self.cursor_position;
+        let reach = self.size.height * 2;
+        let rows = cursor.row.saturating_sub(reach)..cursor.row.saturating_add(reach + 1);
+        let before = cursor
+            .col
+            .checked_sub(1)
+            .map(|col| Position { col, ..cursor });
+        std::iter::once(cursor)
+            .chain(before)
+            .find_map(|at| Some((at, self.buffer.matching_bracket(at, rows.clone())?)))
+    }
+
+    /// The grid range of trailing whitespace on `row`, if it should be
+    /// highlighted.
+    fn trailing_whitespace_on(&self, row: usize) -> Option<Range<usize>> {
+        if !self.config.highlight_trailing_whitespace
+            || (self.config.trailing_whitespace_skip_cursor_line && row == self.cursor_position.row)
+        {
+            return None;
+        }
+        let line = self.buffer.get_line(row)?;
+        line.trailing_whitespace()
+            .map(|range| line.position_of(range.start)..line.width())
+    }
+
+    /// The grid range of the text on `row` past the maximum line length.
+    fn overflow_on(&self, row: usize) -> Option<Range<usize>> {
+        let max_width = self.config.max_line_length?;
+        let line = self.buffer.get_line(row)?;
+        let overflow = line.overflow(max_width)?;
+        Some(line.position_of(overflow.start)..line.width())
+    }
+
+    /// The grid range of the search match on `row`, if any.
+    fn search_match_on(&self, row: usize) -> Option<Range<usize>> {
+        let match_len = self.search_info.as_ref()?.match_len;
+        let Position { col, .. } = self.cursor_position;
+        if match_len == 0 || row != self.cursor_position.row {
+            return None;
+        }
+        let line = self.buffer.get_line(row)?;
+        Some(line.position_of(col)..line.position_of(col + match_len))
+    }
+
+    /// The grid range of the selection on `row`, if any.
+    fn selection_on(&self, row: usize) -> Option<Range<usize>> {
+        let (start, end) = self.selection()?;
+        if row < start.row || row > end.row {
+            return None;
+        }
+        let line = self.buffer.get_line(row)?;
+        let from = if row == start.row { start.col } else { 0 };
+        let to = if row == end.row { end.col } else { line.len() };
+        Some(line.position_of(from)..line.position_of(to))
+    }
+
+    pub fn render_welcome_message(&self) -> Result<(), std::io::Error> {
+        let Size { width, height } = self.size;
+        let message = self
+            .config
+            .welcome_message
+            .clone()
+            .unwrap_or_else(|| format!("{NAME} editor -- v{VERSION}"));
+        let message = truncate_to_width(&message, width);
+        let col = width.saturating_sub(message.width()) / 2;
+        let row = height / 3;
+        Terminal::move_cursor_to(Position { col, row })?;
+        Terminal::print(&message)?;
+        Ok(())
+    }
+}
+
+impl Default for View {
+    fn default() -> Self {
+        Self::new(Terminal::size().unwrap_or_default(), Config::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn setup() -> View {
+        let mut view = View {
+            size: Size {
+                width: 5,
+                height: 3,
+            },
+            ..Default::default()
+        };
+        view.buffer.push("Hello world!");
+        view.buffer.push("How are we all doing?");
+        view.buffer.push("");
+        view.buffer.push("👋Ｂ👋");
+        view.buffer.push("Goodbye all");
+        view
+    }
+
+    #[test]
+    fn move_home_direction() {
+        let mut view = setup();
+        view.cursor_position = Position { row: 1, col: 3 };
+
+        view.move_cursor(&Direction::Home);
+        let expected_position = Position { row: 1, col: 0 };
+        let expected_offset = Position { row: 0, col: 0 };
+
+        assert_eq!(view.cursor_position, expected_position);
+        assert_eq!(view.scroll_offset, expected_offset);
+    }
+
+    #[test]
+    fn move_end_direction() {
+        let mut view = setup();
+        view.cursor_position = Position { row: 1, col: 3 };
+
+        view.move_cursor(&Direction::End);
+        let expected_position = Position { row: 1, col: 21 };
+        let expected_offset = Position { row: 0, col: 17 };
+
+        assert_eq!(view.cursor_position, expected_position);
+        assert_eq!(view.scroll_offset, expected_offset);
+    }
+
+    #[test]
+    fn move_right_cannot_go_past_line() {
+        let mut view = setup();
+        view.cursor_position = Position { row: 3, col: 2 };
+        view.scroll_offset = Position { row: 1, col: 0 };
+
+        view.move_cursor(&Direction::Right);
+        let expected_position = Position { row: 3, col: 3 };
+        let expected_offset = Position { row: 1, col: 2 };
+
+        assert_eq!(view.cursor_position, expected_position);
+        assert_eq!(view.scroll_offset, expected_offset);
+    }
+
+    #[test]
+    fn move_left_cannot_go_past_line() {
+        let mut view = setup();
+        view.cursor_position = Position { row: 3, col: 0 };
+        view.scroll_offset = Position { row: 1, col: 0 };
+        view.move_cursor(&Direction::Left);
+
+        let expected_position = Position { row: 3, col: 0 };
+        let expected_offset = Position { row: 1, col: 0 };
+
+        assert_eq!(view.cursor_position, expected_position);
+        assert_eq!(view.scroll_offset, expected_offset);
+    }
+
+    #[test]
+    fn move_left_moves_offset() {
+        let mut view = setup();
+        view.cursor_position = Position { row: 1, col: 8 };
+        view.scroll_offset = Position { row: 1, col: 7 };
+
+        view.move_cursor(&Direction::Left);
+        let expected_position = Position { row: 1, col: 7 };
+        let expected_offset = Position { row: 1, col: 7 };
+        assert_eq!(view.cursor_position, expected_position);
+        assert_eq!(view.scroll_offset, expected_offset);
+
+        view.move_cursor(&Direction::Left);
+        let expected_position = Position { row: 1, col: 6 };
+        let expected_offset = Position { row: 1, col: 6 };
+        assert_eq!(view.cursor_position, expected_position);
+        assert_eq!(view.scroll_offset, expected_offset);
+    }
+
+    #[test]
+    fn move_right_moves_offset() {
+        let mut view = setup();
+        view.cursor_position = Position { row: 1, col: 8 };
+        view.scroll_offset = Position { row: 1, col: 5 };
+
+        view.move_cursor(&Direction::Right);
+        let expected_position = Position { row: 1, col: 9 };
+        let expected_offset = Position { row: 1, col: 5 };
+        assert_eq!(
+            view.cursor_position, expected_position,
+            "expected position 1"
+        );
+        assert_eq!(view.scroll_offset, expected_offset, "expected offset 1");
+
+        view.move_cursor(&Direction::Right);
+        let expected_position = Position { row: 1, col: 10 };
+        let expected_offset = Position { row: 1, col: 6 };
+        assert_eq!(
+            view.cursor_position, expected_position,
+            "expected position 2"
+        );
+        assert_eq!(view.scroll_offset, expected_offset, "expected offset 2");
+
+        view.move_cursor(&Direction::Right);
+        let expected_position = Position { row: 1, col: 11 };
+        let expected_offset = Position { row: 1, col: 7 };
+        assert_eq!(
+            view.cursor_position, expected_position,
+            "expected position 3"
+        );
+        assert_eq!(view.scroll_offset, expected_offset, "expected offset 3");
+    }
+
+    #[test]
+    fn move_up_moves_offset() {
+        let mut view = setup();
+        view.cursor_position = Position { row: 4, col: 0 };
+        view.scroll_offset = Position { row: 3, col: 0 };
+
+        view.move_cursor(&Direction::Up);
+        let expected_position = Position { row: 3, col: 0 };
+        let expected_offset = Position { row: 3, col: 0 };
+        assert_eq!(
+            view.cursor_position, expected_position,
+            "expected position 1"
+        );
+        assert_eq!(view.scroll_offset, expected_offset, "expected offset 1");
+
+        view.move_cursor(&Direction::Up);
+        let expected_position = Position { row: 2, col: 0 };
+        let expected_offset = Position { row: 2, col: 0 };
+        assert_eq!(
+            view.cursor_position, expected_position,
+            "expected position 2"
+        );
+        assert_eq!(view.scroll_offset, expected_offset, "expected offset 2");
+    }
+
+    #[test]
+    fn move_down_moves_offset() {
+        let mut view = setup();
+        view.cursor_position = Position { row: 2, col: 0 };
+        view.scroll_offset = Position { row: 1, col: 0 };
+
+        view.move_cursor(&Direction::Down);
+        let expected_position = Position { row: 3, col: 0 };
+        let expected_offset = Position { row: 1, col: 0 };
+        assert_eq!(
+            view.cursor_position, expected_position,
+            "expected position 1"
+        );
+        assert_eq!(view.scroll_offset, expected_offset, "expected offset 1");
+
+        view.move_cursor(&Direction::Down);
+        let expected_position = Position { row: 4, col: 0 };
+        let expected_offset = Position { row: 2, col: 0 };
+        assert_eq!(
+            view.cursor_position, expected_position,
+            "expected position 2"
+        );
+        assert_eq!(view.scroll_offset, expected_offset, "expected offset 2");
+    }
+
+    #[test]
+    fn move_down_doesnt_go_below_end() {
+        let mut view = setup();
+        view.cursor_position = Position { row: 3, col: 1 };
+        view.scroll_offset = Position { row: 3, col: 0 };
+
+        view.move_cursor(&Direction::Down);
+        let expected_position = Position { row: 4, col: 1 };
+        let expected_offset = Position { row: 3, col: 0 };
+        assert_eq!(
+            view.cursor_position, expected_position,
+            "expected position 1"
+        );
+        assert_eq!(view.scroll_offset, expected_offset, "expected offset 1");
+
+        view.move_cursor(&Direction::Down);
+        let expected_position = Position { row: 4, col: 1 };
+        let expected_offset = Position { row: 3, col: 0 };
+        assert_eq!(
+            view.cursor_position, expected_position,
+            "expected position 1"
+        );
+        assert_eq!(view.scroll_offset, expected_offset, "expected offset 1");
+    }
+
+    #[test]
+    fn move_pageup_subtract_height() {
+        let mut view = setup();
+        view.cursor_position = Position { row: 5, col: 0 };
+        view.scroll_offset = Position { row: 3, col: 0 };
+
+        view.move_cursor(&Direction::PageUp);
+        let expected_position = Position { row: 2, col: 0 };
+        let expected_offset = Position { row: 2, col: 0 };
+        assert_eq!(
+            view.cursor_position, expected_position,
+            "expected position 1"
+        );
+        assert_eq!(view.scroll_offset, expected_offset, "expected offset 1");
+
+        view.move_cursor(&Direction::PageUp);
+        let expected_position = Position { row: 0, col: 0 };
+        let expected_offset = Position { row: 0, col: 0 };
+        assert_eq!(
+            view.cursor_position, expected_position,
+            "expected position 2"
+        );
+        assert_eq!(view.scroll_offset, expected_offset, "expected offset 2");
+    }
+
+    #[test]
+    fn move_pagedown_add_height() {
+        let mut view = setup();
+        view.cursor_position = Position { row: 0, col: 1 };
+        view.scroll_offset = Position { row: 0, col: 0 };
+
+        view.move_cursor(&Direction::PageDown);
+        let expected_position = Position { row: 3, col: 1 };
         let expected_offset = Position { row: 1, col: 0 };
+        assert_eq!(
+            view.cursor_position, expected_position,
+            "expected position 1"
+        );
+        assert_eq!(view.scroll_offset, expected_offset, "expected offset 1");
+
+        view.move_cursor(&Direction::PageDown);
+        // note row is 4 because we cannot go out of bounds
+        let expected_position = Position { row: 4, col: 1 };
+        let expected_offset = Position { row: 2, col: 0 };
+        assert_eq!(
+            view.cursor_position, expected_position,
+            "expected position 2"
+        );
+        assert_eq!(view.scroll_offset, expected_offset, "expected offset 2");
+    }
+
+    #[test]
+    fn move_paragraph_down_skips_to_next_blank_line() {
+        let mut view = setup();
+        view.cursor_position = Position { row: 0, col: 3 };
+
+        view.move_cursor(&Direction::ParagraphDown);
+        assert_eq!(view.cursor_position, Position { row: 2, col: 0 });
+        assert_eq!(view.scroll_offset, Position { row: 0, col: 0 });
+
+        // no further blank line: go to the last line
+        view.move_cursor(&Direction::ParagraphDown);
+        assert_eq!(view.cursor_position, Position { row: 4, col: 0 });
+        assert_eq!(view.scroll_offset, Position { row: 2, col: 0 });
+    }
+
+    #[test]
+    fn move_paragraph_up_skips_to_previous_blank_line() {
+        let mut view = setup();
+        view.cursor_position = Position { row: 4, col: 3 };
+        view.scroll_offset = Position { row: 2, col: 0 };
+
+        view.move_cursor(&Direction::ParagraphUp);
+        assert_eq!(view.cursor_position, Position { row: 2, col: 0 });
+
+        view.move_cursor(&Direction::ParagraphUp);
+        assert_eq!(view.cursor_position, Position { row: 0, col: 0 });
+        assert_eq!(view.scroll_offset, Position { row: 0, col: 0 });
+    }
+
+    #[test]
+    fn pagedown_scrolls_past_end_only_when_enabled() {
+        let mut view = setup();
+        for _ in 0..3 {
+            view.move_cursor(&Direction::PageDown);
+        }
+        assert_eq!(view.scroll_offset.row, 2);
+
+        let mut view = setup();
+        view.config.scroll_past_end = true;
+        view.move_cursor(&Direction::PageDown);
+        assert_eq!(view.scroll_offset.row, 3);
+        view.move_cursor(&Direction::PageDown);
+        // the last line reaches the top, and no further
+        assert_eq!(view.cursor_position.row, 4);
+        assert_eq!(view.scroll_offset.row, 4);
+        view.move_cursor(&Direction::PageDown);
+        assert_eq!(view.scroll_offset.row, 4);
+        assert_eq!(&view.render_to_lines()[1..], ["~", "~"]);
+
+        view.move_cursor(&Direction::PageUp);
+        assert_eq!(view.cursor_position.row, 1);
+        assert_eq!(view.scroll_offset.row, 1);
+    }
+
+    #[test]
+    fn move_pagedown_keeps_overlap() {
+        let mut view = setup();
+        view.config.page_overlap = 1;
+
+        view.move_cursor(&Direction::PageDown);
+        assert_eq!(view.cursor_position, Position { row: 2, col: 0 });
+
+        view.move_cursor(&Direction::PageUp);
+        assert_eq!(view.cursor_position, Position { row: 0, col: 0 });
+    }
+
+    #[test]
+    fn move_pagedown_advances_with_overlap_of_height() {
+        let mut view = setup();
+        view.config.page_overlap = view.size.height;
+
+        view.move_cursor(&Direction::PageDown);
+        assert_eq!(view.cursor_position, Position { row: 1, col: 0 });
+
+        view.config.page_overlap = 100;
+        view.move_cursor(&Direction::PageDown);
+        assert_eq!(view.cursor_position, Position { row: 2, col: 0 });
+    }
+
+    #[test]
+    fn get_cursor_position_counts_wide_chars() {
+        let mut view = View::default();
+        view.buffer.push("ＡＢ");
+        view.cursor_position = Position { row: 0, col: 1 };
+
+        let pos = view.get_cursor_position();
+        assert_eq!(pos, Position { row: 0, col: 2 });
+    }
+
+    #[test]
+    fn display_column_counts_wide_graphemes() {
+        let mut view = View::default();
+        view.buffer.push("Ａb");
+        view.cursor_position = Position { row: 0, col: 1 };
+        assert_eq!(view.display_column(), 3);
+        assert!(view.display_column() > view.cursor_position.col + 1);
+    }
+
+    #[test]
+    fn get_cursor_position_handles_zero_width() {
+        let mut view = View::default();
+        view.buffer.push("a\u{200B}b");
+        view.cursor_position = Position { row: 0, col: 1 };
+
+        let pos = view.get_cursor_position();
+        assert_eq!(pos, Position { row: 0, col: 1 });
+    }
+
+    #[test]
+    fn delete_right_in_middle_deletes_and_keeps_cursor() {
+        let mut view = View::default();
+        view.buffer.push("Hello");
+        view.cursor_position = Position { row: 0, col: 1 }; // at 'e'
+        view.needs_redraw = false;
+
+        view.delete_right();
+
+        let line = view.buffer.get_line(0).unwrap();
+        let full = line.position_of(line.len());
+        assert_eq!(line.get(0..full), "Hllo");
+        assert_eq!(view.cursor_position, Position { row: 0, col: 1 });
+        assert!(view.needs_redraw);
+    }
+
+    #[test]
+    fn delete_right_at_end_noop() {
+        let mut view = View::default();
+        view.buffer.push("Hello");
+        let end = view.buffer.line_len(0);
+        view.cursor_position = Position { row: 0, col: end };
+        view.needs_redraw = false;
+
+        view.delete_right();
+
+        let line = view.buffer.get_line(0).unwrap();
+        let full = line.position_of(line.len());
+        assert_eq!(line.get(0..full), "Hello");
+        assert_eq!(view.cursor_position, Position { row: 0, col: end });
+        assert!(!view.needs_redraw);
+    }
+
+    #[test]
+    fn delete_left_in_middle_moves_cursor_and_deletes_left() {
+        let mut view = View::default();
+        view.buffer.push("Hello");
+        view.cursor_position = Position { row: 0, col: 2 }; // between e and first l
+        view.needs_redraw = false;
+
+        view.delete_left();
+
+        let line = view.buffer.get_line(0).unwrap();
+        let full = line.position_of(line.len());
+        assert_eq!(line.get(0..full), "Hllo");
+        assert_eq!(view.cursor_position, Position { row: 0, col: 1 });
+        assert!(view.needs_redraw);
+    }
+
+    #[test]
+    fn delete_left_at_line_start_noop() {
+        let mut view = View::default();
+        view.buffer.push("Hello");
+        view.cursor_position = Position { row: 0, col: 0 };
+        view.needs_redraw = false;
+
+        view.delete_left();
+
+        let line = view.buffer.get_line(0).unwrap();
+        let full = line.position_of(line.len());
+        assert_eq!(line.get(0..full), "Hello");
+        assert_eq!(view.cursor_position, Position { row: 0, col: 0 });
+        assert!(!view.needs_redraw);
+    }
+
+    #[test]
+    fn delete_left_wide_grapheme_updates_grid() {
+        let mut view = View {
+            size: Size {
+                width: 80,
+                height: 24,
+            },
+            ..Default::default()
+        };
+        view.buffer.push("a👋b");
+        view.cursor_position = Position { row: 0, col: 2 }; // after 👋
+
+        view.delete_left();
+
+        let line = view.buffer.get_line(0).unwrap();
+        let full = line.position_of(line.len());
+        assert_eq!(line.get(0..full), "ab");
+        assert_eq!(view.cursor_position, Position { row: 0, col: 1 });
+        let grid = view.get_cursor_position();
+        assert_eq!(grid, Position { row: 0, col: 1 });
+    }
+
+    #[test]
+    fn delete_left_zero_width_grapheme_moves_grid_one_column() {
+        let mut view = View {
+            size: Size {
+                width: 80,
+                height: 24,
+            },
+            ..Default::default()
+        };
+        view.buffer.push("a\u{200b}b");
+        view.cursor_position = Position { row: 0, col: 2 }; // after the space
+
+        view.delete_left();
+
+        assert_eq!(view.buffer.get_line(0).unwrap().to_string(), "ab");
+        assert_eq!(view.cursor_position, Position { row: 0, col: 1 });
+        assert_eq!(view.get_cursor_position(), Position { row: 0, col: 1 });
+    }
+
+    #[test]
+    fn delete_left_of_wide_grapheme_scrolls_back_by_its_width() {
+        let mut view = View {
+            size: Size {
+                width: 10,
+                height: 24,
+            },
+            ..Default::default()
+        };
+        view.config.hscroll_step = 1;
+        view.buffer.push(&"ａ".repeat(12));
+        view.move_cursor_to(Position { row: 0, col: 10 });
+        view.move_cursor_to(Position { row: 0, col: 6 });
+        assert_eq!(view.scroll_offset.col, 11);
+
+        view.delete_left();
+
+        assert_eq!(view.cursor_position, Position { row: 0, col: 5 });
+        assert_eq!(view.scroll_offset.col, 10);
+        assert_eq!(view.scroll_offset, view.update_scroll_offset(view.size));
+    }
+
+    #[test]
+    fn delete_right_wide_grapheme_keeps_cursor_and_updates_grid() {
+        let mut view = View {
+            size: Size {
+                width: 80,
+                height: 24,
+            },
+            ..Default::default()
+        };
+        view.buffer.push("a👋b");
+        view.cursor_position = Position { row: 0, col: 1 }; // at 👋
+
+        view.delete_right();
+
+        let line = view.buffer.get_line(0).unwrap();
+        let full = line.position_of(line.len());
+        assert_eq!(line.get(0..full), "ab");
+        assert_eq!(view.cursor_position, Position { row: 0, col: 1 });
+        let grid = view.get_cursor_position();
+        assert_eq!(grid, Position { row: 0, col: 1 });
+    }
+
+    #[test]
+    fn delete_right_zero_width_keeps_grid_cursor() {
+        let mut view = View {
+            size: Size {
+                width: 80,
+                height: 24,
+            },
+            ..Default::default()
+        };
+        view.buffer.push("a\u{200B}b");
+        view.cursor_position = Position { row: 0, col: 1 }; // at zero-width
+
+        view.delete_right();
+
+        let line = view.buffer.get_line(0).unwrap();
+        let full = line.position_of(line.len());
+        assert_eq!(line.get(0..full), "ab");
+        assert_eq!(view.cursor_position, Position { row: 0, col: 1 });
+        let grid = view.get_cursor_position();
+        assert_eq!(grid, Position { row: 0, col: 1 });
+    }
+
+    #[test]
+    fn insert_into_empty_buffer_creates_line_and_moves_cursor() {
+        let mut view = View::default();
+        assert!(view.buffer.is_empty());
+
+        view.insert('A');
+
+        assert_eq!(view.buffer.num_lines(), 1);
+        let line = view.buffer.get_line(0).unwrap();
+        let full_width = line.position_of(line.len());
+        assert_eq!(line.get(0..full_width), "A");
+        assert_eq!(view.cursor_position, Position { row: 0, col: 1 });
+        assert!(view.needs_redraw);
+    }
+
+    #[test]
+    fn insert_in_middle_moves_cursor() {
+        let mut view = View::default();
+        view.buffer.push("Helo");
+        view.cursor_position = Position { row: 0, col: 2 };
+
+        view.insert('l');
+
+        let line = view.buffer.get_line(0).unwrap();
+        let full_width = line.position_of(line.len());
+        assert_eq!(line.get(0..full_width), "Hello");
+        assert_eq!(view.cursor_position, Position { row: 0, col: 3 });
+        assert!(view.needs_redraw);
+    }
+
+    #[test]
+    fn insert_at_end_moves_cursor() {
+        let mut view = View::default();
+        view.buffer.push("Hello");
+        let end = view.buffer.line_len(0);
+        view.cursor_position = Position { row: 0, col: end };
+
+        view.insert('!');
+
+        let line = view.buffer.get_line(0).unwrap();
+        let full_width = line.position_of(line.len());
+        assert_eq!(line.get(0..full_width), "Hello!");
+        assert_eq!(
+            view.cursor_position,
+            Position {
+                row: 0,
+                col: end + 1
+            }
+        );
+        assert!(view.needs_redraw);
+    }
+
+    #[test]
+    fn insert_wide_grapheme_updates_grid_cursor() {
+        let mut view = View {
+            size: Size {
+                width: 80,
+                height: 24,
+            },
+            ..Default::default()
+        };
+        view.buffer.push("ab");
+        view.cursor_position = Position { row: 0, col: 1 }; // between a and b
+
+        view.insert('👋');
+
+        let line = view.buffer.get_line(0).unwrap();
+        let full_width = line.position_of(line.len());
+        assert_eq!(line.get(0..full_width), "a👋b");
+        // Caret moved one grapheme to the right
+        assert_eq!(view.cursor_position, Position { row: 0, col: 2 });
+        // On grid: a(1) + 👋(2) = 3
+        let grid = view.get_cursor_position();
+        assert_eq!(grid, Position { row: 0, col: 3 });
+        assert!(view.needs_redraw);
+    }
+
+    #[test]
+    fn insert_with_cursor_beyond_end_appends_and_moves_cursor() {
+        let mut view = View::default();
+        view.buffer.push("Hi");
+        view.cursor_position = Position { row: 0, col: 100 };
+
+        view.insert('!');
+
+        let line = view.buffer.get_line(0).unwrap();
+        let full_width = line.position_of(line.len());
+        assert_eq!(line.get(0..full_width), "Hi!");
+        // Cursor should clamp to end of line after moving right
+        assert_eq!(view.cursor_position, Position { row: 0, col: 3 });
+        assert!(view.needs_redraw);
+    }
+
+    #[test]
+    fn welcome_dismissed_after_movement_on_empty_buffer() {
+        let mut view = View::default();
+        assert!(view.should_render_welcome());
+
+        view.handle_command(EditorCommand::Move(Direction::Down));
+
+        assert!(view.buffer.is_empty());
+        assert!(!view.should_render_welcome());
+        assert!(view.needs_redraw);
+    }
+
+    #[test]
+    fn welcome_kept_after_resize() {
+        let mut view = View::default();
+
+        view.handle_command(EditorCommand::Resize(Size {
+            width: 10,
+            height: 10,
+        }));
+
+        assert!(view.should_render_welcome());
+    }
+
+    #[test]
+    fn trailing_whitespace_on_grid() {
+        let mut view = View::default();
+        view.buffer.push("👋a  ");
+        view.buffer.push("b ");
+        view.cursor_position = Position { row: 1, col: 0 };
+
+        assert_eq!(view.trailing_whitespace_on(0), Some(3..5));
+        // the cursor line is exempt by default
+        assert_eq!(view.trailing_whitespace_on(1), None);
+
+        view.config.trailing_whitespace_skip_cursor_line = false;
+        assert_eq!(view.trailing_whitespace_on(1), Some(1..2));
+    }
+
+    fn contents(view: &View) -> Vec<String> {
+        (0..view.buffer.num_lines())
+            .filter_map(|row| view.buffer.get_line(row).map(ToString::to_string))
+            .collect()
+    }
+
+    #[test]
+    fn delete_selection_within_line() {
+        let mut view = setup();
+        view.cursor_position = Position { row: 0, col: 5 };
+        view.select(&Direction::Left);
+        view.select(&Direction::Left);
+        assert_eq!(view.selection_on(0), Some(3..5));
+
+        view.delete_selection();
+
+        assert_eq!(contents(&view)[0], "Hel world!");
+        assert_eq!(view.cursor_position, Position { row: 0, col: 3 });
+        assert_eq!(view.selection(), None);
+    }
+
+    #[test]
+    fn delete_selection_across_lines_places_cursor_at_start() {
+        let mut view = setup();
+        view.cursor_position = Position { row: 0, col: 5 };
+        view.select(&Direction::Down);
+        view.select(&Direction::Down);
+        view.select(&Direction::Down);
+
+        view.delete_selection();
+
+        assert_eq!(contents(&view), vec!["Hello👋Ｂ👋", "Goodbye all"]);
+        assert_eq!(view.cursor_position, Position { row: 0, col: 5 });
+    }
+
+    #[test]
+    fn typing_replaces_a_multi_line_selection() {
+        let mut view = setup();
+        view.cursor_position = Position { row: 3, col: 1 };
+        view.select(&Direction::Up);
+        view.select(&Direction::Up);
+
+        // passing the blank line leaves the cursor at the line start
+        assert_eq!(view.cursor_position, Position { row: 1, col: 0 });
+
+        view.insert('x');
+
+        assert_eq!(
+            contents(&view),
+            vec!["Hello world!", "xＢ👋", "Goodbye all"]
+        );
+        assert_eq!(view.cursor_position, Position { row: 1, col: 1 });
+        assert!(view.selection().is_none());
+
+        // the replacement undoes in one step
+        view.undo();
+        assert_eq!(view.buffer.num_lines(), 5);
+    }
+
+    #[test]
+    fn backspace_and_delete_remove_only_the_selection() {
+        let mut view = setup();
+        view.cursor_position = Position { row: 0, col: 6 };
+        for _ in 0..5 {
+            view.select(&Direction::Right);
+        }
+        view.delete_left();
+        assert_eq!(contents(&view)[0], "Hello !");
+        assert_eq!(view.cursor_position, Position { row: 0, col: 6 });
+
+        view.select(&Direction::Left);
+        view.delete_right();
+        assert_eq!(contents(&view)[0], "Hello!");
+        assert_eq!(view.cursor_position, Position { row: 0, col: 5 });
+    }
+
+    #[test]
+    fn typing_a_bracket_surrounds_the_selection() {
+        let mut view = setup();
+        view.cursor_position = Position { row: 0, col: 6 };
+        for _ in 0..5 {
+            view.select(&Direction::Right);
+        }
+
+        view.insert('(');
+
+        assert_eq!(contents(&view)[0], "Hello (world)!");
+        assert_eq!(
+            view.selection(),
+            Some((Position { row: 0, col: 6 }, Position { row: 0, col: 13 }))
+        );
+        assert_eq!(view.cursor_position, Position { row: 0, col: 13 });
+    }
+
+    #[test]
+    fn surrounding_a_multi_line_selection() {
+        let mut view = setup();
+        view.cursor_position = Position { row: 1, col: 0 };
+        view.select(&Direction::Up);
+
+        view.insert('"');
+
+        // the selection ends at the start of the second line, leaving it out
+        assert_eq!(
+            contents(&view)[..2],
+            ["\"Hello world!\"", "How are we all doing?"]
+        );
+        assert_eq!(view.cursor_position, Position { row: 0, col: 0 });
+        assert_eq!(
+            view.selection(),
+            Some((Position { row: 0, col: 0 }, Position { row: 0, col: 14 }))
+        );
+        view.undo();
+        assert_eq!(
+            contents(&view)[..2],
+            ["Hello world!", "How are we all doing?"]
+        );
+    }
+
+    #[test]
+    fn move_clears_selection() {
+        let mut view = setup();
+        view.select(&Direction::Right);
+        assert!(view.selection().is_some());
+
+        view.move_cursor(&Direction::Right);
+        assert_eq!(view.selection(), None);
+    }
+
+    #[test]
+    fn duplicate_selection_copies_lines_below() {
+        let mut view = setup();
+        view.cursor_position = Position { row: 0, col: 2 };
+        view.select(&Direction::Down);
+
+        view.duplicate_selection();
+
+        assert_eq!(
+            contents(&view)[..4],
+            [
+                "Hello world!",
+                "How are we all doing?",
+                "Hello world!",
+                "How are we all doing?"
+            ]
+        );
+        assert_eq!(view.selection_anchor, Some(Position { row: 2, col: 2 }));
+        assert_eq!(view.cursor_position, Position { row: 3, col: 2 });
+    }
+
+    #[test]
+    fn search_moves_to_match_and_next() {
+        let mut view = setup();
+
+        view.enter_search();
+        view.search("all", SearchOptions::default());
+        assert_eq!(view.cursor_position, Position { row: 1, col: 11 });
+
+        view.search_next("all", SearchOptions::default());
+        assert_eq!(view.cursor_position, Position { row: 4, col: 8 });
+
+        view.exit_search();
+        assert_eq!(view.cursor_position, Position { row: 4, col: 8 });
+    }
+
+    #[test]
+    fn dismiss_search_restores_cursor() {
+        let mut view = setup();
+        view.cursor_position = Position { row: 0, col: 2 };
+
+        view.enter_search();
+        view.search("good", SearchOptions::default());
+        assert_eq!(view.cursor_position, Position { row: 4, col: 0 });
+
+        view.dismiss_search();
+        assert_eq!(view.cursor_position, Position { row: 0, col: 2 });
+        assert_eq!(view.scroll_offset, Position { row: 0, col: 0 });
+    }
+
+    #[test]
+    fn jump_to_char_moves_to_next_occurrence_after_cursor() {
+        let mut view = setup();
+        view.cursor_position = Position { row: 1, col: 6 }; // at 'e' of "are"
+
+        view.jump_to_char('e');
+        assert_eq!(view.cursor_position, Position { row: 1, col: 9 });
+
+        view.jump_to_char('o');
+        assert_eq!(view.cursor_position, Position { row: 1, col: 16 });
+
+        // no further 'o' on the line
+        view.repeat_jump();
+        assert_eq!(view.cursor_position, Position { row: 1, col: 16 });
+    }
+
+    #[test]
+    fn jump_to_char_without_occurrence_is_noop() {
+        let mut view = setup();
+        view.cursor_position = Position { row: 1, col: 1 };
+
+        view.jump_to_char('x');
+        assert_eq!(view.cursor_position, Position { row: 1, col: 1 });
+    }
+
+    #[test]
+    fn jump_to_char_multibyte_target() {
+        let mut view = setup();
+        view.cursor_position = Position { row: 3, col: 0 }; // at first 👋
+
+        view.jump_to_char('👋');
+        assert_eq!(view.cursor_position, Position { row: 3, col: 2 });
+    }
+
+    #[test]
+    fn overwrite_replaces_char_under_cursor() {
+        let mut view = View::default();
+        view.buffer.push("Hxllo");
+        view.overwrite = true;
+        view.cursor_position = Position { row: 0, col: 1 };
+
+        view.insert('e');
+
+        let line = view.buffer.get_line(0).unwrap();
+        let full_width = line.position_of(line.len());
+        assert_eq!(line.get(0..full_width), "Hello");
+        assert_eq!(view.cursor_position, Position { row: 0, col: 2 });
+        assert!(view.needs_redraw);
+    }
+
+    #[test]
+    fn overwrite_wide_grapheme_with_narrow_updates_grid() {
+        let mut view = View {
+            size: Size {
+                width: 80,
+                height: 24,
+            },
+            ..Default::default()
+        };
+        view.buffer.push("a👋b");
+        view.overwrite = true;
+        view.cursor_position = Position { row: 0, col: 1 }; // at 👋
+
+        view.insert('x');
+
+        let line = view.buffer.get_line(0).unwrap();
+        let full_width = line.position_of(line.len());
+        assert_eq!(full_width, 3);
+        assert_eq!(line.get(0..full_width), "axb");
+        assert_eq!(view.cursor_position, Position { row: 0, col: 2 });
+        let grid = view.get_cursor_position();
+        assert_eq!(grid, Position { row: 0, col: 2 });
+    }
 
-        assert_eq!(view.cursor_position, expected_position);
-        assert_eq!(view.scroll_offset, expected_offset);
+    #[test]
+    fn overwrite_at_end_of_line_appends() {
+        let mut view = View::default();
+        view.buffer.push("Hello");
+        view.overwrite = true;
+        let end = view.buffer.line_len(0);
+        view.cursor_position = Position { row: 0, col: end };
+
+        view.insert('!');
+
+        let line = view.buffer.get_line(0).unwrap();
+        let full_width = line.position_of(line.len());
+        assert_eq!(line.get(0..full_width), "Hello!");
+        assert_eq!(
+            view.cursor_position,
+            Position {
+                row: 0,
+                col: end + 1
+            }
+        );
     }
 
     #[test]
-    fn move_left_moves_offset() {
+    fn missing_file_opens_as_new_file() -> std::io::Result<()> {
         let mut view = setup();
-        view.cursor_position = Position { row: 1, col: 8 };
-        view.scroll_offset = Position { row: 1, col: 7 };
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let path = std::env::temp_dir().join(format!("hecto_test_view_missing_file_{nanos}"));
+        let file_name = path.to_str().unwrap();
+        view.load(file_name)?;
+        let status = view.get_status();
+        assert_eq!(status.file_name.as_deref(), Some(file_name));
+        assert!(status.is_new_file);
+        assert_eq!(view.buffer.num_lines(), 0);
+        Ok(())
+    }
 
-        view.move_cursor(&Direction::Left);
-        let expected_position = Position { row: 1, col: 7 };
-        let expected_offset = Position { row: 1, col: 7 };
-        assert_eq!(view.cursor_position, expected_position);
-        assert_eq!(view.scroll_offset, expected_offset);
+    #[test]
+    fn unreadable_file_is_an_error() {
+        let denied = std::io::Error::from(std::io::ErrorKind::PermissionDenied);
+        let result = View::loaded_or_new(Err(denied), "secret.txt");
+        assert_eq!(
+            result.err().map(|err| err.kind()),
+            Some(std::io::ErrorKind::PermissionDenied)
+        );
 
-        view.move_cursor(&Direction::Left);
-        let expected_position = Position { row: 1, col: 6 };
-        let expected_offset = Position { row: 1, col: 6 };
-        assert_eq!(view.cursor_position, expected_position);
-        assert_eq!(view.scroll_offset, expected_offset);
+        let missing = std::io::Error::from(std::io::ErrorKind::NotFound);
+        let buffer = View::loaded_or_new(Err(missing), "new.txt").unwrap();
+        assert!(buffer.is_new_file());
+        assert_eq!(buffer.file_name(), Some("new.txt"));
     }
 
     #[test]
-    fn move_right_moves_offset() {
+    fn cursor_reaches_empty_line_after_final_newline() -> std::io::Result<()> {
         let mut view = setup();
-        view.cursor_position = Position { row: 1, col: 8 };
-        view.scroll_offset = Position { row: 1, col: 5 };
+        view.load_from_reader("a\n".as_bytes())?;
+        view.move_cursor(&Direction::Down);
+        assert_eq!(view.cursor_position, Position { row: 1, col: 0 });
 
-        view.move_cursor(&Direction::Right);
-        let expected_position = Position { row: 1, col: 9 };
-        let expected_offset = Position { row: 1, col: 5 };
+        view.insert('b');
+        assert_eq!(contents(&view), vec!["a", "b"]);
+        Ok(())
+    }
+
+    #[test]
+    fn insert_newline_moves_cursor_to_start_of_new_line() {
+        let mut view = setup();
+        view.cursor_position = Position { row: 0, col: 5 };
+        view.handle_command(EditorCommand::InsertNewline);
+        assert_eq!(view.cursor_position, Position { row: 1, col: 0 });
         assert_eq!(
-            view.cursor_position, expected_position,
-            "expected position 1"
+            contents(&view)[..3],
+            ["Hello", " world!", "How are we all doing?"]
         );
-        assert_eq!(view.scroll_offset, expected_offset, "expected offset 1");
+    }
 
-        view.move_cursor(&Direction::Right);
-        let expected_position = Position { row: 1, col: 10 };
-        let expected_offset = Position { row: 1, col: 6 };
+    #[test]
+    fn bracket_pair_for_cursor_on_opening_brace() {
+        let mut view = setup();
+        view.buffer.push("fn main() {");
+        view.buffer.push("    if x { y(); }");
+        view.buffer.push("}");
+        view.cursor_position = Position { row: 5, col: 10 };
         assert_eq!(
-            view.cursor_position, expected_position,
-            "expected position 2"
+            view.bracket_pair(),
+            Some((Position { row: 5, col: 10 }, Position { row: 7, col: 0 }))
         );
-        assert_eq!(view.scroll_offset, expected_offset, "expected offset 2");
 
-        view.move_cursor(&Direction::Right);
-        let expected_position = Position { row: 1, col: 11 };
-        let expected_offset = Position { row: 1, col: 7 };
+        // just past a closing bracket also counts
+        view.cursor_position = Position { row: 6, col: 17 };
         assert_eq!(
-            view.cursor_position, expected_position,
-            "expected position 3"
+            view.bracket_pair(),
+            Some((Position { row: 6, col: 16 }, Position { row: 6, col: 9 }))
         );
-        assert_eq!(view.scroll_offset, expected_offset, "expected offset 3");
+
+        view.cursor_position = Position { row: 0, col: 0 };
+        assert_eq!(view.bracket_pair(), None);
     }
 
     #[test]
-    fn move_up_moves_offset() {
+    fn end_of_buffer_marker_is_configurable() {
         let mut view = setup();
-        view.cursor_position = Position { row: 4, col: 0 };
-        view.scroll_offset = Position { row: 3, col: 0 };
+        view.buffer = Buffer::default();
+        view.buffer.push("Hi");
+        assert_eq!(view.render_to_lines(), vec!["Hi", "~", "~"]);
 
-        view.move_cursor(&Direction::Up);
-        let expected_position = Position { row: 3, col: 0 };
-        let expected_offset = Position { row: 3, col: 0 };
+        view.config.end_of_buffer_marker = String::new();
+        assert_eq!(view.render_to_lines(), vec!["Hi", "", ""]);
+
+        view.config.end_of_buffer_marker = "·········".to_string();
+        assert_eq!(view.render_to_lines(), vec!["Hi", "·····", "·····"]);
+    }
+
+    #[test]
+    fn typing_with_two_cursors_inserts_at_both() {
+        let mut view = setup();
+        view.cursor_position = Position { row: 0, col: 5 };
+        view.handle_command(EditorCommand::AddCursorBelow);
+        assert_eq!(view.extra_cursors, vec![Position { row: 1, col: 5 }]);
+
+        view.handle_command(EditorCommand::Insert('!'));
+        view.handle_command(EditorCommand::Insert('?'));
         assert_eq!(
-            view.cursor_position, expected_position,
-            "expected position 1"
+            contents(&view)[..2],
+            ["Hello!? world!", "How a!?re we all doing?"]
         );
-        assert_eq!(view.scroll_offset, expected_offset, "expected offset 1");
+        assert_eq!(view.cursor_position, Position { row: 0, col: 7 });
+        assert_eq!(view.extra_cursors, vec![Position { row: 1, col: 7 }]);
 
-        view.move_cursor(&Direction::Up);
-        let expected_position = Position { row: 2, col: 0 };
-        let expected_offset = Position { row: 2, col: 0 };
+        view.handle_command(EditorCommand::DeleteLeft);
         assert_eq!(
-            view.cursor_position, expected_position,
-            "expected position 2"
+            contents(&view)[..2],
+            ["Hello! world!", "How a!re we all doing?"]
         );
-        assert_eq!(view.scroll_offset, expected_offset, "expected offset 2");
+
+        // any other command drops the secondary cursors
+        view.handle_command(EditorCommand::Move(Direction::Left));
+        assert!(view.extra_cursors.is_empty());
     }
 
     #[test]
-    fn move_down_moves_offset() {
+    fn add_cursor_below_clamps_column_and_stops_at_last_line() {
+        let mut view = setup();
+        view.cursor_position = Position { row: 3, col: 3 };
+        view.add_cursor_below();
+        assert_eq!(view.extra_cursors, vec![Position { row: 4, col: 3 }]);
+        view.add_cursor_below();
+        assert_eq!(view.extra_cursors.len(), 1);
+
+        view.extra_cursors.clear();
+        view.cursor_position = Position { row: 1, col: 10 };
+        view.add_cursor_below();
+        assert_eq!(view.extra_cursors, vec![Position { row: 2, col: 0 }]);
+    }
+
+    #[test]
+    fn filter_through_replaces_whole_buffer() -> std::io::Result<()> {
+        let mut view = setup();
+        view.cursor_position = Position { row: 4, col: 7 };
+        view.filter_through(|text| crate::editor::pipe::run("cat", text))?;
+        assert_eq!(
+            contents(&view),
+            vec![
+                "Hello world!",
+                "How are we all doing?",
+                "",
+                "👋Ｂ👋",
+                "Goodbye all"
+            ]
+        );
+
+        view.filter_through(|text| crate::editor::pipe::run("tr a-z A-Z", text))?;
+        assert_eq!(contents(&view)[4], "GOODBYE ALL");
+        assert_eq!(view.cursor_position, Position { row: 4, col: 7 });
+        Ok(())
+    }
+
+    #[test]
+    fn filter_through_replaces_selection_only() -> std::io::Result<()> {
+        let mut view = setup();
+        view.cursor_position = Position { row: 0, col: 6 };
+        view.select(&Direction::Down);
+        view.filter_through(|text| crate::editor::pipe::run("tr a-z A-Z", text))?;
+        assert_eq!(
+            contents(&view)[..2],
+            ["Hello WORLD!", "HOW ARe we all doing?"]
+        );
+        assert_eq!(view.cursor_position, Position { row: 0, col: 6 });
+        Ok(())
+    }
+
+    #[test]
+    fn failed_filter_leaves_buffer_unchanged() {
+        let mut view = setup();
+        let result = view.filter_through(|text| crate::editor::pipe::run("exit 1", text));
+        assert!(result.is_err());
+        assert_eq!(contents(&view)[0], "Hello world!");
+        assert!(!view.is_dirty());
+    }
+
+    #[test]
+    fn undo_reverts_typing_run_and_movement_splits_it() {
+        let mut view = setup();
+        view.cursor_position = Position { row: 0, col: 5 };
+        view.handle_command(EditorCommand::Insert(','));
+        view.handle_command(EditorCommand::Insert(' '));
+        view.handle_command(EditorCommand::Move(Direction::End));
+        view.handle_command(EditorCommand::Insert('!'));
+        assert_eq!(contents(&view)[0], "Hello,  world!!");
+
+        view.handle_command(EditorCommand::Undo);
+        assert_eq!(contents(&view)[0], "Hello,  world!");
+        assert_eq!(view.cursor_position, Position { row: 0, col: 14 });
+
+        view.handle_command(EditorCommand::Undo);
+        assert_eq!(contents(&view)[0], "Hello world!");
+        assert_eq!(view.cursor_position, Position { row: 0, col: 5 });
+
+        view.handle_command(EditorCommand::Redo);
+        assert_eq!(contents(&view)[0], "Hello,  world!");
+    }
+
+    #[test]
+    fn ascii_only_renders_ascii_stand_ins() {
+        let mut view = setup();
+        view.buffer = Buffer::default();
+        view.buffer.push("a\u{200B}b");
+        view.buffer.push("abcd👋");
+        view.config.ascii_only = true;
+        assert_eq!(view.render_to_lines(), vec!["a.b", "abcd>", "~"]);
+    }
+
+    #[test]
+    fn add_to_number_increments_and_decrements() {
         let mut view = setup();
+        view.buffer = Buffer::default();
+        view.buffer.push("count: 9");
+        view.buffer.push("x 0");
+        view.buffer.push("id 007");
+        view.buffer.push("none");
+
+        view.handle_command(EditorCommand::AddToNumber(1));
+        assert_eq!(contents(&view)[0], "count: 10");
+        assert_eq!(view.cursor_position, Position { row: 0, col: 8 });
+
+        view.cursor_position = Position { row: 1, col: 0 };
+        view.handle_command(EditorCommand::AddToNumber(-1));
+        assert_eq!(contents(&view)[1], "x -1");
+        view.handle_command(EditorCommand::AddToNumber(1));
+        assert_eq!(contents(&view)[1], "x 0");
+
         view.cursor_position = Position { row: 2, col: 0 };
-        view.scroll_offset = Position { row: 1, col: 0 };
+        view.handle_command(EditorCommand::AddToNumber(1));
+        assert_eq!(contents(&view)[2], "id 008");
 
-        view.move_cursor(&Direction::Down);
-        let expected_position = Position { row: 3, col: 0 };
-        let expected_offset = Position { row: 1, col: 0 };
+        view.cursor_position = Position { row: 3, col: 0 };
+        view.handle_command(EditorCommand::AddToNumber(1));
+        assert_eq!(contents(&view)[3], "none");
+        assert_eq!(view.cursor_position, Position { row: 3, col: 0 });
+    }
+
+    #[test]
+    fn status_reflects_detected_indent() -> std::io::Result<()> {
+        let mut view = setup();
+        assert_eq!(view.get_status().indent_to_string(), "Spaces: 4");
+
+        view.load_from_reader("fn main() {\n\tx();\n}\n".as_bytes())?;
+        assert_eq!(view.get_status().indent_to_string(), "Tabs: 8");
+
+        view.load_from_reader("a:\n  b: 1\n".as_bytes())?;
+        assert_eq!(view.get_status().indent_to_string(), "Spaces: 2");
+        Ok(())
+    }
+
+    #[test]
+    fn backspace_at_line_start_joins_with_previous_line() {
+        let mut view = setup();
+        view.cursor_position = Position { row: 1, col: 0 };
+        view.handle_command(EditorCommand::DeleteLeft);
+        assert_eq!(contents(&view)[0], "Hello world!How are we all doing?");
+        assert_eq!(view.cursor_position, Position { row: 0, col: 12 });
+
+        view.handle_command(EditorCommand::Undo);
         assert_eq!(
-            view.cursor_position, expected_position,
-            "expected position 1"
+            contents(&view)[..2],
+            ["Hello world!", "How are we all doing?"]
         );
-        assert_eq!(view.scroll_offset, expected_offset, "expected offset 1");
+    }
+
+    #[test]
+    fn delete_at_line_end_joins_next_line() {
+        let mut view = setup();
+        view.cursor_position = Position { row: 1, col: 21 };
+        view.handle_command(EditorCommand::DeleteRight);
+        assert_eq!(contents(&view)[1..3], ["How are we all doing?", "👋Ｂ👋"]);
+        assert_eq!(view.cursor_position, Position { row: 1, col: 21 });
+    }
+
+    #[test]
+    fn hscroll_step_jumps_past_right_edge() {
+        let mut view = View::new(
+            Size {
+                width: 20,
+                height: 3,
+            },
+            Config::default(),
+        );
+        view.buffer.push(&"x".repeat(60));
+        view.config.hscroll_step = 8;
+        view.cursor_position = Position { row: 0, col: 19 };
+        view.move_cursor(&Direction::Right);
+        assert_eq!(view.scroll_offset.col, 8);
+
+        // within the view nothing moves
+        view.move_cursor(&Direction::Right);
+        assert_eq!(view.scroll_offset.col, 8);
+
+        // and back past the left edge
+        view.cursor_position = Position { row: 0, col: 8 };
+        view.move_cursor(&Direction::Left);
+        assert_eq!(view.scroll_offset.col, 0);
+    }
+
+    #[test]
+    fn moving_onto_short_line_resets_hscroll() {
+        let mut view = View::new(
+            Size {
+                width: 20,
+                height: 3,
+            },
+            Config::default(),
+        );
+        view.buffer.push(&"x".repeat(60));
+        view.buffer.push("short");
+        view.move_cursor_to(Position { row: 0, col: 50 });
+        assert_eq!(view.scroll_offset.col, 31);
 
         view.move_cursor(&Direction::Down);
-        let expected_position = Position { row: 4, col: 0 };
-        let expected_offset = Position { row: 2, col: 0 };
+        assert_eq!(view.cursor_position, Position { row: 1, col: 5 });
+        assert_eq!(view.scroll_offset.col, 0);
+        assert_eq!(view.get_cursor_position(), Position { row: 1, col: 5 });
+    }
+
+    #[test]
+    fn hscroll_resets_once_every_line_fits() {
+        let mut view = View::new(
+            Size {
+                width: 20,
+                height: 3,
+            },
+            Config::default(),
+        );
+        view.buffer.push(&"x".repeat(30));
+        view.move_cursor_to(Position { row: 0, col: 30 });
+        assert_eq!(view.scroll_offset.col, 11);
+
+        // the cursor stays right of the offset, yet nothing is hidden
+        view.buffer
+            .delete_range(Position { col: 15, row: 0 }, Position { col: 30, row: 0 });
+        view.move_cursor_to(Position { row: 0, col: 12 });
+        assert_eq!(view.scroll_offset.col, 0);
+    }
+
+    #[test]
+    fn word_motions_step_over_a_fold() {
+        let mut view = View::new(
+            Size {
+                width: 20,
+                height: 5,
+            },
+            Config::default(),
+        );
+        for line in ["fn a() {", "    x();", "    y();", "}"] {
+            view.buffer.push(line);
+        }
+        view.handle_command(EditorCommand::ToggleFold);
+        view.move_cursor_to(Position { col: 7, row: 0 });
+
+        view.move_cursor(&Direction::WordRight);
+        assert_eq!(view.cursor_position, Position { col: 0, row: 1 });
+        view.move_cursor(&Direction::WordRight);
+        assert_eq!(view.cursor_position, Position { col: 0, row: 3 });
+        view.move_cursor(&Direction::WordLeft);
+        assert_eq!(view.cursor_position, Position { col: 0, row: 1 });
+        view.move_cursor(&Direction::WordLeft);
+        assert_eq!(view.cursor_position, Position { col: 7, row: 0 });
+    }
+
+    #[test]
+    fn typing_on_a_fold_summary_unfolds_it() {
+        let mut view = View::default();
+        for line in ["fn a() {", "    x();", "    y();", "}"] {
+            view.buffer.push(line);
+        }
+        view.handle_command(EditorCommand::ToggleFold);
+        view.move_cursor_to(Position { col: 0, row: 1 });
+        view.handle_command(EditorCommand::Insert('z'));
+        assert!(view.folds.is_empty());
+        assert_eq!(contents(&view)[1], "z    x();");
+    }
+
+    #[test]
+    fn undo_unfolds_the_lines_it_restores() {
+        let mut view = View::default();
+        for line in ["fn a() {", "    x();", "    y();", "}"] {
+            view.buffer.push(line);
+        }
+        view.move_cursor_to(Position { col: 4, row: 1 });
+        view.handle_command(EditorCommand::Insert('q'));
+        view.move_cursor_to(Position { col: 0, row: 0 });
+        view.handle_command(EditorCommand::ToggleFold);
+        assert!(!view.folds.is_empty());
+
+        view.handle_command(EditorCommand::Undo);
+        assert!(view.folds.is_empty());
+        assert_eq!(contents(&view)[1], "    x();");
+    }
+
+    #[test]
+    fn toggle_fold_collapses_indented_block() {
+        let mut view = View::new(
+            Size {
+                width: 20,
+                height: 4,
+            },
+            Config::default(),
+        );
+        for line in ["fn a() {", "    x();", "    y();", "}", "fn b() {}"] {
+            view.buffer.push(line);
+        }
+        view.handle_command(EditorCommand::ToggleFold);
         assert_eq!(
-            view.cursor_position, expected_position,
-            "expected position 2"
+            view.render_to_lines(),
+            vec!["fn a() {", "··· 2 lines", "}", "fn b() {}"]
         );
-        assert_eq!(view.scroll_offset, expected_offset, "expected offset 2");
+
+        // the fold takes a single step to pass
+        view.handle_command(EditorCommand::Move(Direction::Down));
+        assert_eq!(view.cursor_position, Position { row: 1, col: 0 });
+        view.handle_command(EditorCommand::Move(Direction::Down));
+        assert_eq!(view.cursor_position, Position { row: 3, col: 0 });
+        assert_eq!(view.get_cursor_position(), Position { row: 2, col: 0 });
+
+        view.handle_command(EditorCommand::Move(Direction::Up));
+        view.handle_command(EditorCommand::ToggleFold);
+        assert_eq!(view.render_to_lines()[1..3], ["    x();", "    y();"]);
     }
 
     #[test]
-    fn move_down_doesnt_go_below_end() {
+    fn double_click_selects_word_under_it() {
         let mut view = setup();
-        view.cursor_position = Position { row: 3, col: 1 };
-        view.scroll_offset = Position { row: 3, col: 0 };
+        view.handle_command(EditorCommand::DoubleClick(Position { col: 2, row: 1 }));
+        assert_eq!(
+            view.selection(),
+            Some((Position { col: 0, row: 1 }, Position { col: 3, row: 1 }))
+        );
 
-        view.move_cursor(&Direction::Down);
-        let expected_position = Position { row: 4, col: 1 };
-        let expected_offset = Position { row: 3, col: 0 };
+        view.handle_command(EditorCommand::Click(Position { col: 1, row: 0 }));
+        assert_eq!(view.selection(), None);
+        assert_eq!(view.cursor_position, Position { col: 1, row: 0 });
+    }
+
+    #[test]
+    fn search_match_is_highlighted_with_theme_color() {
+        let mut view = setup();
+        view.enter_search();
+        view.search("are", SearchOptions::default());
         assert_eq!(
-            view.cursor_position, expected_position,
-            "expected position 1"
+            view.highlights_on(1, None),
+            vec![Highlight {
+                range: 4..7,
+                color: view.config.theme.search_match,
+            }]
         );
-        assert_eq!(view.scroll_offset, expected_offset, "expected offset 1");
+        view.exit_search();
+        assert!(view.highlights_on(1, None).is_empty());
+    }
 
-        view.move_cursor(&Direction::Down);
-        let expected_position = Position { row: 4, col: 1 };
-        let expected_offset = Position { row: 3, col: 0 };
+    #[test]
+    fn repeat_edit_reapplies_insert_and_delete() {
+        let mut view = setup();
+        view.handle_command(EditorCommand::Insert('x'));
+        view.handle_command(EditorCommand::RepeatEdit);
         assert_eq!(
-            view.cursor_position, expected_position,
-            "expected position 1"
+            view.buffer.get_line(0).unwrap().to_string(),
+            "xxHello world!"
         );
-        assert_eq!(view.scroll_offset, expected_offset, "expected offset 1");
+
+        view.handle_command(EditorCommand::Move(Direction::Home));
+        view.handle_command(EditorCommand::DeleteRight);
+        view.handle_command(EditorCommand::Move(Direction::Right));
+        view.handle_command(EditorCommand::RepeatEdit);
+        assert_eq!(view.buffer.get_line(0).unwrap().to_string(), "xello world!");
+    }
+
+    #[test]
+    fn movement_is_not_a_repeatable_edit() {
+        let mut view = setup();
+        view.handle_command(EditorCommand::Move(Direction::Right));
+        view.handle_command(EditorCommand::RepeatEdit);
+        assert_eq!(view.buffer.get_line(0).unwrap().to_string(), "Hello world!");
+    }
+
+    #[test]
+    fn unique_lines_clamps_cursor() {
+        let mut view = View::default();
+        for line in ["a", "b", "b", "b"] {
+            view.buffer.push(line);
+        }
+        view.cursor_position = Position { col: 1, row: 3 };
+        view.handle_command(EditorCommand::UniqueLines);
+        assert_eq!(view.buffer.num_lines(), 2);
+        assert_eq!(view.cursor_position, Position { col: 1, row: 1 });
+        view.handle_command(EditorCommand::Undo);
+        assert_eq!(view.buffer.num_lines(), 4);
+    }
+
+    #[test]
+    fn unique_lines_without_duplicates_is_not_an_edit() {
+        let mut view = View::default();
+        view.buffer.push("a");
+        view.handle_command(EditorCommand::Insert('b'));
+        view.handle_command(EditorCommand::Undo);
+        view.handle_command(EditorCommand::UniqueLines);
+        view.handle_command(EditorCommand::Redo);
+        assert_eq!(view.current_line(), "ba");
+    }
+
+    #[test]
+    fn search_progress_counts_matches() {
+        let mut view = setup();
+        view.enter_search();
+        view.search("all", SearchOptions::default());
+        assert_eq!(view.search_progress(), Some((Some(1), 2)));
+        view.search_next("all", SearchOptions::default());
+        assert_eq!(view.search_progress(), Some((Some(2), 2)));
+        view.search("allx", SearchOptions::default());
+        assert_eq!(view.search_progress(), Some((None, 0)));
+    }
+
+    #[test]
+    fn toggle_overwrite_flips_mode_and_redraws() {
+        let mut view = setup();
+        view.needs_redraw = false;
+        view.handle_command(EditorCommand::ToggleOverwrite);
+        assert!(view.overwrite);
+        assert!(view.needs_redraw);
+        assert!(view.get_status().overwrite);
+
+        view.handle_command(EditorCommand::ToggleOverwrite);
+        assert!(!view.get_status().overwrite);
     }
 
     #[test]
-    fn move_pageup_subtract_height() {
+    fn text_past_max_line_length_is_highlighted() {
         let mut view = setup();
-        view.cursor_position = Position { row: 5, col: 0 };
-        view.scroll_offset = Position { row: 3, col: 0 };
-
-        view.move_cursor(&Direction::PageUp);
-        let expected_position = Position { row: 2, col: 0 };
-        let expected_offset = Position { row: 2, col: 0 };
+        view.config.max_line_length = Some(10);
         assert_eq!(
-            view.cursor_position, expected_position,
-            "expected position 1"
+            view.highlights_on(1, None),
+            vec![Highlight {
+                range: 10..21,
+                color: view.config.theme.overflow,
+            }]
         );
-        assert_eq!(view.scroll_offset, expected_offset, "expected offset 1");
+        assert!(view.highlights_on(3, None).is_empty());
+    }
 
-        view.move_cursor(&Direction::PageUp);
-        let expected_position = Position { row: 0, col: 0 };
-        let expected_offset = Position { row: 0, col: 0 };
-        assert_eq!(
-            view.cursor_position, expected_position,
-            "expected position 2"
+    #[test]
+    fn undo_restores_scroll_offset_of_the_edit() {
+        let mut view = View::new(
+            Size {
+                width: 10,
+                height: 3,
+            },
+            Config::default(),
         );
-        assert_eq!(view.scroll_offset, expected_offset, "expected offset 2");
+        for index in 0..20 {
+            view.buffer.push(&format!("line {index}"));
+        }
+        view.move_cursor_to(Position { col: 0, row: 10 });
+        view.move_cursor(&Direction::Up);
+        let scroll_offset = view.scroll_offset;
+        view.handle_command(EditorCommand::Insert('x'));
+
+        view.move_cursor_to(Position { col: 0, row: 0 });
+        view.handle_command(EditorCommand::Undo);
+        assert_eq!(view.cursor_position, Position { col: 0, row: 9 });
+        assert_eq!(view.scroll_offset, scroll_offset);
+        assert_eq!(scroll_offset.row, 8);
     }
 
     #[test]
-    fn move_pagedown_add_height() {
-        let mut view = setup();
-        view.cursor_position = Position { row: 0, col: 1 };
-        view.scroll_offset = Position { row: 0, col: 0 };
-
-        view.move_cursor(&Direction::PageDown);
-        let expected_position = Position { row: 3, col: 1 };
-        let expected_offset = Position { row: 1, col: 0 };
-        assert_eq!(
-            view.cursor_position, expected_position,
-            "expected position 1"
+    fn eol_marker_follows_visible_line_ends() {
+        let mut view = View::new(
+            Size {
+                width: 8,
+                height: 2,
+            },
+            Config::default(),
         );
-        assert_eq!(view.scroll_offset, expected_offset, "expected offset 1");
+        view.buffer.push("short");
+        view.buffer.push("much too long");
+        view.handle_command(EditorCommand::ToggleEol);
+        assert_eq!(view.render_to_lines(), ["short¶", "much too"]);
+
+        view.config.ascii_only = true;
+        view.scroll_offset.col = 5;
+        assert_eq!(view.render_to_lines(), ["$", "too long"]);
+        view.scroll_offset.col = 6;
+        assert_eq!(view.render_to_lines(), ["", "oo long$"]);
+    }
 
-        view.move_cursor(&Direction::PageDown);
-        // note row is 4 because we cannot go out of bounds
-        let expected_position = Position { row: 4, col: 1 };
-        let expected_offset = Position { row: 2, col: 0 };
-        assert_eq!(
-            view.cursor_position, expected_position,
-            "expected position 2"
-        );
-        assert_eq!(view.scroll_offset, expected_offset, "expected offset 2");
+    #[test]
+    fn align_on_needs_a_selection() {
+        let mut view = View::default();
+        view.buffer.push("x = 1");
+        view.buffer.push("yy = 2");
+        view.align_on('=');
+        assert_eq!(view.buffer.get_line(0).unwrap().to_string(), "x = 1");
+
+        view.handle_command(EditorCommand::Select(Direction::Down));
+        view.handle_command(EditorCommand::Select(Direction::End));
+        view.align_on('=');
+        assert_eq!(view.buffer.get_line(0).unwrap().to_string(), "x  = 1");
+        view.handle_command(EditorCommand::Undo);
+        assert_eq!(view.buffer.get_line(0).unwrap().to_string(), "x = 1");
     }
 
     #[test]
-    fn get_cursor_position_counts_wide_chars() {
+    fn go_to_definition_jumps_to_fn_line() {
         let mut view = View::default();
-        view.buffer.push("ＡＢ");
-        view.cursor_position = Position { row: 0, col: 1 };
+        view.buffer.push("fn main() {");
+        view.buffer.push("    foo();");
+        view.buffer.push("}");
+        view.buffer.push("fn foo() {}");
+        view.move_cursor_to(Position { col: 5, row: 1 });
+        assert!(view.go_to_definition());
+        assert_eq!(view.cursor_position, Position { col: 3, row: 3 });
+
+        // on punctuation there is nothing to look up
+        view.move_cursor_to(Position { col: 7, row: 1 });
+        assert!(!view.go_to_definition());
+        assert_eq!(view.cursor_position, Position { col: 7, row: 1 });
+    }
 
-        let pos = view.get_cursor_position();
-        assert_eq!(pos, Position { row: 0, col: 2 });
+    #[test]
+    fn gutter_separator_shifts_cursor_column() {
+        let mut view = setup();
+        view.size.width = 20;
+        view.move_cursor_to(Position { col: 3, row: 1 });
+        assert_eq!(view.get_cursor_position().col, 3);
+
+        view.config.line_numbers = true;
+        // one digit and one column of padding
+        assert_eq!(view.get_cursor_position().col, 5);
+        assert_eq!(view.render_to_lines()[1], "2 How are we all doi");
+
+        view.config.gutter_separator = "│".to_string();
+        assert_eq!(view.get_cursor_position().col, 6);
+        assert_eq!(view.render_to_lines()[1], "2│ How are we all do");
     }
 
     #[test]
-    fn get_cursor_position_handles_zero_width() {
+    fn backspace_run_undoes_at_once_unless_moved_between() {
         let mut view = View::default();
-        view.buffer.push("a\u{200B}b");
-        view.cursor_position = Position { row: 0, col: 1 };
+        view.buffer.push("abcdef");
+        view.move_cursor_to(Position { col: 6, row: 0 });
+        for _ in 0..3 {
+            view.handle_command(EditorCommand::DeleteLeft);
+        }
+        view.handle_command(EditorCommand::Undo);
+        assert_eq!(view.buffer.get_line(0).unwrap().to_string(), "abcdef");
+
+        view.move_cursor_to(Position { col: 6, row: 0 });
+        view.handle_command(EditorCommand::DeleteLeft);
+        view.handle_command(EditorCommand::Move(Direction::Left));
+        view.handle_command(EditorCommand::DeleteLeft);
+        assert_eq!(view.buffer.get_line(0).unwrap().to_string(), "abce");
+        view.handle_command(EditorCommand::Undo);
+        assert_eq!(view.buffer.get_line(0).unwrap().to_string(), "abcde");
+    }
 
-        let pos = view.get_cursor_position();
-        assert_eq!(pos, Position { row: 0, col: 1 });
+    #[test]
+    fn selected_short_line_is_padded_in_selection_color() {
+        let mut view = setup();
+        view.size.width = 20;
+        view.handle_command(EditorCommand::Select(Direction::Down));
+        let segments = view.line_segments(0, view.buffer.get_line(0).unwrap(), None);
+        let width: usize = segments.iter().map(|(text, _)| text.width()).sum();
+        assert_eq!(width, 20);
+        let selection = Some(view.config.theme.selection);
+        assert_eq!(segments.last(), Some(&(" ".repeat(8), selection)));
+
+        // below the selection the padding is plain
+        let segments = view.line_segments(2, view.buffer.get_line(2).unwrap(), None);
+        assert_eq!(segments.last(), Some(&(" ".repeat(20), None)));
     }
 
     #[test]
-    fn delete_right_in_middle_deletes_and_keeps_cursor() {
+    fn insert_str_moves_past_text() {
         let mut view = View::default();
-        view.buffer.push("Hello");
-        view.cursor_position = Position { row: 0, col: 1 }; // at 'e'
-        view.needs_redraw = false;
+        view.buffer.push("ab");
+        view.move_cursor_to(Position { col: 1, row: 0 });
+        view.insert_str("Ｘy");
+        assert_eq!(view.buffer.get_line(0).unwrap().to_string(), "aＸyb");
+        assert_eq!(view.cursor_position, Position { col: 3, row: 0 });
+        assert_eq!(view.get_cursor_position().col, 4);
+    }
 
-        view.delete_right();
+    #[test]
+    fn misspelled_words_are_highlighted_with_dictionary() {
+        let mut view = setup();
+        view.size.width = 30;
+        assert!(view.highlights_on(1, None).is_empty());
 
-        let line = view.buffer.get_line(0).unwrap();
-        let full = line.position_of(line.len());
-        assert_eq!(line.get(0..full), "Hllo");
-        assert_eq!(view.cursor_position, Position { row: 0, col: 1 });
-        assert!(view.needs_redraw);
+        view.dictionary = Some(Dictionary::from_words(["how", "are", "we", "all"]));
+        assert_eq!(
+            view.highlights_on(1, None),
+            vec![Highlight {
+                range: 15..20,
+                color: view.config.theme.misspelled,
+            }]
+        );
     }
 
     #[test]
-    fn delete_right_at_end_noop() {
+    fn goto_percent_moves_proportionally() {
         let mut view = View::default();
-        view.buffer.push("Hello");
-        let end = view.buffer.line_len(0);
-        view.cursor_position = Position { row: 0, col: end };
-        view.needs_redraw = false;
-
-        view.delete_right();
+        for index in 0..11 {
+            view.buffer.push(&format!("line {index}"));
+        }
+        view.goto_percent(50);
+        assert_eq!(view.cursor_position, Position { col: 0, row: 5 });
+        view.goto_percent(100);
+        assert_eq!(view.cursor_position, Position { col: 0, row: 10 });
+        view.goto_percent(0);
+        assert_eq!(view.cursor_position, Position { col: 0, row: 0 });
+        view.goto_percent(250);
+        assert_eq!(view.cursor_position, Position { col: 0, row: 10 });
+    }
 
-        let line = view.buffer.get_line(0).unwrap();
-        let full = line.position_of(line.len());
-        assert_eq!(line.get(0..full), "Hello");
-        assert_eq!(view.cursor_position, Position { row: 0, col: end });
-        assert!(!view.needs_redraw);
+    #[test]
+    fn minimap_takes_columns_from_text() {
+        let mut view = setup();
+        view.size.width = 10;
+        assert!(view.minimap_cell(0).is_none());
+        assert_eq!(view.render_to_lines()[0], "Hello worl");
+
+        view.config.minimap = true;
+        assert_eq!(view.render_to_lines()[0], "Hello wo░░");
+        // five lines over three rows, two lines each; rows 0 and 1 hold
+        // the three lines in view
+        let band = Some(view.config.theme.minimap_view);
+        assert_eq!(view.minimap_cell(0), Some(("░░".to_string(), band)));
+        assert_eq!(view.minimap_cell(2), Some(("░░".to_string(), None)));
     }
 
     #[test]
-    fn delete_left_in_middle_moves_cursor_and_deletes_left() {
+    fn retab_follows_indent_style_and_undoes() {
         let mut view = View::default();
-        view.buffer.push("Hello");
-        view.cursor_position = Position { row: 0, col: 2 }; // between e and first l
-        view.needs_redraw = false;
+        view.buffer.push("\tx();");
+        view.indent.style = IndentStyle::Spaces;
+        view.handle_command(EditorCommand::Retab);
+        let spaces = " ".repeat(view.config.tab_width);
+        assert_eq!(
+            view.buffer.get_line(0).unwrap().to_string(),
+            spaces + "x();"
+        );
+        view.handle_command(EditorCommand::Undo);
+        assert_eq!(view.buffer.get_line(0).unwrap().to_string(), "\tx();");
+    }
 
-        view.delete_left();
+    #[test]
+    fn combining_mark_merges_into_previous_grapheme() {
+        let mut view = View::default();
+        view.buffer.push("ex");
+        view.move_cursor_to(Position { col: 1, row: 0 });
+        view.insert('\u{301}');
 
         let line = view.buffer.get_line(0).unwrap();
-        let full = line.position_of(line.len());
-        assert_eq!(line.get(0..full), "Hllo");
-        assert_eq!(view.cursor_position, Position { row: 0, col: 1 });
-        assert!(view.needs_redraw);
+        assert_eq!(line.to_string(), "e\u{301}x");
+        assert_eq!(line.len(), 2);
+        assert_eq!(line.width(), 2);
+        assert_eq!(line.get(0..1), "e\u{301}");
+        assert_eq!(view.cursor_position, Position { col: 1, row: 0 });
+        assert_eq!(view.get_cursor_position().col, 1);
+
+        // typing on continues after the accented letter
+        view.insert('y');
+        let line = view.buffer.get_line(0).unwrap();
+        assert_eq!(line.to_string(), "e\u{301}yx");
+        assert_eq!(view.cursor_position, Position { col: 2, row: 0 });
     }
 
     #[test]
-    fn delete_left_at_line_start_noop() {
+    fn combining_mark_at_line_start_stands_alone() {
         let mut view = View::default();
-        view.buffer.push("Hello");
-        view.cursor_position = Position { row: 0, col: 0 };
-        view.needs_redraw = false;
-
-        view.delete_left();
-
+        view.buffer.push("e");
+        view.insert('\u{301}');
         let line = view.buffer.get_line(0).unwrap();
-        let full = line.position_of(line.len());
-        assert_eq!(line.get(0..full), "Hello");
-        assert_eq!(view.cursor_position, Position { row: 0, col: 0 });
-        assert!(!view.needs_redraw);
+        assert_eq!(line.len(), 2);
+        assert_eq!(view.cursor_position, Position { col: 1, row: 0 });
     }
 
     #[test]
-    fn delete_left_wide_grapheme_updates_grid() {
-        let mut view = View {
-            size: Size { width: 80, height: 24 },
-            ..Default::default()
-        };
-        view.buffer.push("a👋b");
-        view.cursor_position = Position { row: 0, col: 2 }; // after 👋
-
-        view.delete_left();
+    fn repeat_search_cycles_through_matches() {
+        let mut view = setup();
+        view.enter_search();
+        view.search("all", SearchOptions::default());
+        view.exit_search();
+        assert_eq!(view.cursor_position, Position { row: 1, col: 11 });
+
+        assert!(view.repeat_search(true));
+        assert_eq!(view.cursor_position, Position { row: 4, col: 8 });
+        // wraps around to the first match
+        assert!(view.repeat_search(true));
+        assert_eq!(view.cursor_position, Position { row: 1, col: 11 });
+
+        assert!(view.repeat_search(false));
+        assert_eq!(view.cursor_position, Position { row: 4, col: 8 });
+        assert!(view.repeat_search(false));
+        assert_eq!(view.cursor_position, Position { row: 1, col: 11 });
+    }
 
-        let line = view.buffer.get_line(0).unwrap();
-        let full = line.position_of(line.len());
-        assert_eq!(line.get(0..full), "ab");
-        assert_eq!(view.cursor_position, Position { row: 0, col: 1 });
-        let grid = view.get_cursor_position();
-        assert_eq!(grid, Position { row: 0, col: 1 });
+    #[test]
+    fn repeat_search_without_query_finds_nothing() {
+        let mut view = setup();
+        assert!(!view.repeat_search(true));
+        view.search("", SearchOptions::default());
+        assert!(!view.repeat_search(false));
+        assert_eq!(view.cursor_position, Position::default());
     }
 
     #[test]
-    fn delete_right_wide_grapheme_keeps_cursor_and_updates_grid() {
+    fn relative_gutter_turns_absolute_while_prompting() {
+        let mut view = setup();
+        view.config.line_numbers = true;
+        view.config.relative_line_numbers = true;
+        view.move_cursor_to(Position { col: 0, row: 1 });
+        assert_eq!(view.gutter_label(3), "2 ");
+        assert_eq!(view.gutter_label(1), "2 ");
+
+        view.set_prompt_open(true);
+        assert_eq!(view.gutter_label(3), "4 ");
+        assert_eq!(view.gutter_label(1), "2 ");
+    }
+
+    fn with_trailing_blank_lines(trim_trailing_blank_lines: bool) -> View {
         let mut view = View {
-            size: Size { width: 80, height: 24 },
-            ..Default::default()
+            config: Config {
+                trim_trailing_blank_lines,
+                ..Config::default()
+            },
+            ..View::default()
         };
-        view.buffer.push("a👋b");
-        view.cursor_position = Position { row: 0, col: 1 }; // at 👋
+        view.buffer.push("text");
+        view.buffer.push("");
+        view.buffer.push("");
+        view.move_cursor_to(Position { col: 0, row: 2 });
+        view
+    }
 
-        view.delete_right();
+    #[test]
+    fn buffer_can_turn_trimming_on_save_on() {
+        let mut view = with_trailing_blank_lines(false);
+        view.trim_before_save();
+        assert_eq!(view.buffer.num_lines(), 3);
+
+        view.handle_command(EditorCommand::ToggleTrimOnSave);
+        assert!(view.get_status().trims_on_save);
+        view.trim_before_save();
+        assert_eq!(contents(&view), vec!["text", ""]);
+        assert_eq!(view.cursor_position, Position { col: 0, row: 1 });
+    }
 
-        let line = view.buffer.get_line(0).unwrap();
-        let full = line.position_of(line.len());
-        assert_eq!(line.get(0..full), "ab");
-        assert_eq!(view.cursor_position, Position { row: 0, col: 1 });
-        let grid = view.get_cursor_position();
-        assert_eq!(grid, Position { row: 0, col: 1 });
+    #[test]
+    fn buffer_can_turn_trimming_on_save_off() {
+        let mut view = with_trailing_blank_lines(true);
+        assert!(view.trims_on_save());
+        view.toggle_trim_on_save();
+        assert!(!view.get_status().trims_on_save);
+        view.trim_before_save();
+        assert_eq!(view.buffer.num_lines(), 3);
     }
 
     #[test]
-    fn delete_right_zero_width_keeps_grid_cursor() {
-        let mut view = View {
-            size: Size { width: 80, height: 24 },
-            ..Default::default()
-        };
-        view.buffer.push("a\u{200B}b");
-        view.cursor_position = Position { row: 0, col: 1 }; // at zero-width
+    fn trimming_on_save_is_undoable_and_moves_extra_cursors() {
+        let mut view = with_trailing_blank_lines(true);
+        view.buffer.push("");
+        view.extra_cursors.push(Position { col: 0, row: 3 });
+        view.trim_before_save();
+        assert_eq!(contents(&view), vec!["text", ""]);
+        assert_eq!(view.extra_cursors, vec![Position { col: 0, row: 1 }]);
 
-        view.delete_right();
+        view.handle_command(EditorCommand::Undo);
+        assert_eq!(view.buffer.num_lines(), 4);
+    }
 
-        let line = view.buffer.get_line(0).unwrap();
-        let full = line.position_of(line.len());
-        assert_eq!(line.get(0..full), "ab");
-        assert_eq!(view.cursor_position, Position { row: 0, col: 1 });
-        let grid = view.get_cursor_position();
-        assert_eq!(grid, Position { row: 0, col: 1 });
+    #[test]
+    fn failed_save_does_not_trim() {
+        let mut view = with_trailing_blank_lines(true);
+        assert!(view.save().is_err());
+        assert_eq!(view.buffer.num_lines(), 3);
     }
 
     #[test]
-    fn insert_into_empty_buffer_creates_line_and_moves_cursor() {
+    fn balance_brackets_closes_at_cursor() {
         let mut view = View::default();
-        assert!(view.buffer.is_empty());
-
-        view.insert('A');
-
-        assert_eq!(view.buffer.num_lines(), 1);
-        let line = view.buffer.get_line(0).unwrap();
-        let full_width = line.position_of(line.len());
-        assert_eq!(line.get(0..full_width), "A");
-        assert_eq!(view.cursor_position, Position { row: 0, col: 1 });
-        assert!(view.needs_redraw);
+        view.buffer.push("foo(bar[");
+        view.move_cursor_to(Position { col: 8, row: 0 });
+        view.handle_command(EditorCommand::BalanceBrackets);
+        assert_eq!(view.current_line(), "foo(bar[])");
+        assert_eq!(view.cursor_position, Position { col: 10, row: 0 });
+
+        // now balanced, so nothing more to add
+        view.handle_command(EditorCommand::BalanceBrackets);
+        assert_eq!(view.current_line(), "foo(bar[])");
     }
 
     #[test]
-    fn insert_in_middle_moves_cursor() {
+    fn paste_expands_tabs_only_when_enabled() {
         let mut view = View::default();
-        view.buffer.push("Helo");
-        view.cursor_position = Position { row: 0, col: 2 };
-
-        view.insert('l');
+        view.handle_command(EditorCommand::Paste("a\tb".to_string()));
+        assert_eq!(view.current_line(), "a\tb");
 
-        let line = view.buffer.get_line(0).unwrap();
-        let full_width = line.position_of(line.len());
-        assert_eq!(line.get(0..full_width), "Hello");
-        assert_eq!(view.cursor_position, Position { row: 0, col: 3 });
-        assert!(view.needs_redraw);
+        let mut view = View::default();
+        view.config.paste_tabs_to_spaces = true;
+        view.config.tab_width = 4;
+        view.handle_command(EditorCommand::Paste("a\tb".to_string()));
+        assert_eq!(view.current_line(), "a   b");
+        // the next tab stop counts from the column the paste lands on
+        view.handle_command(EditorCommand::Paste("\tc".to_string()));
+        assert_eq!(view.current_line(), "a   b   c");
     }
 
     #[test]
-    fn insert_at_end_moves_cursor() {
+    fn paste_keeps_tabs_in_tab_indented_buffer() {
         let mut view = View::default();
-        view.buffer.push("Hello");
-        let end = view.buffer.line_len(0);
-        view.cursor_position = Position { row: 0, col: end };
-
-        view.insert('!');
-
-        let line = view.buffer.get_line(0).unwrap();
-        let full_width = line.position_of(line.len());
-        assert_eq!(line.get(0..full_width), "Hello!");
-        assert_eq!(
-            view.cursor_position,
-            Position {
-                row: 0,
-                col: end + 1
-            }
-        );
-        assert!(view.needs_redraw);
+        view.config.paste_tabs_to_spaces = true;
+        view.indent.style = IndentStyle::Tabs;
+        view.paste("\tx");
+        assert_eq!(view.current_line(), "\tx");
     }
 
     #[test]
-    fn insert_wide_grapheme_updates_grid_cursor() {
+    fn bracket_pair_only_looks_near_the_cursor() {
         let mut view = View {
-            size: Size { width: 80, height: 24 },
-            ..Default::default()
+            size: Size {
+                width: 10,
+                height: 3,
+            },
+            ..View::default()
         };
-        view.buffer.push("ab");
-        view.cursor_position = Position { row: 0, col: 1 }; // between a and b
-
-        view.insert('👋');
-
-        let line = view.buffer.get_line(0).unwrap();
-        let full_width = line.position_of(line.len());
-        assert_eq!(line.get(0..full_width), "a👋b");
-        // Caret moved one grapheme to the right
-        assert_eq!(view.cursor_position, Position { row: 0, col: 2 });
-        // On grid: a(1) + 👋(2) = 3
-        let grid = view.get_cursor_position();
-        assert_eq!(grid, Position { row: 0, col: 3 });
-        assert!(view.needs_redraw);
-    }
-
-    #[test]
-    fn insert_with_cursor_beyond_end_appends_and_moves_cursor() {
-        let mut view = View::default();
-        view.buffer.push("Hi");
-        view.cursor_position = Position { row: 0, col: 100 };
-
-        view.insert('!');
+        view.buffer.push("{");
+        for _ in 0..50 {
+            view.buffer.push("x");
+        }
+        view.buffer.push("}");
+        assert_eq!(view.bracket_pair(), None);
 
-        let line = view.buffer.get_line(0).unwrap();
-        let full_width = line.position_of(line.len());
-        assert_eq!(line.get(0..full_width), "Hi!");
-        // Cursor should clamp to end of line after moving right
-        assert_eq!(view.cursor_position, Position { row: 0, col: 3 });
-        assert!(view.needs_redraw);
+        view.size.height = 30;
+        assert_eq!(
+            view.bracket_pair(),
+            Some((Position::default(), Position { col: 0, row: 51 }))
+        );
     }
 }
@@ -1,9 +1,158 @@
-use super::line::Line;
+use super::diff::DiffStats;
+use super::line::{GraphemeWidth, Line, TextFragment};
+use super::search::SearchOptions;
+use crate::editor::config::{Indent, IndentStyle};
 use crate::editor::position::Position;
+use std::cell::{Cell, RefCell};
+use std::cmp::{max, min};
+use std::collections::hash_map::DefaultHasher;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::{BufRead, BufReader, Error, ErrorKind, Read, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use unicode_width::UnicodeWidthStr;
 
+/// The text of a file as a list of lines. Lines are *separated* by
+/// newlines rather than terminated by them: a file ending in `\n` has an
+/// empty last line, which gives the cursor a place to append to the
+/// file, and saving writes the newline back.
 #[derive(Default)]
 pub struct Buffer {
     lines: Vec<Line>,
+    // how lines are separated on disk, taken from the first line break
+    line_ending: LineEnding,
+    file_name: Option<String>,
+    canonical_path: Option<PathBuf>,
+    dirty: bool,
+    // `content_hash` of what is on disk, as of the last load or save
+    saved_hash: Option<u64>,
+    // only the start of the file was loaded, so saving would lose the rest
+    truncated: bool,
+    // the file didn't exist when the buffer was opened and hasn't been
+    // saved since
+    new_file: bool,
+    // the width of the widest line, kept up to date while lines only
+    // grow and dropped when one may have shrunk
+    longest_line_width: Cell<Option<usize>>,
+    // the number of graphemes before each line, plus the total; built on
+    // first use and dropped by `resized` and `shrank`, which every edit
+    // goes through
+    grapheme_offsets: RefCell<Option<Vec<usize>>>,
+}
+
+/// The separator written between lines.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    Crlf,
+}
+
+impl LineEnding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::Crlf => "\r\n",
+        }
+    }
+
+    /// The name shown to the user.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Lf => "LF",
+            Self::Crlf => "CRLF",
+        }
+    }
+
+    /// The other line ending.
+    pub fn toggled(self) -> Self {
+        match self {
+            Self::Lf => Self::Crlf,
+            Self::Crlf => Self::Lf,
+        }
+    }
+
+    /// The line ending of the first line break in `text`, if it has one.
+    fn detect(text: &str) -> Option<Self> {
+        let end = text.find('\n')?;
+        if text[..end].ends_with('\r') {
+            Some(Self::Crlf)
+        } else {
+            Some(Self::Lf)
+        }
+    }
+}
+
+/// What `Buffer::delete` removed, by the width of the grapheme.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Deletion {
+    /// There was no grapheme at the position.
+    Nothing,
+    /// A grapheme with no width of its own, such as a zero-width space or
+    /// a lone combining mark.
+    ZeroWidth,
+    /// A grapheme drawn in one column.
+    HalfWidth,
+    /// A grapheme drawn in two columns.
+    FullWidth,
+}
+
+impl Deletion {
+    pub fn is_deleted(self) -> bool {
+        self != Self::Nothing
+    }
+
+    /// The grid columns the removed grapheme took up; a zero-width one is
+    /// drawn as a one-column replacement.
+    pub fn columns(self) -> usize {
+        match self {
+            Self::Nothing => 0,
+            Self::ZeroWidth | Self::HalfWidth => 1,
+            Self::FullWidth => 2,
+        }
+    }
+
+    // caret notation draws a control character in two columns, whatever
+    // width it reports
+    fn of(fragment: &TextFragment) -> Self {
+        match fragment.rendered_width {
+            GraphemeWidth::Full => Self::FullWidth,
+            GraphemeWidth::Half if fragment.grapheme.width() == 0 => Self::ZeroWidth,
+            GraphemeWidth::Half => Self::HalfWidth,
+        }
+    }
+}
+
+/// Whitespace that tends to show up as noise in diffs, counted in
+/// lines.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct WhitespaceReport {
+    /// Lines indented with both tabs and spaces.
+    pub mixed_indent: usize,
+    /// Lines ending in spaces or tabs.
+    pub trailing: usize,
+}
+
+impl WhitespaceReport {
+    pub fn is_clean(self) -> bool {
+        self.mixed_indent == 0 && self.trailing == 0
+    }
+}
+
+impl Display for WhitespaceReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_clean() {
+            write!(f, "Whitespace OK")
+        } else {
+            write!(
+                f,
+                "Whitespace: {} mixed, {} trailing",
+                self.mixed_indent, self.trailing
+            )
+        }
+    }
 }
 
 impl Buffer {
@@ -11,103 +160,1626 @@ impl Buffer {
         self.lines.get(index)
     }
 
+    /// The lines in `rows` with their row indices, clamped to the buffer.
+    pub fn lines_in_range(
+        &self,
+        rows: Range<usize>,
+    ) -> impl ExactSizeIterator<Item = (usize, &Line)> {
+        let end = min(rows.end, self.lines.len());
+        let start = min(rows.start, end);
+        self.lines[start..end]
+            .iter()
+            .enumerate()
+            .map(move |(index, line)| (start + index, line))
+    }
+
     pub fn is_empty(&self) -> bool {
         self.lines.is_empty()
     }
 
-    #[cfg(test)]
-    pub fn push(&mut self, line: &str) {
-        self.lines.push(Line::from(line));
+    #[cfg(test)]
+    pub fn push(&mut self, line: &str) {
+        self.lines.push(Line::from(line));
+        let row = self.lines.len() - 1;
+        self.resized(row..row + 1, 0);
+    }
+
+    pub fn insert(&mut self, at: Position, ch: char) {
+        if at.row == self.lines.len() {
+            // inserting new line
+            self.lines.push(Line::from(&ch.to_string()));
+            self.dirty = true;
+        } else if let Some(line) = self.lines.get_mut(at.row) {
+            line.insert(at.col, ch);
+            self.dirty = true;
+        }
+        self.resized(at.row..at.row + 1, 0);
+    }
+
+    /// Insert `ch` at `at` and return how many graphemes a cursor there
+    /// moves right: 1 normally, 0 when `ch` merged into the grapheme
+    /// before it, as a combining mark does.
+    pub fn insert_returning_advance(&mut self, at: Position, ch: char) -> usize {
+        let old_len = self.line_len(at.row);
+        self.insert(at, ch);
+        self.line_len(at.row).saturating_sub(old_len)
+    }
+
+    /// Put `open` before `from` and `close` before `to`, returning where
+    /// the text between them, delimiters included, now ends. Expects
+    /// `from < to`. A `to` at the start of a later line stands for the end
+    /// of the line before, as a selection ending there leaves that line
+    /// out.
+    pub fn surround(
+        &mut self,
+        from: Position,
+        to: Position,
+        open: char,
+        close: char,
+    ) -> Option<Position> {
+        if to.row >= self.lines.len() || from >= to {
+            return None;
+        }
+        let to = if to.col == 0 && to.row > from.row {
+            let row = to.row - 1;
+            max(
+                Position {
+                    col: self.line_len(row),
+                    row,
+                },
+                from,
+            )
+        } else {
+            to
+        };
+        // the end first, so that `from` stays where it is
+        let mut end = to;
+        end.col += self.insert_returning_advance(to, close);
+        let advance = self.insert_returning_advance(from, open);
+        if from.row == to.row {
+            end.col += advance;
+        }
+        Some(end)
+    }
+
+    pub fn replace(&mut self, at: Position, ch: char) -> bool {
+        let old_width = self.line_width(at.row);
+        let is_replaced = self
+            .lines
+            .get_mut(at.row)
+            .is_some_and(|line| line.replace(at.col, ch));
+        self.dirty |= is_replaced;
+        self.resized(at.row..at.row + 1, old_width);
+        is_replaced
+    }
+
+    pub fn delete(&mut self, at: Position) -> Deletion {
+        let old_width = self.line_width(at.row);
+        let deletion = self
+            .lines
+            .get_mut(at.row)
+            .and_then(|line| line.delete(at.col))
+            .map_or(Deletion::Nothing, |fragment| Deletion::of(&fragment));
+        self.dirty |= deletion.is_deleted();
+        self.resized(at.row..at.row + 1, old_width);
+        deletion
+    }
+
+    /// Delete the text between `from` (inclusive) and `to` (exclusive),
+    /// joining what is left of their lines. Expects `from <= to`.
+    pub fn delete_range(&mut self, from: Position, to: Position) -> bool {
+        if from.row >= self.lines.len() || from >= to {
+            return false;
+        }
+        let to_row = to.row.min(self.lines.len() - 1);
+        let old_width = self.widest_of(from.row..to_row + 1);
+        // split off the tail first, in case both ends are on the same line
+        let tail = self.lines[to_row].split_off(to.col);
+        self.lines[from.row].split_off(from.col);
+        self.lines[from.row].append(&tail);
+        self.lines.drain(from.row + 1..=to_row);
+        self.dirty = true;
+        self.resized(from.row..from.row + 1, old_width);
+        true
+    }
+
+    /// Swap out the whole text of line `row`. Returns false, leaving the
+    /// buffer untouched, if there is no such line.
+    pub fn replace_line(&mut self, row: usize, text: &str) -> bool {
+        let old_width = self.line_width(row);
+        let Some(line) = self.lines.get_mut(row) else {
+            return false;
+        };
+        *line = Line::from(text);
+        self.dirty = true;
+        self.resized(row..row + 1, old_width);
+        true
+    }
+
+    /// Break line `at.row` in two, moving the text from `at.col` on to
+    /// a new line below, and return the start of that line. Past the end
+    /// of the buffer, empty lines are added up to `at.row` first.
+    pub fn split_line(&mut self, at: Position) -> Position {
+        while self.lines.len() <= at.row {
+            self.lines.push(Line::from(""));
+        }
+        let old_width = self.line_width(at.row);
+        let tail = self.lines[at.row].split_off(at.col);
+        self.lines.insert(at.row + 1, tail);
+        self.dirty = true;
+        self.resized(at.row..at.row + 2, old_width);
+        Position {
+            col: 0,
+            row: at.row + 1,
+        }
+    }
+
+    /// Append line `row + 1` to line `row` and return the position where
+    /// they were joined. `None` if there is no line below `row`.
+    pub fn merge_line(&mut self, row: usize) -> Option<Position> {
+        if row + 1 >= self.lines.len() {
+            return None;
+        }
+        let next = self.lines.remove(row + 1);
+        let col = self.lines[row].len();
+        self.lines[row].append(&next);
+        self.dirty = true;
+        self.resized(row..row + 1, 0);
+        Some(Position { col, row })
+    }
+
+    /// Insert `text`, which may span several lines, at `at` and return
+    /// the position just past it.
+    pub fn insert_text(&mut self, at: Position, text: &str) -> Position {
+        if at.row >= self.lines.len() {
+            self.lines.push(Line::from(""));
+        }
+        let mut row = at.row.min(self.lines.len() - 1);
+        let first_row = row;
+        let old_width = self.line_width(row);
+        let tail = self.lines[row].split_off(at.col);
+        let mut pieces = text.split('\n');
+        self.lines[row].append(&Line::from(pieces.next().unwrap_or_default()));
+        let rest: Vec<&str> = pieces.collect();
+        row += self.insert_lines(row + 1, &rest);
+        let end = Position {
+            col: self.lines[row].len(),
+            row,
+        };
+        self.lines[row].append(&tail);
+        self.dirty = true;
+        // a line break in `text` splits the old line, which may narrow it
+        self.resized(first_row..row + 1, old_width);
+        end
+    }
+
+    /// Insert `lines` before row `at`, or append them if `at` is past the
+    /// end, and return how many were inserted.
+    pub fn insert_lines(&mut self, at: usize, lines: &[&str]) -> usize {
+        if lines.is_empty() {
+            return 0;
+        }
+        let at = at.min(self.lines.len());
+        self.lines
+            .splice(at..at, lines.iter().map(|line| Line::from(line)));
+        self.dirty = true;
+        self.resized(at..at + lines.len(), 0);
+        lines.len()
+    }
+
+    /// The text between `from` (inclusive) and `to` (exclusive), with
+    /// lines joined by `\n`.
+    pub fn text_in(&self, from: Position, to: Position) -> String {
+        let to_row = to.row.min(self.lines.len().saturating_sub(1));
+        (from.row..=to_row)
+            .filter_map(|row| {
+                let line = self.lines.get(row)?;
+                let start = if row == from.row { from.col } else { 0 };
+                let end = if row == to.row { to.col } else { line.len() };
+                Some(line.text(start..end))
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// The position just past the last grapheme of the buffer.
+    pub fn end(&self) -> Position {
+        let row = self.lines.len().saturating_sub(1);
+        Position {
+            col: self.line_len(row),
+            row,
+        }
+    }
+
+    /// Collapse blank lines at the end of the buffer into one, keeping
+    /// the line that holds a final newline. Returns whether any were
+    /// removed.
+    pub fn trim_trailing_blank_lines(&mut self) -> bool {
+        if !self.can_trim_trailing_blank_lines() {
+            return false;
+        }
+        let keep = self.len_after_trim();
+        self.lines.truncate(keep);
+        self.dirty = true;
+        self.shrank();
+        true
+    }
+
+    /// Whether `trim_trailing_blank_lines` would remove anything.
+    pub fn can_trim_trailing_blank_lines(&self) -> bool {
+        self.len_after_trim() < self.lines.len()
+    }
+
+    // the content plus at most one blank line after it
+    fn len_after_trim(&self) -> usize {
+        let content_len = self
+            .lines
+            .iter()
+            .rposition(|line| !line.is_blank())
+            .map_or(0, |last| last + 1);
+        min(content_len + 1, self.lines.len())
+    }
+
+    /// Move the lines of `other` to the end of this buffer.
+    pub fn append(&mut self, mut other: Self) {
+        if other.lines.is_empty() {
+            return;
+        }
+        self.lines.append(&mut other.lines);
+        self.dirty = true;
+        self.shrank();
+    }
+
+    /// Insert a copy of the lines in `rows` directly below them and
+    /// return how many lines were added.
+    pub fn duplicate_lines(&mut self, rows: Range<usize>) -> usize {
+        let rows = rows.start..rows.end.min(self.lines.len());
+        if rows.is_empty() {
+            return 0;
+        }
+        let copies = self.lines[rows.clone()].to_vec();
+        let count = copies.len();
+        self.lines.splice(rows.end..rows.end, copies);
+        self.dirty = true;
+        self.resized(rows.end..rows.end + count, 0);
+        count
+    }
+
+    /// Remove lines in `rows` equal to the line before them, like `uniq`,
+    /// and return how many were removed.
+    pub fn dedup_consecutive(&mut self, rows: Range<usize>) -> usize {
+        let rows = rows.start..rows.end.min(self.lines.len());
+        if rows.len() < 2 {
+            return 0;
+        }
+        let mut kept: Vec<Line> = Vec::with_capacity(rows.len());
+        for line in self.lines.drain(rows.clone()) {
+            if kept
+                .last()
+                .is_none_or(|last| last.to_string() != line.to_string())
+            {
+                kept.push(line);
+            }
+        }
+        let removed = rows.len() - kept.len();
+        self.lines.splice(rows.start..rows.start, kept);
+        if removed > 0 {
+            self.dirty = true;
+            self.shrank();
+        }
+        removed
+    }
+
+    /// Whether `dedup_consecutive` would remove anything from `rows`.
+    pub fn has_consecutive_duplicates(&self, rows: Range<usize>) -> bool {
+        let rows = rows.start..rows.end.min(self.lines.len());
+        rows.len() >= 2
+            && self.lines[rows]
+                .windows(2)
+                .any(|pair| pair[0].to_string() == pair[1].to_string())
+    }
+
+    /// Pad the lines in `rows` with spaces so that the first `sep` on each
+    /// of them starts in the same display column. Lines without `sep` are
+    /// left alone. Returns whether anything changed.
+    pub fn align_on(&mut self, rows: Range<usize>, sep: char) -> bool {
+        let (targets, column) = self.align_targets(rows, sep);
+        let mut changed = false;
+        for (row, index, current) in targets {
+            if current == column {
+                continue;
+            }
+            let line = &self.lines[row];
+            let padded = format!(
+                "{}{:padding$}{}",
+                line.text(0..index),
+                "",
+                line.text(index..line.len()),
+                padding = column - current
+            );
+            self.lines[row] = Line::from(&padded);
+            changed = true;
+        }
+        self.dirty |= changed;
+        self.shrank();
+        changed
+    }
+
+    /// Whether `align_on` would change any of the lines in `rows`.
+    pub fn can_align_on(&self, rows: Range<usize>, sep: char) -> bool {
+        let (targets, column) = self.align_targets(rows, sep);
+        targets.iter().any(|&(_, _, current)| current != column)
+    }
+
+    // the row, index and display column of the first `sep` on each line
+    // in `rows` that has one, and the column they all go to
+    fn align_targets(&self, rows: Range<usize>, sep: char) -> (Vec<(usize, usize, usize)>, usize) {
+        let targets: Vec<(usize, usize, usize)> = self
+            .lines_in_range(rows)
+            .filter_map(|(row, line)| {
+                let index = line.find_char_from(sep, 0)?;
+                Some((row, index, line.position_of(index)))
+            })
+            .collect();
+        let column = targets
+            .iter()
+            .map(|&(_, _, column)| column)
+            .max()
+            .unwrap_or(0);
+        (targets, column)
+    }
+
+    /// Rewrite the leading whitespace of the lines in `rows` with spaces
+    /// only, or with as many tabs as fit and spaces for the rest, keeping
+    /// the indentation as wide as it was with tab stops every `width`
+    /// columns. Returns whether any line changed.
+    pub fn retab(&mut self, rows: Range<usize>, to_spaces: bool, width: usize) -> bool {
+        let rows = rows.start..rows.end.min(self.lines.len());
+        let mut changed = false;
+        for row in rows {
+            if let Some(retabbed) = self.retabbed(row, to_spaces, width) {
+                self.lines[row] = Line::from(&retabbed);
+                changed = true;
+            }
+        }
+        self.dirty |= changed;
+        self.shrank();
+        changed
+    }
+
+    /// Whether `retab` would change any of the lines in `rows`.
+    pub fn can_retab(&self, rows: Range<usize>, to_spaces: bool, width: usize) -> bool {
+        let rows = rows.start..rows.end.min(self.lines.len());
+        rows.into_iter()
+            .any(|row| self.retabbed(row, to_spaces, width).is_some())
+    }
+
+    // line `row` with its indentation rewritten, if that changes it
+    fn retabbed(&self, row: usize, to_spaces: bool, width: usize) -> Option<String> {
+        let width = width.max(1);
+        let line = &self.lines[row];
+        let indent_len = Self::indent_of(line);
+        let columns = line.indent_width(width);
+        let indent = if to_spaces {
+            " ".repeat(columns)
+        } else {
+            "\t".repeat(columns / width) + &" ".repeat(columns % width)
+        };
+        let retabbed = indent + &line.text(indent_len..line.len());
+        (retabbed != line.to_string()).then_some(retabbed)
+    }
+
+    pub fn lines(&self) -> &[Line] {
+        &self.lines
+    }
+
+    /// Put `lines` back in place of the lines in `rows`, as on undo. The
+    /// buffer is dirty unless that brings back the saved text.
+    pub fn restore(&mut self, rows: Range<usize>, lines: Vec<Line>) {
+        self.lines.splice(rows, lines);
+        self.dirty = self.saved_hash != Some(self.content_hash());
+        self.shrank();
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub fn has_file_name(&self) -> bool {
+        self.file_name.is_some()
+    }
+
+    pub fn file_name(&self) -> Option<&str> {
+        self.file_name.as_deref()
+    }
+
+    /// Read an unnamed buffer from `reader`, e.g. text piped into stdin.
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<Self, Error> {
+        let mut contents = String::new();
+        reader.read_to_string(&mut contents)?;
+        Ok(Self {
+            lines: Self::parse_lines(&contents),
+            line_ending: LineEnding::detect(&contents).unwrap_or_default(),
+            ..Self::default()
+        })
+    }
+
+    pub fn load(file_name: &str) -> Result<Self, Error> {
+        Self::load_capped(file_name, None)
+    }
+
+    /// An empty buffer for a file that doesn't exist yet; saving creates
+    /// it.
+    pub fn new_file(file_name: &str) -> Self {
+        Self {
+            file_name: Some(file_name.to_string()),
+            new_file: true,
+            ..Self::default()
+        }
+    }
+
+    /// Whether the file has yet to be created by the first save.
+    pub fn is_new_file(&self) -> bool {
+        self.new_file
+    }
+
+    /// Load `file_name` a line at a time, stopping after `max_lines` lines
+    /// if given. A buffer cut short that way is flagged as truncated and
+    /// refuses to be saved.
+    pub fn load_capped(file_name: &str, max_lines: Option<usize>) -> Result<Self, Error> {
+        let mut reader = BufReader::new(File::open(file_name)?);
+        let mut lines = Vec::new();
+        let mut text = String::new();
+        let mut ends_with_newline = false;
+        let mut line_ending = None;
+        while max_lines.is_none_or(|max| lines.len() < max) {
+            text.clear();
+            if reader.read_line(&mut text)? == 0 {
+                break;
+            }
+            line_ending = line_ending.or_else(|| LineEnding::detect(&text));
+            ends_with_newline = text.ends_with('\n');
+            let line = text.strip_suffix('\n').unwrap_or(&text);
+            lines.push(Line::from(line.strip_suffix('\r').unwrap_or(line)));
+        }
+        let truncated = !reader.fill_buf()?.is_empty();
+        // the empty line after a final newline, as in `parse_lines`
+        if ends_with_newline && !truncated {
+            lines.push(Line::from(""));
+        }
+        let mut buffer = Self {
+            lines,
+            line_ending: line_ending.unwrap_or_default(),
+            file_name: Some(file_name.to_string()),
+            canonical_path: std::fs::canonicalize(file_name).ok(),
+            dirty: false,
+            saved_hash: None,
+            truncated,
+            new_file: false,
+            longest_line_width: Cell::new(None),
+            grapheme_offsets: RefCell::new(None),
+        };
+        buffer.saved_hash = Some(buffer.content_hash());
+        Ok(buffer)
+    }
+
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    // unlike `str::lines`, this keeps the empty line after a final newline
+    fn parse_lines(contents: &str) -> Vec<Line> {
+        if contents.is_empty() {
+            return Vec::new();
+        }
+        contents
+            .split('\n')
+            .map(|line| Line::from(line.strip_suffix('\r').unwrap_or(line)))
+            .collect()
+    }
+
+    /// The path of the loaded file with all symlinks resolved, as
+    /// recorded at load time.
+    pub fn canonical_path(&self) -> Option<&Path> {
+        self.canonical_path.as_deref()
+    }
+
+    /// Whether the file we loaded has disappeared since, e.g. because
+    /// the target of a symlink was removed. Saving recreates it.
+    pub fn is_save_target_missing(&self) -> bool {
+        self.canonical_path().is_some_and(|path| !path.exists())
+    }
+
+    pub fn save(&mut self) -> Result<(), Error> {
+        self.check_saveable()?;
+        let path = self.disk_path()?;
+        let mut file = File::create(path)?;
+        for (index, line) in self.lines.iter().enumerate() {
+            if index > 0 {
+                write!(file, "{}", self.line_ending.as_str())?;
+            }
+            write!(file, "{line}")?;
+        }
+        self.dirty = false;
+        self.new_file = false;
+        self.saved_hash = Some(self.content_hash());
+        Ok(())
+    }
+
+    /// Fail the way `save` would before writing anything: without a file
+    /// name, or with only part of the file loaded.
+    pub fn check_saveable(&self) -> Result<(), Error> {
+        if self.truncated {
+            return Err(Self::truncated_error());
+        }
+        self.disk_path().map(|_| ())
+    }
+
+    fn truncated_error() -> Error {
+        Error::other("only part of the file was loaded")
+    }
+
+    /// Compare the buffer with the file on disk, line by line. A buffer
+    /// holding only the start of its file is refused, since the rest
+    /// would all count as removed.
+    pub fn diff_against_disk(&self) -> Result<DiffStats, Error> {
+        if self.truncated {
+            return Err(Self::truncated_error());
+        }
+        let on_disk = Self::parse_lines(&std::fs::read_to_string(self.disk_path()?)?);
+        let old: Vec<String> = on_disk.iter().map(ToString::to_string).collect();
+        let new: Vec<String> = self.lines.iter().map(ToString::to_string).collect();
+        Ok(DiffStats::between(&old, &new))
+    }
+
+    // the file we load from and save to; the resolved target, so that a
+    // symlink stays a symlink
+    fn disk_path(&self) -> Result<&Path, Error> {
+        self.canonical_path()
+            .or_else(|| self.file_name.as_deref().map(Path::new))
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, "no file name"))
+    }
+
+    pub fn line_ending(&self) -> LineEnding {
+        self.line_ending
+    }
+
+    /// Switch the line ending that saving writes, returning whether it
+    /// changed. The lines themselves are untouched.
+    pub fn set_line_ending(&mut self, line_ending: LineEnding) -> bool {
+        if self.line_ending == line_ending {
+            return false;
+        }
+        self.line_ending = line_ending;
+        self.dirty = true;
+        true
+    }
+
+    /// A fingerprint of the text as saved, equal for equal contents and
+    /// line endings.
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.line_ending.as_str().hash(&mut hasher);
+        self.lines.len().hash(&mut hasher);
+        for line in &self.lines {
+            line.hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
+    /// Clear the dirty flag if the edits since the last save have been
+    /// reverted by hand, and return whether the buffer is still dirty.
+    pub fn recheck_dirty(&mut self) -> bool {
+        if self.dirty && self.saved_hash == Some(self.content_hash()) {
+            self.dirty = false;
+        }
+        self.dirty
+    }
+
+    /// The size of the text in bytes as it would be saved, line endings
+    /// included.
+    pub fn byte_len(&self) -> usize {
+        let text: usize = self.lines.iter().map(Line::byte_len).sum();
+        let breaks = self.lines.len().saturating_sub(1);
+        text + breaks * self.line_ending.as_str().len()
+    }
+
+    /// The number of graphemes, not counting line endings.
+    pub fn grapheme_count(&self) -> usize {
+        self.lines.iter().map(Line::len).sum()
+    }
+
+    pub fn num_lines(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// The graphemes (not counting line endings) and lines between `from`
+    /// and `to`, in either order.
+    pub fn selection_stats(&self, from: Position, to: Position) -> (usize, usize) {
+        let (start, end) = Position::normalize(from, to);
+        let graphemes = self
+            .grapheme_offset(end)
+            .saturating_sub(self.grapheme_offset(start));
+        (graphemes, end.row - start.row + 1)
+    }
+
+    // the number of graphemes before `at`, clamped to the text
+    fn grapheme_offset(&self, at: Position) -> usize {
+        if self.grapheme_offsets.borrow().is_none() {
+            let offsets = std::iter::once(0)
+                .chain(self.lines.iter().scan(0, |offset, line| {
+                    *offset += line.len();
+                    Some(*offset)
+                }))
+                .collect();
+            *self.grapheme_offsets.borrow_mut() = Some(offsets);
+        }
+        let offsets = self.grapheme_offsets.borrow();
+        let offsets = offsets.as_ref().expect("offsets were just built");
+        match self.lines.get(at.row) {
+            Some(line) => offsets[at.row] + min(at.col, line.len()),
+            None => offsets[self.lines.len()],
+        }
+    }
+
+    /// The number of graphemes in a line, i.e. the number of cursor
+    /// columns it has besides the one past its end.
+    pub fn line_len(&self, at: usize) -> usize {
+        let line = self.lines.get(at);
+        line.map_or(0, Line::len)
+    }
+
+    /// Whether a line is empty or whitespace only. Rows past the end of
+    /// the buffer are not considered blank.
+    pub fn is_line_blank(&self, at: usize) -> bool {
+        self.lines.get(at).is_some_and(Line::is_blank)
+    }
+
+    /// The display width of the widest line. Only the first call after
+    /// an edit that narrowed the widest line walks the whole buffer.
+    pub fn longest_line_width(&self) -> usize {
+        if let Some(width) = self.longest_line_width.get() {
+            return width;
+        }
+        let width = self.lines.iter().map(Line::width).max().unwrap_or(0);
+        self.longest_line_width.set(Some(width));
+        width
+    }
+
+    // the lines now at `rows` came from an edit of lines whose widest was
+    // `old_width` wide; only if that was the widest line and the edit
+    // narrowed it is the maximum unknown
+    fn resized(&self, rows: Range<usize>, old_width: usize) {
+        self.grapheme_offsets.take();
+        let Some(max) = self.longest_line_width.get() else {
+            return;
+        };
+        let new_width = self.widest_of(rows);
+        if new_width >= max {
+            self.longest_line_width.set(Some(new_width));
+        } else if old_width >= max {
+            self.shrank();
+        }
+    }
+
+    fn widest_of(&self, rows: Range<usize>) -> usize {
+        self.lines_in_range(rows)
+            .map(|(_, line)| line.width())
+            .max()
+            .unwrap_or(0)
+    }
+
+    // lines may have got narrower or gone, so the maximum is unknown
+    fn shrank(&self) {
+        self.grapheme_offsets.take();
+        self.longest_line_width.set(None);
+    }
+
+    /// The display width of a line in grid cells, as opposed to
+    /// `line_len`, which counts graphemes.
+    pub fn line_width(&self, at: usize) -> usize {
+        self.lines.get(at).map_or(0, Line::width)
+    }
+
+    pub fn find_char_from(&self, at: Position, ch: char) -> Option<Position> {
+        let line = self.lines.get(at.row)?;
+        line.find_char_from(ch, at.col)
+            .map(|col| Position { col, row: at.row })
+    }
+
+    /// Guess the indentation from the leading whitespace of the lines:
+    /// tabs if more lines start with a tab than with a space, otherwise
+    /// spaces as wide as the shallowest indented line. `None` if no line
+    /// is indented.
+    pub fn detect_indent(&self, tab_width: usize) -> Option<Indent> {
+        let mut tab_lines = 0;
+        let mut space_lines = 0;
+        let mut min_spaces = usize::MAX;
+        for line in self.lines.iter().filter(|line| !line.is_blank()) {
+            match line.grapheme_at(0) {
+                Some("\t") => tab_lines += 1,
+                Some(" ") => {
+                    let spaces = (0..line.len())
+                        .take_while(|&at| line.grapheme_at(at) == Some(" "))
+                        .count();
+                    space_lines += 1;
+                    min_spaces = min(min_spaces, spaces);
+                }
+                _ => {}
+            }
+        }
+        if tab_lines > space_lines {
+            Some(Indent {
+                style: IndentStyle::Tabs,
+                width: tab_width,
+            })
+        } else if space_lines > 0 {
+            Some(Indent {
+                style: IndentStyle::Spaces,
+                width: min_spaces,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Count the lines with mixed indentation or trailing whitespace.
+    /// This walks the whole buffer, so it is meant for saves and explicit
+    /// checks rather than every keystroke.
+    pub fn whitespace_issues(&self) -> WhitespaceReport {
+        let mut report = WhitespaceReport::default();
+        for line in &self.lines {
+            let indent = line.text(0..Self::indent_of(line));
+            if indent.contains('\t') && indent.contains(' ') {
+                report.mixed_indent += 1;
+            }
+            if line.trailing_whitespace().is_some() {
+                report.trailing += 1;
+            }
+        }
+        report
+    }
+
+    /// The rows right below `row` that are indented deeper than it,
+    /// including blank lines between them but not after them. A tab
+    /// reaches the next multiple of `tab_width`.
+    pub fn indented_block_below(&self, row: usize, tab_width: usize) -> Range<usize> {
+        let Some(line) = self.lines.get(row) else {
+            return row..row;
+        };
+        let indent = line.indent_width(tab_width);
+        let mut end = row + 1;
+        for (at, line) in self.lines.iter().enumerate().skip(row + 1) {
+            if line.is_blank() {
+                continue;
+            }
+            if line.indent_width(tab_width) <= indent {
+                break;
+            }
+            end = at + 1;
+        }
+        row + 1..end
+    }
+
+    // leading whitespace in graphemes
+    fn indent_of(line: &Line) -> usize {
+        (0..line.len())
+            .take_while(|&at| line.grapheme_at(at).is_some_and(|g| g.trim().is_empty()))
+            .count()
+    }
+
+    /// The grapheme range on line `at.row` of the word, whitespace or
+    /// symbol run under `at`.
+    pub fn word_at(&self, at: Position) -> Range<usize> {
+        self.lines
+            .get(at.row)
+            .map_or(at.col..at.col, |line| line.word_range_at(at.col))
+    }
+
+    /// The start of the next word or symbol run after `from`, going on
+    /// to the first one on a later line; the end of the buffer if there
+    /// is none.
+    pub fn next_word(&self, from: Position) -> Position {
+        let Position { row, col } = from;
+        if let Some(col) = self
+            .lines
+            .get(row)
+            .and_then(|line| line.next_word_start(col))
+        {
+            return Position { col, row };
+        }
+        self.lines
+            .iter()
+            .enumerate()
+            .skip(row + 1)
+            .find_map(|(row, line)| {
+                Some(Position {
+                    row,
+                    col: line.first_word_start()?,
+                })
+            })
+            .unwrap_or_else(|| self.end())
+    }
+
+    /// The start of the word or symbol run before `from`, going back to
+    /// the last one on an earlier line; the start of the buffer if there
+    /// is none.
+    pub fn prev_word(&self, from: Position) -> Position {
+        let Position { row, col } = from;
+        if let Some(col) = self
+            .lines
+            .get(row)
+            .and_then(|line| line.prev_word_start(col))
+        {
+            return Position { col, row };
+        }
+        self.lines[..row.min(self.lines.len())]
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(row, line)| {
+                let col = line.prev_word_start(line.len())?;
+                Some(Position { col, row })
+            })
+            .unwrap_or_default()
+    }
+
+    /// The likely definition of `name`: the first place it follows a
+    /// keyword that introduces a name, such as `fn foo` or `let mut foo`.
+    /// The keywords depend on the file extension.
+    pub fn find_definition(&self, name: &str) -> Option<Position> {
+        let keywords = self.definition_keywords();
+        self.lines.iter().enumerate().find_map(|(row, line)| {
+            let tokens = Self::tokens_of(line);
+            tokens.iter().enumerate().find_map(|(index, (_, token))| {
+                if !keywords.contains(&token.as_str()) {
+                    return None;
+                }
+                tokens[index + 1..]
+                    .iter()
+                    .find(|(_, next)| next != "mut")
+                    .filter(|(_, next)| next == name)
+                    .map(|(range, _)| Position {
+                        col: range.start,
+                        row,
+                    })
+            })
+        })
+    }
+
+    fn definition_keywords(&self) -> &'static [&'static str] {
+        let extension = self
+            .file_name
+            .as_deref()
+            .and_then(|name| Path::new(name).extension())
+            .and_then(|extension| extension.to_str());
+        match extension {
+            Some("rs") => &[
+                "fn",
+                "let",
+                "struct",
+                "enum",
+                "trait",
+                "type",
+                "const",
+                "static",
+                "mod",
+                "macro_rules",
+            ],
+            Some("py") => &["def", "class"],
+            Some("js" | "ts") => &["function", "class", "let", "const", "var"],
+            Some("c" | "h") => &["struct", "enum", "union", "typedef", "define"],
+            _ => &[
+                "fn", "let", "struct", "enum", "trait", "type", "const", "def", "class",
+                "function", "var",
+            ],
+        }
+    }
+
+    // the words and symbol runs on `line`, skipping whitespace
+    fn tokens_of(line: &Line) -> Vec<(Range<usize>, String)> {
+        let mut tokens = Vec::new();
+        let mut at = 0;
+        while at < line.len() {
+            let range = line.word_range_at(at);
+            at = range.end;
+            let text = line.text(range.clone());
+            if !text.trim().is_empty() {
+                tokens.push((range, text));
+            }
+        }
+        tokens
+    }
+
+    /// Find the bracket matching the one at `at`, skipping over nested
+    /// pairs of the same kind and looking no further than the rows in
+    /// `rows`. Returns `None` if `at` is not on a bracket or no match is
+    /// found there.
+    pub fn matching_bracket(&self, at: Position, rows: Range<usize>) -> Option<Position> {
+        let bracket = self.lines.get(at.row)?.grapheme_at(at.col)?;
+        // `entering` nests one level deeper in the direction we scan
+        let (entering, leaving, forward) = match bracket {
+            "(" => ("(", ")", true),
+            "[" => ("[", "]", true),
+            "{" => ("{", "}", true),
+            ")" => (")", "(", false),
+            "]" => ("]", "[", false),
+            "}" => ("}", "{", false),
+            _ => return None,
+        };
+        let mut depth = 0_usize;
+        for position in self
+            .positions_from(at, forward)
+            .take_while(|position| rows.contains(&position.row))
+        {
+            let grapheme = self.lines[position.row].grapheme_at(position.col);
+            if grapheme == Some(entering) {
+                depth += 1;
+            } else if grapheme == Some(leaving) {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(position);
+                }
+            }
+        }
+        None
+    }
+
+    // every grapheme position from `at` (inclusive) to the start or end
+    // of the buffer
+    fn positions_from(
+        &self,
+        at: Position,
+        forward: bool,
+    ) -> Box<dyn Iterator<Item = Position> + '_> {
+        let positions_on = move |row: usize| {
+            let len = self.line_len(row);
+            (0..len).map(move |col| Position { col, row })
+        };
+        if forward {
+            Box::new(
+                (at.row..self.lines.len())
+                    .flat_map(positions_on)
+                    .skip_while(move |position| position.row == at.row && position.col < at.col),
+            )
+        } else {
+            Box::new(
+                (0..=at.row)
+                    .rev()
+                    .flat_map(move |row| positions_on(row).rev())
+                    .skip_while(move |position| position.row == at.row && position.col > at.col),
+            )
+        }
+    }
+
+    /// Find the next match of `query` at or after `from`, wrapping
+    /// around to the start of the buffer.
+    pub fn find(&self, query: &str, from: Position, options: SearchOptions) -> Option<Position> {
+        if self.lines.is_empty() {
+            return None;
+        }
+        let num_lines = self.lines.len();
+        // the extra iteration revisits the first row from its start
+        for offset in 0..=num_lines {
+            let row = (from.row + offset) % num_lines;
+            let start = if offset == 0 { from.col } else { 0 };
+            if let Some(col) = self.lines[row].find(query, start, options) {
+                return Some(Position { col, row });
+            }
+        }
+        None
+    }
+
+    /// Find the last match of `query` before `before`, wrapping around
+    /// to the end of the buffer.
+    pub fn rfind(&self, query: &str, before: Position, options: SearchOptions) -> Option<Position> {
+        if self.lines.is_empty() {
+            return None;
+        }
+        let num_lines = self.lines.len();
+        // the extra iteration revisits the first row from its end
+        for offset in 0..=num_lines {
+            let row = (before.row + num_lines - offset) % num_lines;
+            let limit = if offset == 0 { before.col } else { usize::MAX };
+            let line = &self.lines[row];
+            let mut last = None;
+            let mut from = 0;
+            while let Some(col) = line.find(query, from, options) {
+                if col >= limit {
+                    break;
+                }
+                last = Some(col);
+                from = col + 1;
+            }
+            if let Some(col) = last {
+                return Some(Position { col, row });
+            }
+        }
+        None
+    }
+
+    /// Every match of `query` in document order, including overlapping
+    /// ones. An empty query matches nothing.
+    pub fn search_iter<'a>(
+        &'a self,
+        query: &'a str,
+        options: SearchOptions,
+    ) -> impl Iterator<Item = Position> + 'a {
+        self.lines.iter().enumerate().flat_map(move |(row, line)| {
+            let mut from = 0;
+            std::iter::from_fn(move || {
+                let col = line.find(query, from, options)?;
+                from = col + 1;
+                Some(Position { col, row })
+            })
+        })
+    }
+
+    /// Convert a grapheme-based location (line and column) into a
+    /// position on the rendered grid, where each grapheme may span
+    /// multiple cells.
+    pub fn grid_position_of(&self, location: Position) -> Position {
+        let Position { row, col } = location;
+        let col = self.lines.get(row).map_or(0, |line| line.position_of(col));
+        Position { col, row }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{File, remove_file};
+    use std::io::Write;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn unique_file_path() -> std::path::PathBuf {
+        let mut path = std::env::temp_dir();
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        path.push(format!("hecto_test_buffer_load_{nanos}"));
+        path
+    }
+
+    #[test]
+    fn load_returns_buffer_with_file_contents() -> std::io::Result<()> {
+        let path = unique_file_path();
+        let mut file = File::create(&path)?;
+        write!(file, "first\nsecond")?;
+        drop(file);
+
+        let buffer = Buffer::load(path.to_str().unwrap())?;
+        assert_eq!(buffer.num_lines(), 2);
+        assert_eq!(
+            buffer.get_line(0).map(|line| line.get(0..5)),
+            Some("first".to_string())
+        );
+        assert_eq!(
+            buffer.get_line(1).map(|line| line.get(0..6)),
+            Some("second".to_string())
+        );
+
+        remove_file(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn capped_load_stops_at_limit_and_flags_truncation() -> std::io::Result<()> {
+        let path = unique_file_path();
+        let mut file = File::create(&path)?;
+        for number in 0..1000 {
+            writeln!(file, "line {number}")?;
+        }
+        drop(file);
+
+        let mut buffer = Buffer::load_capped(path.to_str().unwrap(), Some(100))?;
+        assert_eq!(buffer.num_lines(), 100);
+        assert_eq!(buffer.get_line(99).unwrap().to_string(), "line 99");
+        assert!(buffer.is_truncated());
+        assert!(buffer.save().is_err());
+        assert!(buffer.diff_against_disk().is_err());
+
+        let buffer = Buffer::load_capped(path.to_str().unwrap(), Some(1000))?;
+        assert_eq!(buffer.num_lines(), 1001);
+        assert!(!buffer.is_truncated());
+
+        remove_file(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn diff_against_disk_counts_unsaved_lines() -> std::io::Result<()> {
+        let path = unique_file_path();
+        std::fs::write(&path, "one\ntwo\nthree")?;
+        let mut buffer = Buffer::load(path.to_str().unwrap())?;
+        assert!(buffer.diff_against_disk()?.is_empty());
+
+        buffer.replace_line(2, "THREE");
+        buffer.push("four");
+        let stats = buffer.diff_against_disk()?;
+        assert_eq!((stats.changed, stats.added, stats.removed), (1, 1, 0));
+
+        remove_file(path)?;
+        assert!(Buffer::default().diff_against_disk().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn load_returns_error_for_missing_file() {
+        let path = unique_file_path();
+        let result = Buffer::load(path.to_str().unwrap());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn trailing_newline_keeps_empty_last_line_and_round_trips() -> std::io::Result<()> {
+        let path = unique_file_path();
+        std::fs::write(&path, "a\n")?;
+
+        let mut buffer = Buffer::load(path.to_str().unwrap())?;
+        assert_eq!(contents(&buffer), vec!["a", ""]);
+        buffer.save()?;
+        assert_eq!(std::fs::read_to_string(&path)?, "a\n");
+
+        std::fs::write(&path, "a")?;
+        let mut buffer = Buffer::load(path.to_str().unwrap())?;
+        assert_eq!(contents(&buffer), vec!["a"]);
+        buffer.save()?;
+        assert_eq!(std::fs::read_to_string(&path)?, "a");
+
+        remove_file(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn matching_bracket_skips_nested_pairs() {
+        let mut buffer = Buffer::default();
+        buffer.push("a(b[c](d)");
+        buffer.push(")e]");
+        assert_eq!(
+            buffer.matching_bracket(Position { row: 0, col: 1 }, 0..2),
+            Some(Position { row: 1, col: 0 })
+        );
+        assert_eq!(
+            buffer.matching_bracket(Position { row: 1, col: 0 }, 0..2),
+            Some(Position { row: 0, col: 1 })
+        );
+        assert_eq!(
+            buffer.matching_bracket(Position { row: 0, col: 3 }, 0..2),
+            Some(Position { row: 0, col: 5 })
+        );
+        // unbalanced, and not a bracket
+        assert_eq!(
+            buffer.matching_bracket(Position { row: 1, col: 2 }, 0..2),
+            None
+        );
+        assert_eq!(
+            buffer.matching_bracket(Position { row: 0, col: 0 }, 0..2),
+            None
+        );
+        // the match is on a row outside the window
+        assert_eq!(
+            buffer.matching_bracket(Position { row: 0, col: 1 }, 0..1),
+            None
+        );
+    }
+
+    #[test]
+    fn find_definition_skips_uses_of_the_name() {
+        let mut buffer = Buffer::default();
+        buffer.push("let total = count(3);");
+        buffer.push("fn count(n: u32) {");
+        buffer.push("    let mut total = n;");
+        assert_eq!(
+            buffer.find_definition("count"),
+            Some(Position { row: 1, col: 3 })
+        );
+        assert_eq!(
+            buffer.find_definition("total"),
+            Some(Position { row: 0, col: 4 })
+        );
+        assert_eq!(buffer.find_definition("n"), None);
+    }
+
+    #[test]
+    fn replace_line_swaps_middle_line() {
+        let mut buffer = Buffer::default();
+        buffer.push("one");
+        buffer.push("two");
+        buffer.push("three");
+        assert!(buffer.replace_line(1, "zwei 👋"));
+        assert_eq!(contents(&buffer), vec!["one", "zwei 👋", "three"]);
+        assert_eq!(buffer.line_len(1), 6);
+        assert!(buffer.is_dirty());
+    }
+
+    #[test]
+    fn replace_line_out_of_range_is_noop() {
+        let mut buffer = Buffer::default();
+        buffer.push("one");
+        assert!(!buffer.replace_line(1, "two"));
+        assert_eq!(contents(&buffer), vec!["one"]);
+        assert!(!buffer.is_dirty());
+    }
+
+    #[test]
+    fn insert_text_spanning_lines_keeps_tail() {
+        let mut buffer = Buffer::default();
+        buffer.push("Hello world");
+        let end = buffer.insert_text(Position { row: 0, col: 6 }, "big\nwide ");
+        assert_eq!(contents(&buffer), vec!["Hello big", "wide world"]);
+        assert_eq!(end, Position { row: 1, col: 5 });
+    }
+
+    #[test]
+    fn text_in_joins_lines() {
+        let mut buffer = Buffer::default();
+        buffer.push("one");
+        buffer.push("two");
+        buffer.push("three");
+        let text = buffer.text_in(Position { row: 0, col: 1 }, Position { row: 2, col: 2 });
+        assert_eq!(text, "ne\ntwo\nth");
+        assert_eq!(
+            buffer.text_in(Position::default(), buffer.end()),
+            "one\ntwo\nthree"
+        );
+    }
+
+    #[test]
+    fn trailing_blank_lines_collapse_to_one() {
+        let mut buffer = Buffer::default();
+        buffer.push("text");
+        buffer.push("");
+        buffer.push("  ");
+        buffer.push("");
+        assert!(buffer.trim_trailing_blank_lines());
+        assert_eq!(contents(&buffer), vec!["text", ""]);
+        assert!(!buffer.trim_trailing_blank_lines());
+    }
+
+    #[test]
+    fn only_blank_lines_keep_one() {
+        let mut buffer = Buffer::default();
+        buffer.push("");
+        buffer.push("");
+        buffer.push("");
+        assert!(buffer.trim_trailing_blank_lines());
+        assert_eq!(contents(&buffer), vec![""]);
+
+        let mut buffer = Buffer::default();
+        buffer.push("");
+        assert!(!buffer.trim_trailing_blank_lines());
+        assert_eq!(contents(&buffer), vec![""]);
+    }
+
+    #[test]
+    fn detect_indent_from_leading_whitespace() {
+        let mut buffer = Buffer::default();
+        buffer.push("fn main() {");
+        buffer.push("\tif x {");
+        buffer.push("\t\ty();");
+        buffer.push("\t}");
+        buffer.push("}");
+        assert_eq!(
+            buffer.detect_indent(8),
+            Some(Indent {
+                style: IndentStyle::Tabs,
+                width: 8
+            })
+        );
+
+        let mut buffer = Buffer::default();
+        buffer.push("a:");
+        buffer.push("  b:");
+        buffer.push("    c: 1");
+        buffer.push("   ");
+        assert_eq!(
+            buffer.detect_indent(8),
+            Some(Indent {
+                style: IndentStyle::Spaces,
+                width: 2
+            })
+        );
+
+        let mut buffer = Buffer::default();
+        buffer.push("flat");
+        assert_eq!(buffer.detect_indent(8), None);
+    }
+
+    #[test]
+    fn indented_block_below_stops_at_shallower_line() {
+        let mut buffer = Buffer::default();
+        buffer.push("fn a() {");
+        buffer.push("    x();");
+        buffer.push("");
+        buffer.push("    y();");
+        buffer.push("");
+        buffer.push("}");
+        assert_eq!(buffer.indented_block_below(0, 4), 1..4);
+        assert_eq!(buffer.indented_block_below(1, 4), 2..2);
+        assert_eq!(buffer.indented_block_below(9, 4), 9..9);
+    }
+
+    #[test]
+    fn whitespace_issues_counts_mixed_indent_and_trailing_space() {
+        let mut buffer = Buffer::default();
+        buffer.push("fn a() {");
+        buffer.push("\t  x();");
+        buffer.push("    y(); ");
+        buffer.push("\tz();");
+        buffer.push("}");
+        let report = buffer.whitespace_issues();
+        assert_eq!(
+            report,
+            WhitespaceReport {
+                mixed_indent: 1,
+                trailing: 1,
+            }
+        );
+        assert_eq!(report.to_string(), "Whitespace: 1 mixed, 1 trailing");
+    }
+
+    #[test]
+    fn longest_line_width_follows_inserts() {
+        let mut buffer = Buffer::default();
+        buffer.push("short");
+        buffer.push("the longest");
+        assert_eq!(buffer.longest_line_width(), 11);
+        buffer.insert(Position { col: 11, row: 1 }, '!');
+        assert_eq!(buffer.longest_line_width(), 12);
+        // a wide grapheme takes two columns
+        buffer.insert(Position { col: 0, row: 0 }, 'Ｂ');
+        assert_eq!(buffer.longest_line_width(), 12);
+        buffer.insert_text(Position { col: 0, row: 0 }, "wider still: ");
+        assert_eq!(buffer.longest_line_width(), 20);
+    }
+
+    #[test]
+    fn longest_line_width_recomputed_after_delete() {
+        let mut buffer = Buffer::default();
+        buffer.push("a bit longer");
+        buffer.push("short");
+        assert_eq!(buffer.longest_line_width(), 12);
+        buffer.delete_range(Position { col: 0, row: 0 }, Position { col: 6, row: 0 });
+        assert_eq!(buffer.longest_line_width(), 6);
+        buffer.delete(Position { col: 0, row: 0 });
+        assert_eq!(buffer.longest_line_width(), 5);
+        buffer.restore(0..2, Vec::new());
+        assert_eq!(buffer.longest_line_width(), 0);
+    }
+
+    #[test]
+    fn whitespace_issues_on_clean_buffer() {
+        let mut buffer = Buffer::default();
+        buffer.push("\tx();");
+        buffer.push("  y();");
+        assert!(buffer.whitespace_issues().is_clean());
+    }
+
+    #[test]
+    fn indented_block_compares_tabs_by_width() {
+        let mut buffer = Buffer::default();
+        buffer.push("    if x {");
+        buffer.push("\ty();");
+        buffer.push("\t\tz();");
+        // a tab counts as its width, not as one grapheme
+        assert_eq!(buffer.indented_block_below(0, 4), 1..1);
+        assert_eq!(buffer.indented_block_below(0, 8), 1..3);
+    }
+
+    #[test]
+    fn split_line_at_various_columns() {
+        let mut buffer = Buffer::default();
+        buffer.push("Hello world");
+        let at = buffer.split_line(Position { row: 0, col: 5 });
+        assert_eq!(contents(&buffer), vec!["Hello", " world"]);
+        assert_eq!(at, Position { row: 1, col: 0 });
+        assert!(buffer.is_dirty());
+
+        buffer.split_line(Position { row: 0, col: 0 });
+        assert_eq!(contents(&buffer), vec!["", "Hello", " world"]);
+
+        buffer.split_line(Position { row: 2, col: 6 });
+        assert_eq!(contents(&buffer), vec!["", "Hello", " world", ""]);
     }
 
-    pub fn insert(&mut self, at: Position, ch: char) {
-        if at.row == self.lines.len() {
-            // inserting new line
-            self.lines.push(Line::from(&ch.to_string()));
-        } else if let Some(line) = self.lines.get_mut(at.row) {
-            line.insert(at.col, ch);
-        }
+    #[test]
+    fn split_line_past_end_adds_lines() {
+        let mut buffer = Buffer::default();
+        let at = buffer.split_line(Position { row: 0, col: 0 });
+        assert_eq!(contents(&buffer), vec!["", ""]);
+        assert_eq!(at, Position { row: 1, col: 0 });
     }
 
-    pub fn delete(&mut self, at: Position) -> bool {
-        if let Some(line) = self.lines.get_mut(at.row) {
-            return line.delete(at.col);
-        }
-        false
-    }
+    #[test]
+    fn merge_line_joins_next_line() {
+        let mut buffer = Buffer::default();
+        buffer.push("Hello");
+        buffer.push(" world");
+        buffer.push("");
+        assert_eq!(buffer.merge_line(0), Some(Position { row: 0, col: 5 }));
+        assert_eq!(contents(&buffer), vec!["Hello world", ""]);
 
-    pub fn load(file_name: &str) -> Result<Self, std::io::Error> {
-        let contents = std::fs::read_to_string(file_name)?;
-        let mut lines = Vec::new();
-        for line in contents.lines() {
-            lines.push(Line::from(line));
-        }
-        Ok(Self { lines })
-    }
+        // merging an empty line leaves the line as it is
+        assert_eq!(buffer.merge_line(0), Some(Position { row: 0, col: 11 }));
+        assert_eq!(contents(&buffer), vec!["Hello world"]);
 
-    pub fn num_lines(&self) -> usize {
-        self.lines.len()
+        assert_eq!(buffer.merge_line(0), None);
     }
 
-    pub fn line_len(&self, at: usize) -> usize {
-        let line = self.lines.get(at);
-        line.map_or(0, Line::len)
+    #[test]
+    fn from_reader_parses_piped_lines_into_unnamed_buffer() -> std::io::Result<()> {
+        let input: &[u8] = b"first\r\nsecond\n\nlast\n";
+        let buffer = Buffer::from_reader(input)?;
+        assert_eq!(contents(&buffer), vec!["first", "second", "", "last", ""]);
+        assert!(!buffer.has_file_name());
+        assert!(!buffer.is_dirty());
+        Ok(())
     }
 
-    /// Convert a grapheme-based location (line and column) into a
-    /// position on the rendered grid, where each grapheme may span
-    /// multiple cells.
-    pub fn grid_position_of(&self, location: Position) -> Position {
-        let Position { row, col } = location;
-        let col = self.lines.get(row).map_or(0, |line| line.position_of(col));
-        Position { col, row }
+    #[test]
+    fn byte_len_counts_multibyte_graphemes_and_line_endings() {
+        let mut buffer = Buffer::default();
+        buffer.push("añ");
+        buffer.push("👋");
+        assert_eq!(buffer.grapheme_count(), 3);
+        assert_eq!(buffer.byte_len(), 3 + 1 + 4);
+
+        buffer.line_ending = LineEnding::Crlf;
+        assert_eq!(buffer.byte_len(), 3 + 2 + 4);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::fs::{File, remove_file};
-    use std::io::Write;
-    use std::time::{SystemTime, UNIX_EPOCH};
+    #[test]
+    fn crlf_files_are_saved_with_crlf() -> std::io::Result<()> {
+        let path = unique_file_path();
+        std::fs::write(&path, "one\r\ntwo\r\n")?;
+        let mut buffer = Buffer::load(path.to_str().unwrap())?;
+        assert_eq!(contents(&buffer), vec!["one", "two", ""]);
+        assert_eq!(buffer.byte_len(), 10);
 
-    fn unique_file_path() -> std::path::PathBuf {
-        let mut path = std::env::temp_dir();
-        let nanos = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_nanos();
-        path.push(format!("hecto_test_buffer_load_{nanos}"));
-        path
+        buffer.save()?;
+        assert_eq!(std::fs::read_to_string(&path)?, "one\r\ntwo\r\n");
+        remove_file(path)?;
+        Ok(())
     }
 
     #[test]
-    fn load_returns_buffer_with_file_contents() -> std::io::Result<()> {
+    fn converted_line_endings_are_used_by_save() -> std::io::Result<()> {
         let path = unique_file_path();
-        let mut file = File::create(&path)?;
-        write!(file, "first\nsecond")?;
-        drop(file);
+        std::fs::write(&path, "one\ntwo\n")?;
+        let mut buffer = Buffer::load(path.to_str().unwrap())?;
 
-        let buffer = Buffer::load(path.to_str().unwrap())?;
-        assert_eq!(buffer.num_lines(), 2);
+        assert!(buffer.set_line_ending(LineEnding::Crlf));
+        assert!(buffer.is_dirty());
+        buffer.save()?;
+        assert_eq!(std::fs::read_to_string(&path)?, "one\r\ntwo\r\n");
+
+        assert!(!buffer.set_line_ending(LineEnding::Crlf));
+        assert!(buffer.set_line_ending(LineEnding::Lf));
+        buffer.save()?;
+        assert_eq!(std::fs::read_to_string(&path)?, "one\ntwo\n");
+        remove_file(path)?;
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn save_through_symlink_writes_target() -> std::io::Result<()> {
+        let target = unique_file_path();
+        let mut link = target.clone();
+        link.set_extension("link");
+        std::fs::write(&target, "Hello\n")?;
+        std::os::unix::fs::symlink(&target, &link)?;
+
+        let mut buffer = Buffer::load(link.to_str().unwrap())?;
         assert_eq!(
-            buffer.get_line(0).map(|line| line.get(0..5)),
-            Some("first".to_string())
+            buffer.canonical_path(),
+            Some(target.canonicalize()?.as_path())
         );
+        buffer.insert(Position { row: 0, col: 5 }, '!');
+        assert!(buffer.is_dirty());
+        buffer.save()?;
+        assert!(!buffer.is_dirty());
+
+        assert_eq!(std::fs::read_to_string(&target)?, "Hello!\n");
+        assert!(std::fs::symlink_metadata(&link)?.file_type().is_symlink());
+
+        remove_file(link)?;
+        remove_file(target)?;
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn save_recreates_missing_symlink_target() -> std::io::Result<()> {
+        let target = unique_file_path();
+        let mut link = target.clone();
+        link.set_extension("link");
+        std::fs::write(&target, "Hello\n")?;
+        std::os::unix::fs::symlink(&target, &link)?;
+
+        let mut buffer = Buffer::load(link.to_str().unwrap())?;
+        remove_file(&target)?;
+        assert!(buffer.is_save_target_missing());
+        buffer.save()?;
+
+        assert!(!buffer.is_save_target_missing());
+        assert_eq!(std::fs::read_to_string(&link)?, "Hello\n");
+
+        remove_file(link)?;
+        remove_file(target)?;
+        Ok(())
+    }
+
+    #[test]
+    fn save_without_file_name_fails() {
+        let mut buffer = Buffer::default();
+        buffer.push("Hello");
+        assert!(buffer.save().is_err());
+    }
+
+    #[test]
+    fn rfind_searches_backward_and_wraps() {
+        let mut buffer = Buffer::default();
+        buffer.push("one two one");
+        buffer.push("two one");
+        let options = SearchOptions::default();
+        let at = |col, row| Position { col, row };
+        assert_eq!(buffer.rfind("one", at(4, 1), options), Some(at(8, 0)));
+        assert_eq!(buffer.rfind("one", at(8, 0), options), Some(at(0, 0)));
+        // wraps to the last match in the buffer
+        assert_eq!(buffer.rfind("one", at(0, 0), options), Some(at(4, 1)));
+        assert_eq!(buffer.rfind("three", at(0, 1), options), None);
+    }
+
+    #[test]
+    fn search_iter_yields_matches_in_order() {
+        let mut buffer = Buffer::default();
+        buffer.push("foo foo");
+        buffer.push("bar");
+        buffer.push("a foo");
+        let options = SearchOptions::default();
+        let matches: Vec<Position> = buffer.search_iter("foo", options).collect();
         assert_eq!(
-            buffer.get_line(1).map(|line| line.get(0..6)),
-            Some("second".to_string())
+            matches,
+            vec![
+                Position { col: 0, row: 0 },
+                Position { col: 4, row: 0 },
+                Position { col: 2, row: 2 },
+            ]
         );
+        assert_eq!(buffer.search_iter("", options).count(), 0);
+    }
 
-        remove_file(path)?;
-        Ok(())
+    #[test]
+    fn find_moves_to_later_lines_and_wraps() {
+        let mut buffer = Buffer::default();
+        buffer.push("foo bar");
+        buffer.push("baz foo");
+        let options = SearchOptions::default();
+
+        let first = buffer.find("foo", Position { row: 0, col: 1 }, options);
+        assert_eq!(first, Some(Position { row: 1, col: 4 }));
+        let wrapped = buffer.find("foo", Position { row: 1, col: 5 }, options);
+        assert_eq!(wrapped, Some(Position { row: 0, col: 0 }));
+        assert_eq!(buffer.find("qux", Position::default(), options), None);
     }
 
     #[test]
-    fn load_returns_error_for_missing_file() {
-        let path = unique_file_path();
-        let result = Buffer::load(path.to_str().unwrap());
-        assert!(result.is_err());
+    fn find_on_empty_buffer() {
+        let buffer = Buffer::default();
+        let result = buffer.find("foo", Position::default(), SearchOptions::default());
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn line_width_counts_cells_not_graphemes() {
+        let mut buffer = Buffer::default();
+        buffer.push("a👋Ｂ");
+        assert_eq!(buffer.line_len(0), 3);
+        assert_eq!(buffer.line_width(0), 5);
+        assert_eq!(buffer.line_width(1), 0);
     }
 
     #[test]
@@ -175,7 +1847,7 @@ mod tests {
         let mut buffer = Buffer::default();
         buffer.push("Hello");
         let deleted = buffer.delete(Position { row: 0, col: 0 });
-        assert!(deleted);
+        assert_eq!(deleted, Deletion::HalfWidth);
         let line = buffer.get_line(0).unwrap();
         let full_width = line.position_of(line.len());
         assert_eq!(line.get(0..full_width), "ello");
@@ -187,7 +1859,7 @@ mod tests {
         let mut buffer = Buffer::default();
         buffer.push("Hxllo");
         let deleted = buffer.delete(Position { row: 0, col: 2 });
-        assert!(deleted);
+        assert_eq!(deleted, Deletion::HalfWidth);
         let line = buffer.get_line(0).unwrap();
         let full_width = line.position_of(line.len());
         assert_eq!(line.get(0..full_width), "Hxlo");
@@ -200,7 +1872,7 @@ mod tests {
         buffer.push("Hello!");
         let last = buffer.line_len(0) - 1;
         let deleted = buffer.delete(Position { row: 0, col: last });
-        assert!(deleted);
+        assert_eq!(deleted, Deletion::HalfWidth);
         let line = buffer.get_line(0).unwrap();
         let full_width = line.position_of(line.len());
         assert_eq!(line.get(0..full_width), "Hello");
@@ -212,7 +1884,7 @@ mod tests {
         let mut buffer = Buffer::default();
         buffer.push("Hello");
         let deleted = buffer.delete(Position { row: 0, col: 100 });
-        assert!(!deleted);
+        assert_eq!(deleted, Deletion::Nothing);
         let line = buffer.get_line(0).unwrap();
         let full_width = line.position_of(line.len());
         assert_eq!(line.get(0..full_width), "Hello");
@@ -224,7 +1896,7 @@ mod tests {
         let mut buffer = Buffer::default();
         buffer.push("Hello");
         let deleted = buffer.delete(Position { row: 2, col: 0 });
-        assert!(!deleted);
+        assert_eq!(deleted, Deletion::Nothing);
         assert_eq!(buffer.num_lines(), 1);
         let line = buffer.get_line(0).unwrap();
         let full_width = line.position_of(line.len());
@@ -241,7 +1913,7 @@ mod tests {
         assert_eq!(full_width_before, 4);
 
         let deleted = buffer.delete(Position { row: 0, col: 1 });
-        assert!(deleted);
+        assert_eq!(deleted, Deletion::FullWidth);
 
         let line_after = buffer.get_line(0).unwrap();
         let full_width_after = line_after.position_of(line_after.len());
@@ -250,11 +1922,389 @@ mod tests {
         assert_eq!(line_after.len(), 2);
     }
 
+    fn contents(buffer: &Buffer) -> Vec<String> {
+        buffer.lines.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn delete_range_within_line() {
+        let mut buffer = Buffer::default();
+        buffer.push("Hello world");
+        assert!(buffer.delete_range(Position { row: 0, col: 2 }, Position { row: 0, col: 8 }));
+        assert_eq!(contents(&buffer), vec!["Herld"]);
+        assert!(buffer.is_dirty());
+    }
+
+    #[test]
+    fn delete_range_across_lines() {
+        let mut buffer = Buffer::default();
+        buffer.push("first line");
+        buffer.push("second line");
+        buffer.push("third line");
+        buffer.push("fourth line");
+        let deleted = buffer.delete_range(Position { row: 0, col: 6 }, Position { row: 2, col: 6 });
+        assert!(deleted);
+        assert_eq!(contents(&buffer), vec!["first line", "fourth line"]);
+    }
+
+    #[test]
+    fn surround_within_line() {
+        let mut buffer = Buffer::default();
+        buffer.push("call x + 1 now");
+        let end = buffer.surround(
+            Position { row: 0, col: 5 },
+            Position { row: 0, col: 10 },
+            '(',
+            ')',
+        );
+        assert_eq!(end, Some(Position { row: 0, col: 12 }));
+        assert_eq!(contents(&buffer), vec!["call (x + 1) now"]);
+    }
+
+    #[test]
+    fn surround_up_to_a_line_start_closes_the_line_before() {
+        let mut buffer = Buffer::default();
+        buffer.push("let a = 1;");
+        buffer.push("let b = 2;");
+        let end = buffer.surround(
+            Position { row: 0, col: 0 },
+            Position { row: 1, col: 0 },
+            '(',
+            ')',
+        );
+        assert_eq!(end, Some(Position { row: 0, col: 12 }));
+        assert_eq!(contents(&buffer), vec!["(let a = 1;)", "let b = 2;"]);
+    }
+
+    #[test]
+    fn surround_across_lines() {
+        let mut buffer = Buffer::default();
+        buffer.push("let a = 1;");
+        buffer.push("let b = 2;");
+        let end = buffer.surround(
+            Position { row: 0, col: 0 },
+            Position { row: 1, col: 10 },
+            '{',
+            '}',
+        );
+        assert_eq!(end, Some(Position { row: 1, col: 11 }));
+        assert_eq!(contents(&buffer), vec!["{let a = 1;", "let b = 2;}"]);
+        assert_eq!(
+            buffer.surround(
+                Position { row: 1, col: 2 },
+                Position { row: 1, col: 2 },
+                '[',
+                ']'
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn delete_range_empty_is_noop() {
+        let mut buffer = Buffer::default();
+        buffer.push("Hello");
+        let at = Position { row: 0, col: 2 };
+        assert!(!buffer.delete_range(at, at));
+        assert_eq!(contents(&buffer), vec!["Hello"]);
+        assert!(!buffer.is_dirty());
+    }
+
+    #[test]
+    fn append_adds_lines_in_order() {
+        let mut buffer = Buffer::default();
+        buffer.push("one");
+        buffer.push("two");
+        buffer.push("three");
+        let mut other = Buffer::default();
+        other.push("four");
+        other.push("five");
+
+        buffer.append(other);
+
+        assert_eq!(buffer.num_lines(), 5);
+        assert_eq!(
+            contents(&buffer),
+            vec!["one", "two", "three", "four", "five"]
+        );
+        assert!(buffer.is_dirty());
+    }
+
+    #[test]
+    fn append_empty_buffer_keeps_clean() {
+        let mut buffer = Buffer::default();
+        buffer.push("one");
+        buffer.append(Buffer::default());
+        assert_eq!(buffer.num_lines(), 1);
+        assert!(!buffer.is_dirty());
+    }
+
+    #[test]
+    fn duplicate_lines_inserts_copy_below() {
+        let mut buffer = Buffer::default();
+        buffer.push("a");
+        buffer.push("b");
+        buffer.push("c");
+        assert_eq!(buffer.duplicate_lines(0..2), 2);
+        assert_eq!(contents(&buffer), vec!["a", "b", "a", "b", "c"]);
+        assert_eq!(buffer.duplicate_lines(7..9), 0);
+    }
+
+    #[test]
+    fn reverting_an_edit_restores_the_hash() {
+        let mut buffer = Buffer::default();
+        buffer.push("one");
+        buffer.push("two");
+        let original = buffer.content_hash();
+
+        buffer.insert(Position { col: 3, row: 0 }, '!');
+        assert_ne!(buffer.content_hash(), original);
+        buffer.delete(Position { col: 3, row: 0 });
+        assert_eq!(buffer.content_hash(), original);
+
+        // the line breaks count, not just the text
+        buffer.merge_line(0);
+        assert_ne!(buffer.content_hash(), original);
+    }
+
+    #[test]
+    fn recheck_dirty_notices_reverted_edits() -> std::io::Result<()> {
+        let path = unique_file_path();
+        std::fs::write(&path, "abc")?;
+        let mut buffer = Buffer::load(path.to_str().unwrap())?;
+        buffer.insert(Position { col: 0, row: 0 }, 'x');
+        assert!(buffer.recheck_dirty());
+        buffer.delete(Position { col: 0, row: 0 });
+        assert!(!buffer.recheck_dirty());
+        remove_file(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn insert_lines_in_middle_and_at_end() {
+        let mut buffer = Buffer::default();
+        buffer.push("a");
+        buffer.push("d");
+        assert_eq!(buffer.insert_lines(1, &["b", "c"]), 2);
+        assert_eq!(contents(&buffer), vec!["a", "b", "c", "d"]);
+        assert_eq!(buffer.insert_lines(4, &["e"]), 1);
+        assert_eq!(contents(&buffer), vec!["a", "b", "c", "d", "e"]);
+        assert!(buffer.is_dirty());
+        assert_eq!(buffer.insert_lines(0, &[]), 0);
+    }
+
+    #[test]
+    fn lines_in_range_yields_clamped_subset() {
+        let mut buffer = Buffer::default();
+        for line in ["a", "b", "c", "d"] {
+            buffer.push(line);
+        }
+        let rows = |range| {
+            buffer
+                .lines_in_range(range)
+                .map(|(row, line)| (row, line.to_string()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(rows(1..3), vec![(1, "b".to_string()), (2, "c".to_string())]);
+        assert_eq!(rows(3..10), vec![(3, "d".to_string())]);
+        assert!(rows(6..10).is_empty());
+    }
+
+    #[test]
+    fn align_on_lines_up_separators() {
+        let mut buffer = Buffer::default();
+        for line in ["a = 1", "long_name = 2", "no separator", "ＢＢ = 3"] {
+            buffer.push(line);
+        }
+        assert!(buffer.can_align_on(0..4, '='));
+        assert!(buffer.align_on(0..4, '='));
+        assert_eq!(
+            contents(&buffer),
+            vec![
+                "a         = 1",
+                "long_name = 2",
+                "no separator",
+                "ＢＢ      = 3"
+            ]
+        );
+        assert!(!buffer.can_align_on(0..4, '='));
+        assert!(!buffer.align_on(0..4, '='));
+    }
+
+    #[test]
+    fn dedup_consecutive_collapses_runs_only() {
+        let mut buffer = Buffer::default();
+        for line in ["a", "a", "a", "b", "a", "c", "c"] {
+            buffer.push(line);
+        }
+        assert_eq!(buffer.dedup_consecutive(0..7), 3);
+        assert_eq!(contents(&buffer), vec!["a", "b", "a", "c"]);
+        assert_eq!(buffer.dedup_consecutive(0..4), 0);
+    }
+
+    #[test]
+    fn dedup_consecutive_stays_within_rows() {
+        let mut buffer = Buffer::default();
+        for line in ["x", "x", "x", "x"] {
+            buffer.push(line);
+        }
+        assert_eq!(buffer.dedup_consecutive(1..3), 1);
+        assert_eq!(contents(&buffer), vec!["x", "x", "x"]);
+    }
+
+    #[test]
+    fn delete_zero_width_grapheme() {
+        let mut buffer = Buffer::default();
+        buffer.push("a\u{200b}b");
+        let deleted = buffer.delete(Position { row: 0, col: 1 });
+        assert_eq!(deleted, Deletion::ZeroWidth);
+        assert!(buffer.is_dirty());
+        let line = buffer.get_line(0).unwrap();
+        assert_eq!(line.to_string(), "ab");
+    }
+
+    #[test]
+    fn deletion_columns_follow_the_drawn_width() {
+        let mut buffer = Buffer::default();
+        buffer.push("\u{200b}a👋\r");
+        let columns: Vec<usize> = (0..4)
+            .map(|_| buffer.delete(Position { row: 0, col: 0 }).columns())
+            .collect();
+        assert_eq!(columns, vec![1, 1, 2, 2]);
+        assert_eq!(buffer.delete(Position { row: 0, col: 0 }).columns(), 0);
+    }
+
     #[test]
     fn delete_on_empty_buffer_noop() {
         let mut buffer = Buffer::default();
         let deleted = buffer.delete(Position { row: 0, col: 0 });
-        assert!(!deleted);
+        assert_eq!(deleted, Deletion::Nothing);
         assert_eq!(buffer.num_lines(), 0);
+        assert!(!buffer.is_dirty());
+    }
+
+    #[test]
+    fn selection_stats_within_line() {
+        let mut buffer = Buffer::default();
+        buffer.push("Hello world");
+        let from = Position { col: 6, row: 0 };
+        let to = Position { col: 11, row: 0 };
+        assert_eq!(buffer.selection_stats(from, to), (5, 1));
+        assert_eq!(buffer.selection_stats(to, from), (5, 1));
+    }
+
+    #[test]
+    fn selection_stats_across_lines() {
+        let mut buffer = Buffer::default();
+        buffer.push("first");
+        buffer.push("second");
+        buffer.push("third");
+        let from = Position { col: 2, row: 0 };
+        let to = Position { col: 3, row: 2 };
+        // "rst", "second" and "thi"
+        assert_eq!(buffer.selection_stats(to, from), (12, 3));
+    }
+
+    #[test]
+    fn selection_stats_follow_edits() {
+        let mut buffer = Buffer::default();
+        buffer.push("first");
+        buffer.push("second");
+        let from = Position { col: 0, row: 0 };
+        let to = Position { col: 0, row: 9 };
+        assert_eq!(buffer.selection_stats(from, to), (11, 10));
+        buffer.duplicate_lines(1..2);
+        assert_eq!(buffer.selection_stats(from, to), (17, 10));
+        buffer.delete(Position { col: 0, row: 0 });
+        assert_eq!(buffer.selection_stats(from, to), (16, 10));
+    }
+
+    #[test]
+    fn retab_converts_between_tabs_and_spaces() {
+        let mut buffer = Buffer::default();
+        buffer.push("\tone");
+        buffer.push("\t\t two");
+        buffer.push("  \tthree");
+        buffer.push("none");
+        assert!(buffer.retab(0..4, true, 4));
+        let lines: Vec<String> = buffer.lines.iter().map(ToString::to_string).collect();
+        assert_eq!(lines, ["    one", "         two", "    three", "none"]);
+
+        assert!(buffer.retab(0..4, false, 4));
+        let lines: Vec<String> = buffer.lines.iter().map(ToString::to_string).collect();
+        assert_eq!(lines, ["\tone", "\t\t two", "\tthree", "none"]);
+        assert!(!buffer.can_retab(0..4, false, 4));
+        assert!(!buffer.retab(0..4, false, 4));
+    }
+
+    #[test]
+    fn insert_returning_advance_skips_combining_marks() {
+        let mut buffer = Buffer::default();
+        buffer.push("e");
+        assert_eq!(
+            buffer.insert_returning_advance(Position { col: 1, row: 0 }, '\u{301}'),
+            0
+        );
+        assert_eq!(buffer.line_len(0), 1);
+        assert_eq!(
+            buffer.insert_returning_advance(Position { col: 1, row: 0 }, 'x'),
+            1
+        );
+        assert_eq!(buffer.get_line(0).unwrap().to_string(), "e\u{301}x");
+    }
+
+    fn word_buffer() -> Buffer {
+        let mut buffer = Buffer::default();
+        buffer.push("let foo = bar;");
+        buffer.push("");
+        buffer.push("  baz");
+        buffer
+    }
+
+    #[test]
+    fn next_word_from_middle_of_word_and_whitespace() {
+        let buffer = word_buffer();
+        let at = |col, row| Position { col, row };
+        assert_eq!(buffer.next_word(at(5, 0)), at(8, 0));
+        assert_eq!(buffer.next_word(at(3, 0)), at(4, 0));
+        assert_eq!(buffer.next_word(at(8, 0)), at(10, 0));
+    }
+
+    #[test]
+    fn word_motion_crosses_line_boundaries() {
+        let buffer = word_buffer();
+        let at = |col, row| Position { col, row };
+        // past the blank line to the next line's first word
+        assert_eq!(buffer.next_word(at(13, 0)), at(2, 2));
+        assert_eq!(buffer.next_word(at(2, 2)), at(5, 2));
+        assert_eq!(buffer.prev_word(at(2, 2)), at(13, 0));
+        assert_eq!(buffer.prev_word(at(5, 0)), at(4, 0));
+        assert_eq!(buffer.prev_word(at(0, 0)), at(0, 0));
+    }
+
+    #[test]
+    fn longest_line_width_kept_when_a_narrower_line_shrinks() {
+        let mut buffer = Buffer::default();
+        buffer.push("the longest");
+        buffer.push("short");
+        assert_eq!(buffer.longest_line_width(), 11);
+        buffer.delete(Position { col: 0, row: 1 });
+        assert_eq!(buffer.longest_line_width.get(), Some(11));
+        buffer.insert_text(Position { col: 3, row: 0 }, "\n");
+        assert_eq!(buffer.longest_line_width(), 8);
+    }
+
+    #[test]
+    fn restoring_the_saved_text_clears_dirty() -> std::io::Result<()> {
+        let path = unique_file_path();
+        std::fs::write(&path, "abc")?;
+        let mut buffer = Buffer::load(path.to_str().unwrap())?;
+        buffer.insert(Position { col: 0, row: 0 }, 'x');
+        buffer.restore(0..1, vec![Line::from("abc")]);
+        assert!(!buffer.is_dirty());
+        buffer.restore(0..1, vec![Line::from("xabc")]);
+        assert!(buffer.is_dirty());
+        remove_file(path)?;
+        Ok(())
     }
 }
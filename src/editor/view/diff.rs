@@ -0,0 +1,152 @@
+use std::fmt::{Display, Formatter};
+
+// the most cells of the table `aligned` may fill; beyond that the lines
+// between the shared start and end are counted as one block
+const MAX_ALIGN_CELLS: usize = 1 << 22;
+
+/// How many lines differ between two versions of a text.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DiffStats {
+    pub added: usize,
+    pub removed: usize,
+    pub changed: usize,
+}
+
+impl DiffStats {
+    /// Count the differences from `old` to `new`. The lines both share at
+    /// the start and at the end are equal. Of the lines in between, those
+    /// at the same place are compared if both sides have as many, and
+    /// otherwise aligned on their longest common subsequence; in each gap
+    /// between aligned lines, as many as both sides have are changed and
+    /// the rest added or removed.
+    pub fn between<T: PartialEq>(old: &[T], new: &[T]) -> Self {
+        let prefix = old
+            .iter()
+            .zip(new)
+            .take_while(|(left, right)| left == right)
+            .count();
+        let (old, new) = (&old[prefix..], &new[prefix..]);
+        let suffix = old
+            .iter()
+            .rev()
+            .zip(new.iter().rev())
+            .take_while(|(left, right)| left == right)
+            .count();
+        let (old, new) = (&old[..old.len() - suffix], &new[..new.len() - suffix]);
+        if old.len() == new.len() {
+            let changed = old.iter().zip(new).filter(|(left, right)| left != right);
+            return Self {
+                changed: changed.count(),
+                ..Self::default()
+            };
+        }
+        if (old.len() + 1).saturating_mul(new.len() + 1) > MAX_ALIGN_CELLS {
+            let mut stats = Self::default();
+            stats.add_gap(old.len(), new.len());
+            return stats;
+        }
+        Self::aligned(old, new)
+    }
+
+    fn aligned<T: PartialEq>(old: &[T], new: &[T]) -> Self {
+        let width = new.len() + 1;
+        // at `i * width + j`, the length of the longest common subsequence
+        // of `old[i..]` and `new[j..]`
+        let mut common = vec![0; (old.len() + 1) * width];
+        for i in (0..old.len()).rev() {
+            for j in (0..new.len()).rev() {
+                common[i * width + j] = if old[i] == new[j] {
+                    common[(i + 1) * width + j + 1] + 1
+                } else {
+                    common[(i + 1) * width + j].max(common[i * width + j + 1])
+                };
+            }
+        }
+        let mut stats = Self::default();
+        let (mut i, mut j) = (0, 0);
+        let (mut removed, mut added) = (0, 0);
+        while i < old.len() || j < new.len() {
+            if i < old.len() && j < new.len() && old[i] == new[j] {
+                stats.add_gap(removed, added);
+                (removed, added) = (0, 0);
+                (i, j) = (i + 1, j + 1);
+            } else if j == new.len()
+                || (i < old.len() && common[(i + 1) * width + j] >= common[i * width + j + 1])
+            {
+                removed += 1;
+                i += 1;
+            } else {
+                added += 1;
+                j += 1;
+            }
+        }
+        stats.add_gap(removed, added);
+        stats
+    }
+
+    // count a run of `removed` old lines replaced by `added` new ones
+    fn add_gap(&mut self, removed: usize, added: usize) {
+        let changed = removed.min(added);
+        self.changed += changed;
+        self.added += added - changed;
+        self.removed += removed - changed;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl Display for DiffStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} changed, {} added, {} removed",
+            self.changed, self.added, self.removed
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(old: &[&str], new: &[&str]) -> (usize, usize, usize) {
+        let DiffStats {
+            added,
+            removed,
+            changed,
+        } = DiffStats::between(old, new);
+        (changed, added, removed)
+    }
+
+    #[test]
+    fn identical_texts_have_no_differences() {
+        assert!(DiffStats::between(&["a", "b"], &["a", "b"]).is_empty());
+    }
+
+    #[test]
+    fn counts_changed_added_and_removed_lines() {
+        assert_eq!(stats(&["a", "b", "c"], &["a", "B", "c"]), (1, 0, 0));
+        assert_eq!(stats(&["a", "c"], &["a", "b", "b", "c"]), (0, 2, 0));
+        assert_eq!(stats(&["a", "b", "c", "d"], &["a", "d"]), (0, 0, 2));
+        assert_eq!(stats(&["a", "b"], &["x", "y", "z"]), (2, 1, 0));
+    }
+
+    #[test]
+    fn scattered_edits_are_counted_apart() {
+        assert_eq!(
+            stats(&["a", "b", "c", "d", "e"], &["a", "B", "c", "d", "E"]),
+            (2, 0, 0)
+        );
+        assert_eq!(
+            stats(&["a", "b", "c", "d"], &["x", "a", "c", "d", "y"]),
+            (0, 2, 1)
+        );
+    }
+
+    #[test]
+    fn repeated_lines_are_not_counted_twice() {
+        assert_eq!(stats(&["a", "a"], &["a", "a", "a"]), (0, 1, 0));
+    }
+}
@@ -0,0 +1,155 @@
+use std::ops::Range;
+
+/// What is shown on a row of the view.
+#[derive(Clone, Debug, PartialEq)]
+pub enum VisibleRow {
+    Line(usize),
+    /// A summary standing in for a folded range of lines.
+    Fold(Range<usize>),
+}
+
+/// Folded line ranges, and the mapping between buffer rows and the rows
+/// visible once each fold is collapsed into one.
+#[derive(Default)]
+pub struct Folds {
+    // sorted, disjoint and non-empty
+    ranges: Vec<Range<usize>>,
+    // the buffer length the folds were made for
+    line_count: usize,
+}
+
+impl Folds {
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Whether the folds were made for a buffer of a different length,
+    /// in which case their rows no longer line up with the text.
+    pub fn is_stale(&self, line_count: usize) -> bool {
+        !self.is_empty() && self.line_count != line_count
+    }
+
+    pub fn clear(&mut self) {
+        self.ranges.clear();
+    }
+
+    /// Fold `range`, replacing any folds it overlaps.
+    pub fn add(&mut self, range: Range<usize>, line_count: usize) {
+        if range.is_empty() {
+            return;
+        }
+        self.ranges
+            .retain(|fold| fold.end <= range.start || fold.start >= range.end);
+        let index = self.ranges.partition_point(|fold| fold.start < range.start);
+        self.ranges.insert(index, range);
+        self.line_count = line_count;
+    }
+
+    /// Unfold the fold containing `row`, returning whether there was one.
+    pub fn remove_containing(&mut self, row: usize) -> bool {
+        let len = self.ranges.len();
+        self.ranges.retain(|fold| !fold.contains(&row));
+        self.ranges.len() != len
+    }
+
+    /// Unfold every fold sharing a row with `rows`, returning whether
+    /// there was any.
+    pub fn remove_overlapping(&mut self, rows: Range<usize>) -> bool {
+        let len = self.ranges.len();
+        self.ranges
+            .retain(|fold| fold.end <= rows.start || fold.start >= rows.end);
+        self.ranges.len() != len
+    }
+
+    pub fn containing(&self, row: usize) -> Option<Range<usize>> {
+        self.ranges.iter().find(|fold| fold.contains(&row)).cloned()
+    }
+
+    /// The visible row showing buffer row `row`.
+    pub fn to_visible(&self, row: usize) -> usize {
+        let mut hidden = 0;
+        for fold in &self.ranges {
+            if row >= fold.end {
+                hidden += fold.len() - 1;
+            } else if row >= fold.start {
+                return fold.start - hidden;
+            } else {
+                break;
+            }
+        }
+        row - hidden
+    }
+
+    /// What is shown on visible row `visible`.
+    pub fn to_buffer(&self, visible: usize) -> VisibleRow {
+        let mut row = visible;
+        for fold in &self.ranges {
+            if row < fold.start {
+                break;
+            }
+            if row == fold.start {
+                return VisibleRow::Fold(fold.clone());
+            }
+            row += fold.len() - 1;
+        }
+        VisibleRow::Line(row)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn folds(ranges: &[Range<usize>]) -> Folds {
+        let mut folds = Folds::default();
+        for range in ranges {
+            folds.add(range.clone(), 20);
+        }
+        folds
+    }
+
+    #[test]
+    fn rows_map_around_one_fold() {
+        let mut folds = Folds::default();
+        folds.add(3..7, 20);
+        assert_eq!(folds.to_visible(2), 2);
+        assert_eq!(folds.to_visible(3), 3);
+        assert_eq!(folds.to_visible(6), 3);
+        assert_eq!(folds.to_visible(7), 4);
+        assert_eq!(folds.to_visible(10), 7);
+
+        assert_eq!(folds.to_buffer(2), VisibleRow::Line(2));
+        assert_eq!(folds.to_buffer(3), VisibleRow::Fold(3..7));
+        assert_eq!(folds.to_buffer(4), VisibleRow::Line(7));
+        assert_eq!(folds.to_buffer(7), VisibleRow::Line(10));
+    }
+
+    #[test]
+    fn rows_map_around_two_folds() {
+        let folds = folds(&[8..10, 2..5]);
+        assert_eq!(folds.to_buffer(2), VisibleRow::Fold(2..5));
+        assert_eq!(folds.to_buffer(5), VisibleRow::Line(7));
+        assert_eq!(folds.to_buffer(6), VisibleRow::Fold(8..10));
+        assert_eq!(folds.to_buffer(7), VisibleRow::Line(10));
+        assert_eq!(folds.to_visible(10), 7);
+    }
+
+    #[test]
+    fn overlapping_fold_replaces_old_one() {
+        let mut folds = folds(&[3..5, 8..9]);
+        folds.add(2..6, 20);
+        assert_eq!(folds.containing(4), Some(2..6));
+        assert!(folds.remove_containing(4));
+        assert_eq!(folds.containing(4), None);
+        assert_eq!(folds.containing(8), Some(8..9));
+    }
+
+    #[test]
+    fn folds_go_stale_when_line_count_changes() {
+        let mut folds = Folds::default();
+        folds.add(3..5, 20);
+        assert!(!folds.is_stale(20));
+        assert!(folds.is_stale(21));
+        assert!(!Folds::default().is_stale(21));
+    }
+}
@@ -0,0 +1,96 @@
+use crate::editor::config::Config;
+use unicode_width::UnicodeWidthStr;
+
+/// The line number column drawn left of the text: each number is right
+/// aligned, followed by the separator and then the padding.
+pub struct Gutter<'a> {
+    digits: usize,
+    separator: &'a str,
+    padding: usize,
+    // the cursor row, for numbers relative to it
+    relative_to: Option<usize>,
+}
+
+impl<'a> Gutter<'a> {
+    /// The gutter for a buffer of `num_lines` lines, or `None` if line
+    /// numbers are turned off.
+    pub fn new(config: &'a Config, num_lines: usize) -> Option<Self> {
+        config.line_numbers.then(|| Self {
+            digits: num_lines.max(1).to_string().len(),
+            separator: &config.gutter_separator,
+            padding: config.gutter_padding,
+            relative_to: None,
+        })
+    }
+
+    /// Label lines by their distance from `cursor_row` instead, all but
+    /// the cursor line itself.
+    pub fn relative_to(self, cursor_row: usize) -> Self {
+        Self {
+            relative_to: Some(cursor_row),
+            ..self
+        }
+    }
+
+    /// The number of columns taken from the text.
+    pub fn width(&self) -> usize {
+        self.digits + self.separator.width() + self.padding
+    }
+
+    /// The gutter of the line at `row`, or a blank one for `None`.
+    pub fn label(&self, row: Option<usize>) -> String {
+        let number = match (row, self.relative_to) {
+            (Some(row), Some(cursor_row)) if row != cursor_row => {
+                row.abs_diff(cursor_row).to_string()
+            }
+            (Some(row), _) => (row + 1).to_string(),
+            (None, _) => String::new(),
+        };
+        format!(
+            "{number:>digits$}{separator}{:padding$}",
+            "",
+            digits = self.digits,
+            separator = self.separator,
+            padding = self.padding
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(separator: &str, padding: usize) -> Config {
+        Config {
+            line_numbers: true,
+            gutter_separator: separator.to_string(),
+            gutter_padding: padding,
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn numbers_are_aligned_to_widest() {
+        let config = config("│", 1);
+        let gutter = Gutter::new(&config, 120).unwrap();
+        assert_eq!(gutter.width(), 5);
+        assert_eq!(gutter.label(Some(8)), "  9│ ");
+        assert_eq!(gutter.label(Some(119)), "120│ ");
+        assert_eq!(gutter.label(None), "   │ ");
+    }
+
+    #[test]
+    fn relative_numbers_count_from_cursor_row() {
+        let config = config("", 1);
+        let gutter = Gutter::new(&config, 120).unwrap().relative_to(10);
+        assert_eq!(gutter.label(Some(8)), "  2 ");
+        assert_eq!(gutter.label(Some(13)), "  3 ");
+        // the cursor line keeps its own number
+        assert_eq!(gutter.label(Some(10)), " 11 ");
+    }
+
+    #[test]
+    fn no_gutter_without_line_numbers() {
+        assert!(Gutter::new(&Config::default(), 10).is_none());
+    }
+}
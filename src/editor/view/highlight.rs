@@ -0,0 +1,97 @@
+use crossterm::style::Color;
+use std::ops::Range;
+
+/// A range of grid columns on a rendered row, drawn with a background
+/// color.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Highlight {
+    pub range: Range<usize>,
+    pub color: Color,
+}
+
+/// Split the visible `window` of a row into consecutive segments at the
+/// highlight boundaries, each paired with the color to draw it with.
+/// Later highlights take precedence over earlier ones.
+pub fn segments(
+    window: Range<usize>,
+    highlights: &[Highlight],
+) -> Vec<(Range<usize>, Option<Color>)> {
+    let mut bounds = vec![window.start, window.end];
+    for highlight in highlights {
+        bounds.push(highlight.range.start.clamp(window.start, window.end));
+        bounds.push(highlight.range.end.clamp(window.start, window.end));
+    }
+    bounds.sort_unstable();
+    bounds.dedup();
+
+    let mut segments: Vec<(Range<usize>, Option<Color>)> = Vec::new();
+    for pair in bounds.windows(2) {
+        let color = highlights
+            .iter()
+            .rev()
+            .find(|highlight| highlight.range.contains(&pair[0]))
+            .map(|highlight| highlight.color);
+        match segments.last_mut() {
+            // merge neighbours drawn the same way
+            Some((range, last_color)) if *last_color == color => range.end = pair[1],
+            _ => segments.push((pair[0]..pair[1], color)),
+        }
+    }
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_highlights_is_single_segment() {
+        assert_eq!(segments(2..10, &[]), vec![(2..10, None)]);
+    }
+
+    #[test]
+    fn highlight_splits_window() {
+        let highlights = [Highlight {
+            range: 4..6,
+            color: Color::Red,
+        }];
+        assert_eq!(
+            segments(0..10, &highlights),
+            vec![(0..4, None), (4..6, Some(Color::Red)), (6..10, None)]
+        );
+    }
+
+    #[test]
+    fn highlight_is_clipped_to_window() {
+        let highlights = [Highlight {
+            range: 0..20,
+            color: Color::Red,
+        }];
+        assert_eq!(
+            segments(5..10, &highlights),
+            vec![(5..10, Some(Color::Red))]
+        );
+    }
+
+    #[test]
+    fn later_highlight_wins_on_overlap() {
+        let highlights = [
+            Highlight {
+                range: 0..6,
+                color: Color::Red,
+            },
+            Highlight {
+                range: 4..8,
+                color: Color::Blue,
+            },
+        ];
+        assert_eq!(
+            segments(0..10, &highlights),
+            vec![
+                (0..4, Some(Color::Red)),
+                (4..8, Some(Color::Blue)),
+                (8..10, None)
+            ]
+        );
+    }
+}
@@ -1,8 +1,12 @@
+use super::search::SearchOptions;
+use std::cell::{Ref, RefCell};
+use std::fmt::{Display, Formatter};
+use std::hash::{Hash, Hasher};
 use std::ops::Range;
 use unicode_segmentation::UnicodeSegmentation;
 use unicode_width::UnicodeWidthStr;
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum GraphemeWidth {
     Half,
     Full,
@@ -17,20 +21,73 @@ impl GraphemeWidth {
     }
 }
 
+/// The symbols drawn for graphemes that can't be shown as they are.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Glyphs {
+    #[default]
+    Unicode,
+    /// For terminals without the symbols used by `Unicode`.
+    Ascii,
+}
+
+impl Glyphs {
+    /// Marks a wide grapheme cut off at the edge of the range.
+    fn clipped(self) -> char {
+        match self {
+            Glyphs::Unicode => '⋯',
+            Glyphs::Ascii => '>',
+        }
+    }
+
+    fn substitute(self, replacement: &str) -> &str {
+        match (self, replacement) {
+            (Glyphs::Ascii, "␣") => "_",
+            (Glyphs::Ascii, "▯") => "?",
+            (Glyphs::Ascii, "·") => ".",
+            _ => replacement,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct TextFragment {
     pub grapheme: String,
     pub rendered_width: GraphemeWidth,
-    pub replacement: Option<char>,
+    pub replacement: Option<String>,
 }
 
+// the arguments and result of the last `get_with`
+type Rendered = (Range<usize>, Glyphs, String);
+
+#[derive(Clone)]
 pub struct Line {
     fragments: Vec<TextFragment>,
+    // reused while the line is unchanged, as when scrolling back and
+    // forth; every mutation clears it
+    rendered: RefCell<Option<Rendered>>,
+    // the grid column each fragment starts at, plus the total width at
+    // the end; built on first use and cleared along with `rendered`
+    starts: RefCell<Option<Vec<usize>>>,
 }
 
 impl Line {
     pub fn from(line_str: &str) -> Self {
-        let fragments = Self::str_to_fragments(line_str);
-        Self { fragments }
+        Self::from_fragments(Self::str_to_fragments(line_str))
+    }
+
+    /// Build a line from fragments that are already segmented, e.g. taken
+    /// from another line.
+    pub(crate) fn from_fragments(fragments: Vec<TextFragment>) -> Self {
+        Self {
+            fragments,
+            rendered: RefCell::new(None),
+            starts: RefCell::new(None),
+        }
+    }
+
+    fn invalidate(&mut self) {
+        *self.rendered.get_mut() = None;
+        *self.starts.get_mut() = None;
     }
 
     pub fn insert(&mut self, at: usize, ch: char) {
@@ -49,33 +106,95 @@ impl Line {
         }
 
         self.fragments = Self::str_to_fragments(&result);
+        self.invalidate();
     }
 
-    pub fn delete(&mut self, at: usize) -> bool {
+    pub fn replace(&mut self, at: usize, ch: char) -> bool {
         if at >= self.fragments.len() {
-            // nothing to remove
+            // nothing to replace
             return false;
         }
-        self.fragments.remove(at);
+        let mut result = String::new();
+
+        for (index, fragment) in self.fragments.iter().enumerate() {
+            if index == at {
+                result.push(ch);
+            } else {
+                result.push_str(&fragment.grapheme);
+            }
+        }
+
+        self.fragments = Self::str_to_fragments(&result);
+        self.invalidate();
         true
     }
 
+    /// Split the line at grapheme index `at`, keeping the head and
+    /// returning the tail.
+    pub fn split_off(&mut self, at: usize) -> Self {
+        let at = at.min(self.fragments.len());
+        self.invalidate();
+        Self::from_fragments(self.fragments.split_off(at))
+    }
+
+    pub fn append(&mut self, other: &Self) {
+        let Some((first, rest)) = other.fragments.split_first() else {
+            return;
+        };
+        self.invalidate();
+        // regional indicators pair up by their count from the start of a
+        // run, so a flag at the seam can shift every pairing after it
+        let is_flag = |fragment: &TextFragment| {
+            fragment
+                .grapheme
+                .starts_with(|ch| ('\u{1F1E6}'..='\u{1F1FF}').contains(&ch))
+        };
+        if self.fragments.last().is_some_and(is_flag) && is_flag(first) {
+            let text = format!("{self}{other}");
+            self.fragments = Self::str_to_fragments(&text);
+            return;
+        }
+        // otherwise only the seam needs re-segmenting, it may join into one
+        // grapheme
+        let seam = match self.fragments.pop() {
+            Some(last) => format!("{}{}", last.grapheme, first.grapheme),
+            None => first.grapheme.clone(),
+        };
+        self.fragments.extend(Self::str_to_fragments(&seam));
+        self.fragments.extend_from_slice(rest);
+    }
+
+    /// Remove the grapheme at `at`, returning it.
+    pub fn delete(&mut self, at: usize) -> Option<TextFragment> {
+        if at >= self.fragments.len() {
+            // nothing to remove
+            return None;
+        }
+        let fragment = self.fragments.remove(at);
+        self.invalidate();
+        Some(fragment)
+    }
+
     fn str_to_fragments(line_str: &str) -> Vec<TextFragment> {
         line_str
             .graphemes(true)
             .map(|grapheme| {
-                let (replacement, rendered_width) = Self::replacement_character(grapheme)
-                    .map_or_else(
-                        || {
-                            let unicode_width = grapheme.width();
-                            let rendered_width = match unicode_width {
-                                0 | 1 => GraphemeWidth::Half,
-                                _ => GraphemeWidth::Full,
-                            };
-                            (None, rendered_width)
-                        },
-                        |replacement| (Some(replacement), GraphemeWidth::Half),
-                    );
+                let (replacement, rendered_width) =
+                    if let Some(caret) = Self::caret_notation(grapheme) {
+                        (Some(caret), GraphemeWidth::Full)
+                    } else {
+                        Self::replacement_character(grapheme).map_or_else(
+                            || {
+                                let unicode_width = grapheme.width();
+                                let rendered_width = match unicode_width {
+                                    0 | 1 => GraphemeWidth::Half,
+                                    _ => GraphemeWidth::Full,
+                                };
+                                (None, rendered_width)
+                            },
+                            |replacement| (Some(replacement.to_string()), GraphemeWidth::Half),
+                        )
+                    };
 
                 TextFragment {
                     grapheme: grapheme.to_string(),
@@ -86,6 +205,19 @@ impl Line {
             .collect()
     }
 
+    /// Render ASCII control characters other than tab in caret notation,
+    /// e.g. `^M` for a carriage return, whatever width they report.
+    fn caret_notation(for_str: &str) -> Option<String> {
+        let mut chars = for_str.chars();
+        let ch = chars.next()?;
+        if chars.next().is_some() || ch == '\t' || !ch.is_ascii_control() {
+            return None;
+        }
+        // DEL is shown as ^?, the rest map onto @, A-Z, [, \, ], ^ and _
+        let symbol = char::from(u8::try_from(ch).ok()? ^ 0x40);
+        Some(format!("^{symbol}"))
+    }
+
     fn replacement_character(for_str: &str) -> Option<char> {
         let width = for_str.width();
         match for_str {
@@ -106,29 +238,66 @@ impl Line {
         }
     }
 
+    #[cfg(test)]
     pub fn get(&self, range: Range<usize>) -> String {
+        self.get_with(range, Glyphs::Unicode)
+    }
+
+    /// The text shown in the grid columns `range`, drawing replacements
+    /// from `glyphs`.
+    pub fn get_with(&self, range: Range<usize>, glyphs: Glyphs) -> String {
+        if let Some((cached_range, cached_glyphs, text)) = &*self.rendered.borrow()
+            && *cached_range == range
+            && *cached_glyphs == glyphs
+        {
+            return text.clone();
+        }
+        let text = self.render(range.clone(), glyphs);
+        *self.rendered.borrow_mut() = Some((range, glyphs, text.clone()));
+        text
+    }
+
+    fn starts(&self) -> Ref<'_, Vec<usize>> {
+        if self.starts.borrow().is_none() {
+            let starts = std::iter::once(0)
+                .chain(self.fragments.iter().scan(0, |pos, fragment| {
+                    *pos += fragment.rendered_width.width();
+                    Some(*pos)
+                }))
+                .collect();
+            *self.starts.borrow_mut() = Some(starts);
+        }
+        Ref::map(self.starts.borrow(), |starts| {
+            starts.as_ref().expect("starts were just built")
+        })
+    }
+
+    // skips the fragments left of the window with a binary search, so a
+    // long line scrolled far right costs the window, not the whole line
+    fn render(&self, range: Range<usize>, glyphs: Glyphs) -> String {
+        let first = self.starts()[1..].partition_point(|&end| end <= range.start);
+        self.render_from(first, range, glyphs)
+    }
+
+    fn render_from(&self, first: usize, range: Range<usize>, glyphs: Glyphs) -> String {
         use std::ops::ControlFlow::{Break, Continue};
 
-        let result = self
-            .fragments
+        let starts = self.starts();
+        let result = self.fragments[first..]
             .iter()
-            .scan(0, |pos, fragment| {
-                let start = *pos;
-                let end = start + fragment.rendered_width.width();
-                *pos = end;
-                Some((start, end, fragment))
-            })
+            .zip(starts[first..].windows(2))
+            .map(|(fragment, bounds)| (bounds[0], bounds[1], fragment))
             .try_fold(String::new(), |mut acc, (start, end, fragment)| {
                 if end <= range.start {
                     Continue(acc)
                 } else if start >= range.end {
                     Break(acc)
                 } else if start < range.start || end > range.end {
-                    acc.push('⋯');
+                    acc.push(glyphs.clipped());
                     Break(acc)
                 } else {
-                    match fragment.replacement {
-                        Some(c) => acc.push(c),
+                    match &fragment.replacement {
+                        Some(replacement) => acc.push_str(glyphs.substitute(replacement)),
                         None => acc.push_str(fragment.grapheme.as_str()),
                     }
                     Continue(acc)
@@ -140,19 +309,338 @@ impl Line {
         }
     }
 
+    /// The graphemes in `range` (by grapheme index) as written in the
+    /// file, unlike `get`, which takes grid columns and substitutes
+    /// replacement glyphs.
+    pub fn text(&self, range: Range<usize>) -> String {
+        let end = range.end.min(self.fragments.len());
+        let start = range.start.min(end);
+        self.fragments[start..end]
+            .iter()
+            .map(|fragment| fragment.grapheme.as_str())
+            .collect()
+    }
+
+    /// The integer the grapheme index `at` is on, or else the first one
+    /// after it, as its grapheme range (including a minus sign) and value.
+    pub fn number_span_at(&self, at: usize) -> Option<(Range<usize>, i64)> {
+        let is_digit = |index: usize| {
+            self.grapheme_at(index).is_some_and(|grapheme| {
+                grapheme.len() == 1 && grapheme.as_bytes()[0].is_ascii_digit()
+            })
+        };
+        let mut start = if is_digit(at) {
+            (0..at)
+                .rev()
+                .take_while(|&index| is_digit(index))
+                .last()
+                .unwrap_or(at)
+        } else {
+            (at..self.len()).find(|&index| is_digit(index))?
+        };
+        let end = (start..self.len())
+            .find(|&index| !is_digit(index))
+            .unwrap_or(self.len());
+        if start > 0 && self.grapheme_at(start - 1) == Some("-") {
+            start -= 1;
+        }
+        let value = self.text(start..end).parse().ok()?;
+        Some((start..end, value))
+    }
+
+    /// The grapheme at grapheme index `at`, as written in the file.
+    pub fn grapheme_at(&self, at: usize) -> Option<&str> {
+        self.fragments
+            .get(at)
+            .map(|fragment| fragment.grapheme.as_str())
+    }
+
+    /// Find the grapheme index of the first occurrence of `ch` at or
+    /// after grapheme index `from`.
+    pub fn find_char_from(&self, ch: char, from: usize) -> Option<usize> {
+        let mut buf = [0; 4];
+        let needle: &str = ch.encode_utf8(&mut buf);
+        self.fragments
+            .iter()
+            .enumerate()
+            .skip(from)
+            .find(|(_, fragment)| fragment.grapheme == needle)
+            .map(|(index, _)| index)
+    }
+
+    /// Find the grapheme index of the first match of `query` starting at
+    /// or after grapheme index `from`.
+    pub fn find(&self, query: &str, from: usize, options: SearchOptions) -> Option<usize> {
+        let needle: Vec<&str> = query.graphemes(true).collect();
+        if needle.is_empty() || needle.len() > self.fragments.len() {
+            return None;
+        }
+        (from..=self.fragments.len() - needle.len()).find(|&start| {
+            let candidate = &self.fragments[start..start + needle.len()];
+            candidate.iter().zip(&needle).all(|(fragment, grapheme)| {
+                Self::graphemes_match(&fragment.grapheme, grapheme, options.match_case)
+            }) && (!options.whole_word || self.is_word_boundary(start, start + needle.len()))
+        })
+    }
+
+    fn graphemes_match(left: &str, right: &str, match_case: bool) -> bool {
+        if match_case {
+            left == right
+        } else {
+            left.to_lowercase() == right.to_lowercase()
+        }
+    }
+
+    /// Whether the graphemes directly around `start..end` are not part of
+    /// the same word as the range itself.
+    fn is_word_boundary(&self, start: usize, end: usize) -> bool {
+        let is_word_at = |index: usize| {
+            self.fragments
+                .get(index)
+                .is_some_and(|fragment| Self::is_word_grapheme(&fragment.grapheme))
+        };
+        let starts_word = start == 0 || !is_word_at(start - 1) || !is_word_at(start);
+        let ends_word = !is_word_at(end) || !is_word_at(end - 1);
+        starts_word && ends_word
+    }
+
+    /// The grapheme range of the run around index `at` of word
+    /// graphemes, whitespace, or other symbols, whichever `at` is on.
+    /// Empty past the end of the line.
+    pub fn word_range_at(&self, at: usize) -> Range<usize> {
+        let class_at = |index: usize| {
+            self.grapheme_at(index).map(|grapheme| {
+                if Self::is_word_grapheme(grapheme) {
+                    0
+                } else if grapheme.trim().is_empty() {
+                    1
+                } else {
+                    2
+                }
+            })
+        };
+        let Some(class) = class_at(at) else {
+            return at..at;
+        };
+        let start = (0..at)
+            .rev()
+            .take_while(|&index| class_at(index) == Some(class))
+            .last()
+            .unwrap_or(at);
+        let end = (at..self.len())
+            .find(|&index| class_at(index) != Some(class))
+            .unwrap_or(self.len());
+        start..end
+    }
+
+    /// The start of the first word or symbol run after the one `at` is
+    /// in, skipping whitespace; `None` if the line has no further one.
+    pub fn next_word_start(&self, at: usize) -> Option<usize> {
+        let after = if self.is_blank_at(at) {
+            at
+        } else {
+            self.word_range_at(at).end
+        };
+        (after..self.len()).find(|&index| !self.is_blank_at(index))
+    }
+
+    /// The start of the first word or symbol run in the line.
+    pub fn first_word_start(&self) -> Option<usize> {
+        (0..self.len()).find(|&index| !self.is_blank_at(index))
+    }
+
+    /// The start of the last word or symbol run beginning before `at`;
+    /// `None` if there is only whitespace before it.
+    pub fn prev_word_start(&self, at: usize) -> Option<usize> {
+        (0..at.min(self.len()))
+            .rev()
+            .find(|&index| !self.is_blank_at(index))
+            .map(|index| self.word_range_at(index).start)
+    }
+
+    // past the end counts as blank
+    fn is_blank_at(&self, at: usize) -> bool {
+        self.grapheme_at(at)
+            .is_none_or(|grapheme| grapheme.trim().is_empty())
+    }
+
+    /// The closing brackets, innermost first, for the brackets left open
+    /// before grapheme index `at`. Brackets inside double-quoted strings
+    /// don't count, and a stray closing bracket is ignored.
+    pub fn missing_closers(&self, at: usize) -> String {
+        let mut open = Vec::new();
+        let mut in_string = false;
+        let mut escaped = false;
+        for index in 0..at.min(self.len()) {
+            let Some(grapheme) = self.grapheme_at(index) else {
+                break;
+            };
+            if in_string {
+                match grapheme {
+                    _ if escaped => escaped = false,
+                    "\\" => escaped = true,
+                    "\"" => in_string = false,
+                    _ => {}
+                }
+                continue;
+            }
+            match grapheme {
+                "\"" => in_string = true,
+                "(" => open.push(')'),
+                "[" => open.push(']'),
+                "{" => open.push('}'),
+                ")" | "]" | "}"
+                    if open
+                        .last()
+                        .is_some_and(|close| grapheme.starts_with(*close)) =>
+                {
+                    open.pop();
+                }
+                _ => {}
+            }
+        }
+        open.iter().rev().collect()
+    }
+
+    /// The grapheme index at grid column `col`; a column inside a wide
+    /// grapheme maps to that grapheme, one past the end to the line end.
+    pub fn index_at_column(&self, col: usize) -> usize {
+        let mut width = 0;
+        for (index, fragment) in self.fragments.iter().enumerate() {
+            width += fragment.rendered_width.width();
+            if width > col {
+                return index;
+            }
+        }
+        self.len()
+    }
+
+    /// The graphemes reaching past grid column `max_width`, if any.
+    pub fn overflow(&self, max_width: usize) -> Option<Range<usize>> {
+        let start = self.index_at_column(max_width);
+        (start < self.len()).then_some(start..self.len())
+    }
+
+    fn is_word_grapheme(grapheme: &str) -> bool {
+        grapheme
+            .chars()
+            .next()
+            .is_some_and(|ch| ch.is_alphanumeric() || ch == '_')
+    }
+
+    /// Whether the line reads right to left, judged by its first strongly
+    /// directional grapheme. Lines are still laid out left to right; this
+    /// only lets the editor point out that the display may be off.
+    pub fn is_rtl(&self) -> bool {
+        self.fragments
+            .iter()
+            .filter_map(|fragment| fragment.grapheme.chars().next())
+            .find_map(|ch| {
+                if is_rtl_char(ch) {
+                    Some(true)
+                } else {
+                    ch.is_alphabetic().then_some(false)
+                }
+            })
+            .unwrap_or(false)
+    }
+
+    /// Whether the line is empty or consists only of whitespace.
+    pub fn is_blank(&self) -> bool {
+        self.fragments
+            .iter()
+            .all(|fragment| fragment.grapheme.trim().is_empty())
+    }
+
+    /// The display width of the leading whitespace, with tab stops every
+    /// `tab_width` columns.
+    pub fn indent_width(&self, tab_width: usize) -> usize {
+        let tab_width = tab_width.max(1);
+        self.fragments
+            .iter()
+            .map(|fragment| fragment.grapheme.as_str())
+            .take_while(|grapheme| grapheme.trim().is_empty())
+            .fold(0, |column, grapheme| {
+                if grapheme == "\t" {
+                    (column / tab_width + 1) * tab_width
+                } else {
+                    column + grapheme.width()
+                }
+            })
+    }
+
+    /// The grapheme range of trailing spaces and tabs, if any.
+    pub fn trailing_whitespace(&self) -> Option<Range<usize>> {
+        let content_len = self
+            .fragments
+            .iter()
+            .rposition(|fragment| !matches!(fragment.grapheme.as_str(), " " | "\t"))
+            .map_or(0, |index| index + 1);
+        (content_len < self.fragments.len()).then_some(content_len..self.fragments.len())
+    }
+
     pub fn len(&self) -> usize {
         self.fragments.len()
     }
 
+    /// The length of the line's text in bytes.
+    pub fn byte_len(&self) -> usize {
+        self.fragments
+            .iter()
+            .map(|fragment| fragment.grapheme.len())
+            .sum()
+    }
+
+    /// The total rendered width of the line.
+    pub fn width(&self) -> usize {
+        self.position_of(self.len())
+    }
+
     pub fn position_of(&self, grapheme: usize) -> usize {
-        let mut width = 0;
-        for fragment in self.fragments.iter().take(grapheme) {
-            width += fragment.rendered_width.width();
+        self.starts()[grapheme.min(self.fragments.len())]
+    }
+}
+
+// lines are equal by their text alone, whatever has been cached
+impl PartialEq for Line {
+    fn eq(&self, other: &Self) -> bool {
+        self.fragments == other.fragments
+    }
+}
+
+// hashes like the line's text as a `str`, without building it
+impl Hash for Line {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for fragment in &self.fragments {
+            state.write(fragment.grapheme.as_bytes());
         }
-        width
+        state.write_u8(0xff);
     }
 }
 
+impl Display for Line {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for fragment in &self.fragments {
+            write!(f, "{}", fragment.grapheme)?;
+        }
+        Ok(())
+    }
+}
+
+// letters of the scripts written right to left: Hebrew, Arabic, Syriac,
+// Thaana, N'Ko and their presentation forms, which stop short of the
+// byte order mark at U+FEFF
+fn is_rtl_char(ch: char) -> bool {
+    matches!(
+        ch,
+        '\u{0590}'..='\u{08FF}'
+            | '\u{FB1D}'..='\u{FDFF}'
+            | '\u{FE70}'..='\u{FEFC}'
+            | '\u{10800}'..='\u{10FFF}'
+            | '\u{1E800}'..='\u{1EFFF}'
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -178,6 +666,12 @@ mod tests {
         assert_eq!(line.get(14..16), "");
     }
 
+    #[test]
+    fn display_keeps_original_graphemes() {
+        let line = Line::from("a\t👋\u{200B}b");
+        assert_eq!(line.to_string(), "a\t👋\u{200B}b");
+    }
+
     #[test]
     fn test_len() {
         let line = Line::from("Hello, world!");
@@ -190,6 +684,28 @@ mod tests {
         assert_eq!(line.get(0..1), "·");
     }
 
+    #[test]
+    fn carriage_return_rendered_in_caret_notation() {
+        let line = Line::from("a\rb");
+        assert_eq!(line.width(), 4);
+        assert_eq!(line.get(0..4), "a^Mb");
+        assert_eq!(line.to_string(), "a\rb");
+    }
+
+    #[test]
+    fn other_controls_rendered_in_caret_notation() {
+        assert_eq!(Line::from("\u{1b}").get(0..2), "^[");
+        assert_eq!(Line::from("\u{0}").get(0..2), "^@");
+        assert_eq!(Line::from("\u{7f}").get(0..2), "^?");
+    }
+
+    #[test]
+    fn ordinary_text_not_replaced() {
+        let line = Line::from("a\tb c");
+        assert_eq!(line.width(), 5);
+        assert_eq!(line.get(0..5), "a b c");
+    }
+
     #[test]
     fn wide_char_truncated_shows_ellipsis() {
         let line = Line::from("👋");
@@ -239,10 +755,160 @@ mod tests {
         assert_eq!(line.get(0..full_width), "a👋b");
     }
 
+    #[test]
+    fn replace_wide_grapheme_with_narrow() {
+        let mut line = Line::from("a👋b");
+        assert!(line.replace(1, 'x'));
+        let full_width = line.position_of(line.len());
+        assert_eq!(full_width, 3);
+        assert_eq!(line.get(0..full_width), "axb");
+    }
+
+    #[test]
+    fn replace_beyond_end_noop() {
+        let mut line = Line::from("Hello");
+        assert!(!line.replace(5, 'X'));
+        assert_eq!(line.get(0..5), "Hello");
+    }
+
+    #[test]
+    fn find_char_from_finds_next_occurrence() {
+        let line = Line::from("abcabc");
+        assert_eq!(line.find_char_from('b', 0), Some(1));
+        assert_eq!(line.find_char_from('b', 2), Some(4));
+    }
+
+    #[test]
+    fn find_char_from_without_occurrence() {
+        let line = Line::from("abcabc");
+        assert_eq!(line.find_char_from('x', 0), None);
+        assert_eq!(line.find_char_from('a', 4), None);
+    }
+
+    #[test]
+    fn find_char_from_multibyte_target() {
+        let line = Line::from("a👋bé👋");
+        assert_eq!(line.find_char_from('👋', 2), Some(4));
+        assert_eq!(line.find_char_from('é', 0), Some(3));
+    }
+
+    #[test]
+    fn find_from_position() {
+        let line = Line::from("abcabc");
+        let options = SearchOptions::default();
+        assert_eq!(line.find("bc", 0, options), Some(1));
+        assert_eq!(line.find("bc", 2, options), Some(4));
+        assert_eq!(line.find("bc", 5, options), None);
+        assert_eq!(line.find("", 0, options), None);
+    }
+
+    #[test]
+    fn find_case_insensitive() {
+        let line = Line::from("Hello World");
+        let insensitive = SearchOptions::default();
+        let sensitive = SearchOptions {
+            match_case: true,
+            ..SearchOptions::default()
+        };
+        assert_eq!(line.find("world", 0, insensitive), Some(6));
+        assert_eq!(line.find("world", 0, sensitive), None);
+        assert_eq!(line.find("World", 0, sensitive), Some(6));
+    }
+
+    #[test]
+    fn find_whole_word_rejects_substring() {
+        let line = Line::from("scatter cat");
+        let options = SearchOptions {
+            whole_word: true,
+            ..SearchOptions::default()
+        };
+        assert_eq!(line.find("cat", 0, SearchOptions::default()), Some(1));
+        assert_eq!(line.find("cat", 0, options), Some(8));
+        assert_eq!(line.find("catter", 0, options), None);
+    }
+
+    #[test]
+    fn is_blank_on_empty_line() {
+        assert!(Line::from("").is_blank());
+    }
+
+    #[test]
+    fn is_blank_on_whitespace_only_line() {
+        assert!(Line::from("  \t ").is_blank());
+    }
+
+    #[test]
+    fn is_blank_on_content_line() {
+        assert!(!Line::from("  x ").is_blank());
+    }
+
+    #[test]
+    fn indent_width_of_spaces() {
+        assert_eq!(Line::from("    x").indent_width(8), 4);
+        assert_eq!(Line::from("x  ").indent_width(8), 0);
+    }
+
+    #[test]
+    fn indent_width_of_tabs_depends_on_tab_width() {
+        let line = Line::from("\t\tx");
+        assert_eq!(line.indent_width(4), 8);
+        assert_eq!(line.indent_width(8), 16);
+    }
+
+    #[test]
+    fn indent_width_of_mixed_indent() {
+        // the tab runs to the next stop after the spaces
+        assert_eq!(Line::from("  \tx").indent_width(4), 4);
+        assert_eq!(Line::from("\t  x").indent_width(4), 6);
+        assert_eq!(Line::from("    \tx").indent_width(4), 8);
+    }
+
+    #[test]
+    fn trailing_whitespace_range() {
+        let line = Line::from("let a = 1;  \t ");
+        assert_eq!(line.trailing_whitespace(), Some(10..14));
+    }
+
+    #[test]
+    fn trailing_whitespace_none_without_trailing_blanks() {
+        assert_eq!(Line::from("  indented").trailing_whitespace(), None);
+        assert_eq!(Line::from("").trailing_whitespace(), None);
+    }
+
+    #[test]
+    fn trailing_whitespace_covers_blank_line() {
+        assert_eq!(Line::from(" \t ").trailing_whitespace(), Some(0..3));
+    }
+
+    #[test]
+    fn split_off_in_middle() {
+        let mut line = Line::from("Hel👋lo");
+        let tail = line.split_off(3);
+        assert_eq!(line.to_string(), "Hel");
+        assert_eq!(tail.to_string(), "👋lo");
+        assert_eq!(tail.width(), 4);
+    }
+
+    #[test]
+    fn split_off_beyond_end_returns_empty_tail() {
+        let mut line = Line::from("Hello");
+        let tail = line.split_off(100);
+        assert_eq!(line.to_string(), "Hello");
+        assert_eq!(tail.len(), 0);
+    }
+
+    #[test]
+    fn append_joins_lines() {
+        let mut line = Line::from("Hel");
+        line.append(&Line::from("lo"));
+        assert_eq!(line.to_string(), "Hello");
+        assert_eq!(line.len(), 5);
+    }
+
     #[test]
     fn delete_at_start() {
         let mut line = Line::from("Hello");
-        assert!(line.delete(0));
+        assert!(line.delete(0).is_some());
         let full_width = line.position_of(line.len());
         assert_eq!(line.get(0..full_width), "ello");
         assert_eq!(line.len(), 4);
@@ -251,7 +917,7 @@ mod tests {
     #[test]
     fn delete_in_middle() {
         let mut line = Line::from("Hxllo");
-        assert!(line.delete(2));
+        assert!(line.delete(2).is_some());
         let full_width = line.position_of(line.len());
         assert_eq!(line.get(0..full_width), "Hxlo");
         assert_eq!(line.len(), 4);
@@ -261,7 +927,7 @@ mod tests {
     fn delete_at_end() {
         let mut line = Line::from("Hello!");
         let last = line.len() - 1;
-        assert!(line.delete(last));
+        assert!(line.delete(last).is_some());
         let full_width = line.position_of(line.len());
         assert_eq!(line.get(0..full_width), "Hello");
         assert_eq!(line.len(), 5);
@@ -270,7 +936,7 @@ mod tests {
     #[test]
     fn delete_beyond_end_noop() {
         let mut line = Line::from("Hello");
-        assert!(!line.delete(100));
+        assert!(line.delete(100).is_none());
         let full_width = line.position_of(line.len());
         assert_eq!(line.get(0..full_width), "Hello");
         assert_eq!(line.len(), 5);
@@ -285,7 +951,7 @@ mod tests {
         let full_width_before = line.position_of(line.len());
         assert_eq!(full_width_before, 4);
 
-        assert!(line.delete(1)); // remove the 👋
+        assert!(line.delete(1).is_some()); // remove the 👋
 
         // After delete, width should drop to 2 and content be "ab"
         let full_width_after = line.position_of(line.len());
@@ -297,8 +963,177 @@ mod tests {
     #[test]
     fn delete_on_empty_line_noop() {
         let mut line = Line::from("");
-        assert!(!line.delete(0));
+        assert!(line.delete(0).is_none());
         assert_eq!(line.len(), 0);
         assert_eq!(line.get(0..0), "");
     }
+
+    #[test]
+    fn ascii_glyphs_replace_unicode_symbols() {
+        let line = Line::from("a\u{200B}b");
+        assert_eq!(line.get_with(0..3, Glyphs::Ascii), "a.b");
+        assert_eq!(line.get_with(0..3, Glyphs::Unicode), "a·b");
+
+        let line = Line::from("a👋b");
+        assert_eq!(line.get_with(0..2, Glyphs::Ascii), "a>");
+        assert_eq!(line.get_with(0..2, Glyphs::Unicode), "a⋯");
+    }
+
+    #[test]
+    fn number_span_at_finds_number_on_or_after_index() {
+        let line = Line::from("x = -42, y = 7");
+        assert_eq!(line.number_span_at(0), Some((4..7, -42)));
+        assert_eq!(line.number_span_at(6), Some((4..7, -42)));
+        assert_eq!(line.number_span_at(7), Some((13..14, 7)));
+        assert_eq!(line.number_span_at(14), None);
+        assert_eq!(Line::from("no digits").number_span_at(0), None);
+    }
+
+    #[test]
+    fn word_starts_skip_whitespace_and_symbols_runs() {
+        let line = Line::from("let  foo = bar;");
+        assert_eq!(line.next_word_start(1), Some(5));
+        assert_eq!(line.next_word_start(3), Some(5));
+        assert_eq!(line.next_word_start(5), Some(9));
+        assert_eq!(line.next_word_start(14), None);
+        assert_eq!(line.prev_word_start(7), Some(5));
+        assert_eq!(line.prev_word_start(5), Some(0));
+        assert_eq!(line.prev_word_start(0), None);
+        assert_eq!(Line::from("   ").prev_word_start(3), None);
+        assert_eq!(line.first_word_start(), Some(0));
+        assert_eq!(Line::from("  ;").first_word_start(), Some(2));
+    }
+
+    #[test]
+    fn missing_closers_in_nested_order() {
+        assert_eq!(Line::from("foo(bar[").missing_closers(8), "])");
+        assert_eq!(Line::from("f(a[1], {b}) + g()").missing_closers(18), "");
+        // only what is open before the index counts
+        assert_eq!(Line::from("f(a[1])").missing_closers(4), "])");
+        assert_eq!(Line::from("x) (y").missing_closers(5), ")");
+    }
+
+    #[test]
+    fn missing_closers_skip_strings() {
+        let line = Line::from(r#"print("(\"[", {"#);
+        assert_eq!(line.missing_closers(line.len()), "})");
+    }
+
+    #[test]
+    fn word_range_at_selects_run_of_same_class() {
+        let line = Line::from("let foo_1 = a->b;  x");
+        // inside a word
+        assert_eq!(line.word_range_at(5), 4..9);
+        // on punctuation
+        assert_eq!(line.word_range_at(13), 13..15);
+        // on whitespace
+        assert_eq!(line.word_range_at(18), 17..19);
+        assert_eq!(line.word_range_at(19), 19..20);
+        assert_eq!(line.word_range_at(20), 20..20);
+    }
+
+    #[test]
+    fn index_at_column_maps_into_wide_graphemes() {
+        let line = Line::from("a👋b");
+        assert_eq!(line.index_at_column(0), 0);
+        assert_eq!(line.index_at_column(1), 1);
+        assert_eq!(line.index_at_column(2), 1);
+        assert_eq!(line.index_at_column(3), 2);
+        assert_eq!(line.index_at_column(9), 3);
+    }
+
+    #[test]
+    fn direction_follows_first_strong_grapheme() {
+        assert!(Line::from("שלום עולם").is_rtl());
+        assert!(Line::from("  (1) مرحبا hello").is_rtl());
+        assert!(!Line::from("hello עולם").is_rtl());
+        assert!(!Line::from("123 !?").is_rtl());
+    }
+
+    #[test]
+    fn byte_order_mark_has_no_direction() {
+        assert!(!is_rtl_char('\u{FEFF}'));
+        assert!(!Line::from("\u{FEFF}\u{FEFF}hello").is_rtl());
+        assert!(Line::from("\u{FEFF}\u{FEFC}").is_rtl());
+    }
+
+    #[test]
+    fn overflow_starts_at_limit() {
+        let line = Line::from("abcdefgh");
+        assert_eq!(line.overflow(5), Some(5..8));
+        assert_eq!(line.overflow(8), None);
+        // a wide grapheme straddling the limit overflows
+        assert_eq!(Line::from("abＢc").overflow(3), Some(2..4));
+    }
+
+    #[test]
+    fn split_off_keeps_tail_fragments() {
+        let mut line = Line::from("ab👋\tＢ");
+        let expected = line.fragments[2..].to_vec();
+        let tail = line.split_off(2);
+        assert_eq!(tail.fragments, expected);
+        assert_eq!(line.to_string(), "ab");
+    }
+
+    #[test]
+    fn append_joins_graphemes_at_seam() {
+        let mut line = Line::from("ae");
+        line.append(&Line::from("\u{301}x"));
+        assert_eq!(line.len(), 3);
+        assert_eq!(line.grapheme_at(1), Some("e\u{301}"));
+
+        let mut empty = Line::from("");
+        empty.append(&Line::from("xy"));
+        assert_eq!(empty.to_string(), "xy");
+    }
+
+    #[test]
+    fn hash_matches_the_text() {
+        use std::collections::hash_map::DefaultHasher;
+        let line = Line::from("e\u{301}👋b");
+        let mut from_line = DefaultHasher::new();
+        line.hash(&mut from_line);
+        let mut from_text = DefaultHasher::new();
+        line.to_string().hash(&mut from_text);
+        assert_eq!(from_line.finish(), from_text.finish());
+    }
+
+    #[test]
+    fn append_repairs_flags_across_the_seam() {
+        // a lone indicator, then the flags of Spain and France
+        let mut line = Line::from("a\u{1F1E9}");
+        line.append(&Line::from("\u{1F1EA}\u{1F1F8}\u{1F1EB}\u{1F1F7}"));
+        let text = line.to_string();
+        let expected = Line::from(&text);
+        assert_eq!(line.len(), expected.len());
+        assert_eq!(line.grapheme_at(2), expected.grapheme_at(2));
+        assert_eq!(line.grapheme_at(2), Some("\u{1F1F8}\u{1F1EB}"));
+    }
+
+    #[test]
+    fn rendered_text_is_cached_until_changed() {
+        let mut line = Line::from("hello");
+        assert_eq!(line.get(0..3), "hel");
+        assert!(line.rendered.borrow().is_some());
+        assert_eq!(line.get(0..3), "hel");
+
+        line.insert(0, 'o');
+        assert!(line.rendered.borrow().is_none());
+        assert_eq!(line.get(0..3), "ohe");
+        assert_eq!(line.get(1..4), "hel");
+    }
+
+    #[test]
+    fn windows_of_long_wide_line_match_full_scan() {
+        let line = Line::from(&"ａbｃ\t".repeat(1000));
+        for start in [0, 1, 2, 3, 999, 1000, 1001, 4997, 5998, 6000, 6005] {
+            let range = start..start + 17;
+            assert_eq!(
+                line.render(range.clone(), Glyphs::Unicode),
+                line.render_from(0, range, Glyphs::Unicode),
+                "window at {start}"
+            );
+        }
+        assert_eq!(line.get(2..5), "bｃ");
+    }
 }
@@ -0,0 +1,80 @@
+use super::line::{Glyphs, Line};
+use std::ops::Range;
+
+/// Columns the minimap takes from the right edge of the view.
+pub const WIDTH: usize = 2;
+
+// lines sampled per minimap row to judge its density, so that a frame
+// costs the same however long the file is
+const SAMPLES: usize = 8;
+
+/// How many buffer lines each of `height` minimap rows stands for, so
+/// that `num_lines` lines fit. Short buffers get one line per row.
+pub fn lines_per_row(num_lines: usize, height: usize) -> usize {
+    num_lines.div_ceil(height.max(1)).max(1)
+}
+
+/// The buffer lines minimap row `row` stands for, empty past the end.
+pub fn lines_of_row(row: usize, num_lines: usize, height: usize) -> Range<usize> {
+    let per_row = lines_per_row(num_lines, height);
+    let start = (row * per_row).min(num_lines);
+    start..(start + per_row).min(num_lines)
+}
+
+/// The minimap rows covering the buffer lines `visible`, drawn
+/// brightened to show where the view is.
+pub fn visible_band(visible: &Range<usize>, num_lines: usize, height: usize) -> Range<usize> {
+    let per_row = lines_per_row(num_lines, height);
+    visible.start / per_row..visible.end.div_ceil(per_row)
+}
+
+/// The block drawn for `lines`, shaded by their average width.
+pub fn shade<'a>(lines: impl ExactSizeIterator<Item = &'a Line>, glyphs: Glyphs) -> char {
+    let step = lines.len().div_ceil(SAMPLES).max(1);
+    let (total, count) = lines.step_by(step).fold((0, 0), |(total, count), line| {
+        (total + line.width(), count + 1)
+    });
+    let average = total.checked_div(count).unwrap_or(0);
+    let level = match average {
+        0 => 0,
+        1..20 => 1,
+        20..40 => 2,
+        40..60 => 3,
+        _ => 4,
+    };
+    let blocks = match glyphs {
+        Glyphs::Unicode => [' ', '░', '▒', '▓', '█'],
+        Glyphs::Ascii => [' ', '.', ':', '+', '#'],
+    };
+    blocks[level]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_cover_lines_evenly() {
+        assert_eq!(lines_per_row(100, 10), 10);
+        assert_eq!(lines_per_row(101, 10), 11);
+        assert_eq!(lines_per_row(3, 10), 1);
+        assert_eq!(lines_of_row(2, 100, 10), 20..30);
+        assert_eq!(lines_of_row(9, 101, 10), 99..101);
+        assert_eq!(lines_of_row(5, 3, 10), 3..3);
+    }
+
+    #[test]
+    fn band_covers_visible_lines() {
+        assert_eq!(visible_band(&(0..10), 100, 10), 0..1);
+        assert_eq!(visible_band(&(25..35), 100, 10), 2..4);
+        assert_eq!(visible_band(&(1..3), 3, 10), 1..3);
+    }
+
+    #[test]
+    fn shade_reflects_line_width() {
+        let lines = [Line::from(""), Line::from("")];
+        assert_eq!(shade(lines.iter(), Glyphs::Unicode), ' ');
+        let lines = [Line::from(&"x".repeat(70))];
+        assert_eq!(shade(lines.iter(), Glyphs::Ascii), '#');
+    }
+}
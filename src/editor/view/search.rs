@@ -0,0 +1,34 @@
+use crate::editor::position::Position;
+
+/// Flags controlling how a search query is matched against the buffer.
+#[derive(Copy, Clone, Default, Debug, PartialEq)]
+pub struct SearchOptions {
+    /// Only match when the case of every grapheme agrees.
+    pub match_case: bool,
+    /// Only match when the surrounding graphemes are not word characters.
+    pub whole_word: bool,
+}
+
+/// The 1-based number of the match starting at `at` among `matches`,
+/// which are in document order.
+pub fn match_number(matches: &[Position], at: Position) -> Option<usize> {
+    matches.binary_search(&at).ok().map(|index| index + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn match_number_counts_from_one() {
+        let matches = [
+            Position { col: 3, row: 0 },
+            Position { col: 0, row: 2 },
+            Position { col: 5, row: 2 },
+        ];
+        assert_eq!(match_number(&matches, Position { col: 3, row: 0 }), Some(1));
+        assert_eq!(match_number(&matches, Position { col: 5, row: 2 }), Some(3));
+        assert_eq!(match_number(&matches, Position { col: 1, row: 2 }), None);
+        assert_eq!(match_number(&[], Position::default()), None);
+    }
+}
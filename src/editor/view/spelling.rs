@@ -0,0 +1,65 @@
+use super::line::Line;
+use std::collections::HashSet;
+use std::ops::Range;
+use std::path::Path;
+
+/// A word list to check prose against.
+pub struct Dictionary {
+    // lowercase, so that capitalised words at the start of a sentence
+    // are found too
+    words: HashSet<String>,
+}
+
+impl Dictionary {
+    /// Read a word list with one word per line.
+    pub fn load(path: &Path) -> Result<Self, std::io::Error> {
+        Ok(Self::from_words(std::fs::read_to_string(path)?.lines()))
+    }
+
+    pub fn from_words<'a>(words: impl IntoIterator<Item = &'a str>) -> Self {
+        Self {
+            words: words
+                .into_iter()
+                .map(str::trim)
+                .filter(|word| !word.is_empty())
+                .map(str::to_lowercase)
+                .collect(),
+        }
+    }
+
+    /// The grapheme ranges of the words on `line` missing from the
+    /// dictionary. Only purely alphabetic words are checked, which leaves
+    /// out numbers and identifiers such as `max_len`.
+    pub fn misspelled(&self, line: &Line) -> Vec<Range<usize>> {
+        let mut misspelled = Vec::new();
+        let mut at = 0;
+        while at < line.len() {
+            let range = line.word_range_at(at);
+            at = range.end;
+            let word = line.text(range.clone());
+            if word.chars().all(char::is_alphabetic) && !self.words.contains(&word.to_lowercase()) {
+                misspelled.push(range);
+            }
+        }
+        misspelled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn words_missing_from_dictionary_are_reported() {
+        let dictionary = Dictionary::from_words(["the", "quick", "fox", "jumps"]);
+        let line = Line::from("The quikc fox jumsp, 42 times!");
+        assert_eq!(dictionary.misspelled(&line), vec![4..9, 14..19, 24..29]);
+    }
+
+    #[test]
+    fn identifiers_and_numbers_are_skipped() {
+        let dictionary = Dictionary::from_words(["set"]);
+        let line = Line::from("set max_len to 3");
+        assert_eq!(dictionary.misspelled(&line), vec![12..14]);
+    }
+}
@@ -0,0 +1,372 @@
+use super::line::Line;
+use crate::editor::position::Position;
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+/// Edits of the same coalescing kind less than this far apart, each
+/// continuing where the one before left off, are undone together.
+const GROUP_GAP: Duration = Duration::from_secs(1);
+
+/// The kind of an edit, deciding whether it may join the undo group of
+/// the edit before it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EditKind {
+    /// Typing; a run of inserts is undone as one.
+    Insert,
+    /// Backspace; a run walking left is undone as one.
+    DeleteLeft,
+    /// Delete; a run at the same position is undone as one.
+    DeleteRight,
+    /// Anything else, always a group of its own.
+    Other,
+}
+
+impl EditKind {
+    // whether an edit of this kind with the cursor at `at` continues the
+    // run of the edit before, which had the cursor at `last_at`
+    fn continues(self, last_at: Position, at: Position) -> bool {
+        match self {
+            EditKind::Insert => true,
+            // either one grapheme further left, or onto the end of the
+            // line above after joining the lines
+            EditKind::DeleteLeft => {
+                (at.row == last_at.row && at.col + 1 == last_at.col)
+                    || (at.row + 1 == last_at.row && last_at.col == 0)
+            }
+            EditKind::DeleteRight => at == last_at,
+            EditKind::Other => false,
+        }
+    }
+}
+
+/// The cursor and scroll offset to go back to along with the lines.
+#[derive(Clone, Copy)]
+pub struct Snapshot {
+    pub cursor: Position,
+    pub scroll_offset: Position,
+}
+
+/// One step of undo or redo: put `lines` in place of the lines in `rows`,
+/// then go back to `view`.
+pub struct Restore {
+    pub rows: Range<usize>,
+    pub lines: Vec<Line>,
+    pub view: Snapshot,
+}
+
+// a group of edits as the lines it replaced, from `at`, and how many
+// lines it left in their place
+struct Change {
+    at: usize,
+    lines: Vec<Line>,
+    len: usize,
+    view: Snapshot,
+}
+
+/// Undo and redo history, one line-range change per group of edits.
+#[derive(Default)]
+pub struct UndoStack {
+    // oldest first, so that the oldest groups can be dropped
+    undo: VecDeque<Change>,
+    redo: Vec<Change>,
+    // the most groups kept for undo, `None` for no limit
+    limit: Option<usize>,
+    // kind, cursor position and time of the latest edit, while its group
+    // can still grow
+    open_group: Option<(EditKind, Position, Instant)>,
+    // the lines as they were when the latest group started; the only
+    // copy of the whole buffer, taken on the first edit
+    base: Option<Vec<Line>>,
+    // the view when the latest group started, until the group is closed
+    pending: Option<Snapshot>,
+}
+
+impl UndoStack {
+    /// An empty history keeping at most `limit` groups to undo.
+    pub fn with_limit(limit: Option<usize>) -> Self {
+        Self {
+            limit,
+            ..Self::default()
+        }
+    }
+
+    /// Record that an edit of `kind` is about to happen to `lines`, with
+    /// the view at `view`, at time `now`. Only an edit that starts a new
+    /// group compares the lines with those of the group before.
+    pub fn record(&mut self, kind: EditKind, now: Instant, lines: &[Line], view: Snapshot) {
+        let at = view.cursor;
+        let joins_group = self
+            .open_group
+            .is_some_and(|(last_kind, last_at, last_time)| {
+                kind == last_kind
+                    && kind.continues(last_at, at)
+                    && now.saturating_duration_since(last_time) <= GROUP_GAP
+            });
+        if !joins_group {
+            self.close_group(lines);
+            self.pending = Some(view);
+        }
+        self.open_group = Some((kind, at, now));
+        self.redo.clear();
+    }
+
+    /// Make the next edit start a new group, e.g. after the cursor moved.
+    pub fn break_group(&mut self) {
+        self.open_group = None;
+    }
+
+    /// Step back one group from `lines`. `current` is kept for redo.
+    pub fn undo(&mut self, lines: &[Line], current: Snapshot) -> Option<Restore> {
+        self.close_group(lines);
+        let change = self.undo.pop_back()?;
+        let (restore, inverse) = self.apply(change, current);
+        self.redo.push(inverse);
+        Some(restore)
+    }
+
+    /// Step forward again after an undo. `current` is kept for undo.
+    pub fn redo(&mut self, lines: &[Line], current: Snapshot) -> Option<Restore> {
+        self.close_group(lines);
+        let change = self.redo.pop()?;
+        let (restore, inverse) = self.apply(change, current);
+        self.push_undo(inverse);
+        Some(restore)
+    }
+
+    // bring `base` up to `lines`, keeping the difference as the change of
+    // the latest group; a group that changed nothing is dropped
+    fn close_group(&mut self, lines: &[Line]) {
+        self.open_group = None;
+        let pending = self.pending.take();
+        let Some(base) = self.base.as_mut() else {
+            self.base = Some(lines.to_vec());
+            return;
+        };
+        let prefix = base
+            .iter()
+            .zip(lines)
+            .take_while(|(old, new)| old == new)
+            .count();
+        let suffix = base[prefix..]
+            .iter()
+            .rev()
+            .zip(lines[prefix..].iter().rev())
+            .take_while(|(old, new)| old == new)
+            .count();
+        let len = lines.len() - prefix - suffix;
+        let replaced: Vec<Line> = base
+            .splice(
+                prefix..base.len() - suffix,
+                lines[prefix..prefix + len].iter().cloned(),
+            )
+            .collect();
+        if replaced.is_empty() && len == 0 {
+            return;
+        }
+        if let Some(view) = pending {
+            self.push_undo(Change {
+                at: prefix,
+                lines: replaced,
+                len,
+                view,
+            });
+        }
+    }
+
+    // undo `change` on `base`, returning the step for the buffer and the
+    // change that takes it back to `current`
+    fn apply(&mut self, change: Change, current: Snapshot) -> (Restore, Change) {
+        let rows = change.at..change.at + change.len;
+        let base = self.base.get_or_insert_default();
+        let replaced = base
+            .splice(rows.clone(), change.lines.iter().cloned())
+            .collect();
+        let inverse = Change {
+            at: change.at,
+            lines: replaced,
+            len: change.lines.len(),
+            view: current,
+        };
+        let restore = Restore {
+            rows,
+            lines: change.lines,
+            view: change.view,
+        };
+        (restore, inverse)
+    }
+
+    // add a group, forgetting the oldest one past the limit
+    fn push_undo(&mut self, change: Change) {
+        self.undo.push_back(change);
+        if self.limit.is_some_and(|limit| self.undo.len() > limit) {
+            self.undo.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<Line> {
+        text.split('\n').map(Line::from).collect()
+    }
+
+    fn text(lines: &[Line]) -> String {
+        lines
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn view(col: usize, row: usize) -> Snapshot {
+        Snapshot {
+            cursor: Position { col, row },
+            scroll_offset: Position::default(),
+        }
+    }
+
+    // undo once on `current`, returning whether there was anything to undo
+    fn undo(stack: &mut UndoStack, current: &mut Vec<Line>) -> bool {
+        let Some(restore) = stack.undo(current, view(0, 0)) else {
+            return false;
+        };
+        current.splice(restore.rows, restore.lines);
+        true
+    }
+
+    fn redo(stack: &mut UndoStack, current: &mut Vec<Line>) -> bool {
+        let Some(restore) = stack.redo(current, view(0, 0)) else {
+            return false;
+        };
+        current.splice(restore.rows, restore.lines);
+        true
+    }
+
+    #[test]
+    fn inserts_within_gap_coalesce() {
+        let mut stack = UndoStack::default();
+        let start = Instant::now();
+        stack.record(EditKind::Insert, start, &lines(""), view(0, 0));
+        stack.record(
+            EditKind::Insert,
+            start + GROUP_GAP / 2,
+            &lines("a"),
+            view(0, 0),
+        );
+
+        let mut current = lines("ab");
+        assert!(undo(&mut stack, &mut current));
+        assert_eq!(text(&current), "");
+        assert!(!undo(&mut stack, &mut current));
+    }
+
+    #[test]
+    fn inserts_beyond_gap_start_new_group() {
+        let mut stack = UndoStack::default();
+        let start = Instant::now();
+        stack.record(EditKind::Insert, start, &lines(""), view(0, 0));
+        stack.record(
+            EditKind::Insert,
+            start + GROUP_GAP * 2,
+            &lines("a"),
+            view(0, 0),
+        );
+
+        let mut current = lines("ab");
+        undo(&mut stack, &mut current);
+        assert_eq!(text(&current), "a");
+        undo(&mut stack, &mut current);
+        assert_eq!(text(&current), "");
+    }
+
+    #[test]
+    fn break_group_splits_inserts() {
+        let mut stack = UndoStack::default();
+        let start = Instant::now();
+        stack.record(EditKind::Insert, start, &lines(""), view(0, 0));
+        stack.break_group();
+        stack.record(EditKind::Insert, start, &lines("a"), view(0, 0));
+
+        let mut current = lines("ab");
+        undo(&mut stack, &mut current);
+        assert_eq!(text(&current), "a");
+    }
+
+    #[test]
+    fn redo_reverts_undo_and_new_edit_clears_it() {
+        let mut stack = UndoStack::default();
+        stack.record(EditKind::Other, Instant::now(), &lines(""), view(0, 0));
+        let mut current = lines("a");
+        undo(&mut stack, &mut current);
+        assert!(redo(&mut stack, &mut current));
+        assert_eq!(text(&current), "a");
+
+        undo(&mut stack, &mut current);
+        stack.record(EditKind::Other, Instant::now(), &current, view(0, 0));
+        let mut current = lines("b");
+        assert!(!redo(&mut stack, &mut current));
+    }
+
+    #[test]
+    fn adjacent_backspaces_coalesce() {
+        let mut stack = UndoStack::default();
+        let start = Instant::now();
+        stack.record(EditKind::DeleteLeft, start, &lines("abc"), view(0, 1));
+        stack.record(EditKind::DeleteLeft, start, &lines("ab"), view(3, 0));
+        stack.record(EditKind::DeleteLeft, start, &lines("a"), view(2, 0));
+        // not where the previous backspace left the cursor
+        stack.record(EditKind::DeleteLeft, start, &lines(""), view(5, 0));
+
+        let mut current = lines("x");
+        undo(&mut stack, &mut current);
+        assert_eq!(text(&current), "");
+        undo(&mut stack, &mut current);
+        assert_eq!(text(&current), "abc");
+        assert!(!undo(&mut stack, &mut current));
+    }
+
+    #[test]
+    fn oldest_groups_are_dropped_past_limit() {
+        let mut stack = UndoStack::with_limit(Some(2));
+        for text in ["", "a", "ab"] {
+            stack.record(EditKind::Other, Instant::now(), &lines(text), view(0, 0));
+        }
+
+        let mut current = lines("abc");
+        undo(&mut stack, &mut current);
+        assert_eq!(text(&current), "ab");
+        undo(&mut stack, &mut current);
+        assert_eq!(text(&current), "a");
+        // the group back to "" was dropped
+        assert!(!undo(&mut stack, &mut current));
+    }
+
+    #[test]
+    fn groups_keep_only_the_lines_they_changed() {
+        let mut stack = UndoStack::default();
+        stack.record(
+            EditKind::Other,
+            Instant::now(),
+            &lines("a\nb\nc"),
+            view(1, 1),
+        );
+        let restore = stack.undo(&lines("a\nx\ny\nc"), view(0, 0)).unwrap();
+        assert_eq!(restore.rows, 1..3);
+        assert_eq!(text(&restore.lines), "b");
+        assert_eq!(restore.view.cursor, Position { col: 1, row: 1 });
+    }
+
+    #[test]
+    fn group_without_changes_is_dropped() {
+        let mut stack = UndoStack::default();
+        stack.record(EditKind::Other, Instant::now(), &lines("a"), view(0, 0));
+        stack.record(EditKind::Other, Instant::now(), &lines("a"), view(0, 0));
+        let mut current = lines("b");
+        undo(&mut stack, &mut current);
+        assert_eq!(text(&current), "a");
+        assert!(!undo(&mut stack, &mut current));
+    }
+}